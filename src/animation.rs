@@ -0,0 +1,389 @@
+use crate::{chunks::{fram::{BlendOp, DisposeOp}, Anim, Body, Fram}, color::{ChannelValue, ChannelVariant, Color, ColorList, ColorVariant, ColorVecDataInner, Rgba}, error::InvalidParams, format::ChannelValueType, Head, XZIB};
+
+/// A frame of an animation covering the whole canvas.
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    delay: u32,
+    data: ColorList,
+}
+
+impl AnimationFrame {
+    /// `delay` is in milliseconds.
+    #[inline]
+    pub fn new(delay: u32, data: ColorList) -> Self {
+        Self { delay, data }
+    }
+
+    /// Delay in milliseconds.
+    #[inline]
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+
+    #[inline]
+    pub fn data(&self) -> &ColorList {
+        &self.data
+    }
+
+    #[inline]
+    pub fn into_data(self) -> ColorList {
+        self.data
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Animation {
+    loop_count: u32,
+    width: u32,
+    height: u32,
+    frames: Vec<AnimationFrame>,
+}
+
+impl Animation {
+    /// `loop_count` 0 means loop forever. All frames have to cover the whole
+    /// canvas of `width` x `height`.
+    #[inline]
+    pub fn new(loop_count: u32, width: u32, height: u32, frames: Vec<AnimationFrame>) -> Self {
+        Self { loop_count, width, height, frames }
+    }
+
+    /// How often the animation is played. 0 means forever.
+    #[inline]
+    pub fn loop_count(&self) -> u32 {
+        self.loop_count
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    #[inline]
+    pub fn into_frames(self) -> Vec<AnimationFrame> {
+        self.frames
+    }
+}
+
+impl XZIB {
+    /// Composites the BODY chunk and all FRAM chunks to full canvas RGBA
+    /// frames with the palette applied, converted from the color model defined
+    /// in the header and with straight alpha. Images without an ANIM chunk
+    /// result in a single frame with a delay of 0.
+    pub fn animation(&self) -> Result<Option<Animation>, InvalidParams> {
+        let Some(body) = &self.body else {
+            return Ok(None);
+        };
+
+        let (loop_count, default_delay) = match &self.anim {
+            Some(anim) => (anim.loop_count(), anim.default_delay()),
+            None => (0, 0),
+        };
+
+        let width  = self.head.width();
+        let height = self.head.height();
+
+        let first = self.body_rgb_data(body)?;
+        let mut canvas = blank_canvas(first.channel_value_type(), width as usize * height as usize);
+        draw_frame(&mut canvas, width, &first, 0, 0, width, BlendOp::Source)?;
+
+        let mut frames = Vec::with_capacity(self.frames.len() + 1);
+        frames.push(AnimationFrame::new(default_delay, canvas.clone()));
+
+        for fram in &self.frames {
+            let data = self.body_rgb_data(fram.body())?;
+            let previous = canvas.clone();
+
+            draw_frame(&mut canvas, width, &data, fram.x(), fram.y(), fram.width(), fram.blend_op())?;
+            frames.push(AnimationFrame::new(fram.delay().unwrap_or(default_delay), canvas.clone()));
+
+            match fram.dispose_op() {
+                DisposeOp::None => {}
+                DisposeOp::Background => {
+                    clear_rect(&mut canvas, width, fram.x(), fram.y(), fram.width(), fram.height());
+                }
+                DisposeOp::Previous => {
+                    // drawing the frame only changed its rectangle
+                    canvas = previous;
+                }
+            }
+        }
+
+        Ok(Some(Animation::new(loop_count, width, height, frames)))
+    }
+
+    /// Creates an animated image. The frames have to be in the format defined
+    /// by `head` (or indices if it is indexed) and cover the whole canvas. The
+    /// first frame is stored in the BODY chunk, each further frame only stores
+    /// the rectangle that changed compared to the previous frame.
+    pub fn from_animation(head: Head, animation: Animation) -> Result<Self, InvalidParams> {
+        if animation.width() != head.width() || animation.height() != head.height() {
+            return Err(InvalidParams::with_message(
+                format!("animation size {} x {} doesn't match image size {} x {}",
+                    animation.width(), animation.height(), head.width(), head.height())));
+        }
+
+        let width = head.width();
+        let color_count = width as usize * head.height() as usize;
+        let loop_count = animation.loop_count();
+        let mut frames = animation.into_frames().into_iter();
+
+        let Some(first) = frames.next() else {
+            return Err(InvalidParams::with_message("animation has no frames"));
+        };
+
+        let default_delay = first.delay();
+        let first = first.into_data();
+        if first.len() != color_count {
+            return Err(InvalidParams::with_message(
+                format!("frame 0 needs {color_count} colors, but has: {}", first.len())));
+        }
+
+        let mut xzib = XZIB::new(head);
+        let mut previous = first.clone();
+
+        for (index, frame) in frames.enumerate() {
+            let delay = frame.delay();
+            let data = frame.into_data();
+            if data.len() != color_count {
+                return Err(InvalidParams::with_message(
+                    format!("frame {} needs {color_count} colors, but has: {}", index + 1, data.len())));
+            }
+
+            // unchanged frames still need a rectangle to keep their delay
+            let unchanged = if color_count == 0 { (0, 0, 0, 0) } else { (0, 0, 1, 1) };
+            let (x, y, rect_width, rect_height) = changed_rect(&previous, &data, width)?.unwrap_or(unchanged);
+
+            let mut fram = Fram::new(x, y, rect_width, rect_height, Body::with_data(crop(&data, width, x, y, rect_width, rect_height)));
+            if delay != default_delay {
+                fram.set_delay(Some(delay));
+            }
+            xzib.frames.push(fram);
+
+            previous = data;
+        }
+
+        xzib.anim = Some(Anim::new(loop_count, default_delay));
+        xzib.body = Some(Body::with_data(first));
+
+        Ok(xzib)
+    }
+}
+
+#[inline]
+fn blank_canvas_inner<C: ChannelValue>(len: usize) -> ColorVariant<C, ColorVecDataInner> {
+    ColorVariant::Rgba(vec![Rgba([C::ZERO; 4]); len])
+}
+
+/// Transparent black RGBA colors.
+fn blank_canvas(channel_value_type: ChannelValueType, len: usize) -> ColorList {
+    match channel_value_type {
+        ChannelValueType::U8   => ChannelVariant::U8  (blank_canvas_inner(len)),
+        ChannelValueType::U16  => ChannelVariant::U16 (blank_canvas_inner(len)),
+        ChannelValueType::U32  => ChannelVariant::U32 (blank_canvas_inner(len)),
+        ChannelValueType::U64  => ChannelVariant::U64 (blank_canvas_inner(len)),
+        ChannelValueType::U128 => ChannelVariant::U128(blank_canvas_inner(len)),
+        ChannelValueType::F32  => ChannelVariant::F32 (blank_canvas_inner(len)),
+        ChannelValueType::F64  => ChannelVariant::F64 (blank_canvas_inner(len)),
+    }
+}
+
+/// Straight alpha `source` over `dest`.
+#[inline]
+fn blend_over<C: ChannelValue>(source: Rgba<C>, dest: Rgba<C>) -> Rgba<C> {
+    let Rgba(source) = source;
+    let Rgba(dest) = dest;
+
+    if source[3] == C::MAX_VALUE || dest[3] == C::ZERO {
+        return Rgba(source);
+    }
+
+    if source[3] == C::ZERO {
+        return Rgba(dest);
+    }
+
+    let source_alpha = source[3].as_f64();
+    let dest_alpha = dest[3].as_f64() * (1.0 - source_alpha);
+    let alpha = source_alpha + dest_alpha;
+
+    let mut color = [C::ZERO; 4];
+    for channel in 0..3 {
+        color[channel] = C::from_f64((source[channel].as_f64() * source_alpha + dest[channel].as_f64() * dest_alpha) / alpha);
+    }
+    color[3] = C::from_f64(alpha);
+
+    Rgba(color)
+}
+
+fn draw_frame_inner<C: ChannelValue, Col: Color<C>>(canvas: &mut [Rgba<C>], canvas_width: u32, frame: &[Col], x: u32, y: u32, width: u32, blend_op: BlendOp) {
+    if width == 0 {
+        return;
+    }
+
+    let canvas_width = canvas_width as usize;
+    let x = x as usize;
+    let y = y as usize;
+
+    for (row_index, row) in frame.chunks(width as usize).enumerate() {
+        let offset = (y + row_index) * canvas_width + x;
+        for (dest, color) in canvas[offset..offset + row.len()].iter_mut().zip(row) {
+            *dest = match blend_op {
+                BlendOp::Source => color.to_rgba(),
+                BlendOp::Over   => blend_over(color.to_rgba(), *dest),
+            };
+        }
+    }
+}
+
+#[inline]
+fn draw_frame_variant_inner<C: ChannelValue>(canvas: &mut [Rgba<C>], canvas_width: u32, frame: &ColorVariant<C, ColorVecDataInner>, x: u32, y: u32, width: u32, blend_op: BlendOp) {
+    match frame {
+        ColorVariant::L   (frame) => draw_frame_inner(canvas, canvas_width, frame, x, y, width, blend_op),
+        ColorVariant::La  (frame) => draw_frame_inner(canvas, canvas_width, frame, x, y, width, blend_op),
+        ColorVariant::Rgb (frame) => draw_frame_inner(canvas, canvas_width, frame, x, y, width, blend_op),
+        ColorVariant::Rgba(frame) => draw_frame_inner(canvas, canvas_width, frame, x, y, width, blend_op),
+    }
+}
+
+/// Draws the colors of `frame`, which is `width` colors wide, at `x`, `y` onto
+/// the RGBA `canvas`. The rectangle has to lie within the canvas.
+fn draw_frame(canvas: &mut ColorList, canvas_width: u32, frame: &ColorList, x: u32, y: u32, width: u32, blend_op: BlendOp) -> Result<(), InvalidParams> {
+    match (canvas, frame) {
+        (ChannelVariant::U8  (ColorVariant::Rgba(canvas)), ChannelVariant::U8  (frame)) => draw_frame_variant_inner(canvas, canvas_width, frame, x, y, width, blend_op),
+        (ChannelVariant::U16 (ColorVariant::Rgba(canvas)), ChannelVariant::U16 (frame)) => draw_frame_variant_inner(canvas, canvas_width, frame, x, y, width, blend_op),
+        (ChannelVariant::U32 (ColorVariant::Rgba(canvas)), ChannelVariant::U32 (frame)) => draw_frame_variant_inner(canvas, canvas_width, frame, x, y, width, blend_op),
+        (ChannelVariant::U64 (ColorVariant::Rgba(canvas)), ChannelVariant::U64 (frame)) => draw_frame_variant_inner(canvas, canvas_width, frame, x, y, width, blend_op),
+        (ChannelVariant::U128(ColorVariant::Rgba(canvas)), ChannelVariant::U128(frame)) => draw_frame_variant_inner(canvas, canvas_width, frame, x, y, width, blend_op),
+        (ChannelVariant::F32 (ColorVariant::Rgba(canvas)), ChannelVariant::F32 (frame)) => draw_frame_variant_inner(canvas, canvas_width, frame, x, y, width, blend_op),
+        (ChannelVariant::F64 (ColorVariant::Rgba(canvas)), ChannelVariant::F64 (frame)) => draw_frame_variant_inner(canvas, canvas_width, frame, x, y, width, blend_op),
+        (canvas, frame) => return Err(InvalidParams::with_message(
+            format!("cannot draw {} {} colors onto {} {} canvas",
+                frame.color_type(), frame.channel_value_type(), canvas.color_type(), canvas.channel_value_type()))),
+    }
+
+    Ok(())
+}
+
+fn clear_rect_inner<C: ChannelValue>(canvas: &mut [Rgba<C>], canvas_width: u32, x: u32, y: u32, width: u32, height: u32) {
+    let canvas_width = canvas_width as usize;
+    for row in y as usize..(y + height) as usize {
+        let offset = row * canvas_width + x as usize;
+        canvas[offset..offset + width as usize].fill(Rgba([C::ZERO; 4]));
+    }
+}
+
+/// Sets the rectangle of the RGBA `canvas` to transparent black.
+fn clear_rect(canvas: &mut ColorList, canvas_width: u32, x: u32, y: u32, width: u32, height: u32) {
+    match canvas {
+        ChannelVariant::U8  (ColorVariant::Rgba(canvas)) => clear_rect_inner(canvas, canvas_width, x, y, width, height),
+        ChannelVariant::U16 (ColorVariant::Rgba(canvas)) => clear_rect_inner(canvas, canvas_width, x, y, width, height),
+        ChannelVariant::U32 (ColorVariant::Rgba(canvas)) => clear_rect_inner(canvas, canvas_width, x, y, width, height),
+        ChannelVariant::U64 (ColorVariant::Rgba(canvas)) => clear_rect_inner(canvas, canvas_width, x, y, width, height),
+        ChannelVariant::U128(ColorVariant::Rgba(canvas)) => clear_rect_inner(canvas, canvas_width, x, y, width, height),
+        ChannelVariant::F32 (ColorVariant::Rgba(canvas)) => clear_rect_inner(canvas, canvas_width, x, y, width, height),
+        ChannelVariant::F64 (ColorVariant::Rgba(canvas)) => clear_rect_inner(canvas, canvas_width, x, y, width, height),
+        _ => {}
+    }
+}
+
+fn changed_rect_inner<C: ChannelValue, Col: Color<C>>(previous: &[Col], current: &[Col], width: u32) -> Option<(u32, u32, u32, u32)> {
+    if width == 0 {
+        return None;
+    }
+
+    let width = width as usize;
+    let mut min_x = usize::MAX;
+    let mut min_y = usize::MAX;
+    let mut max_x = 0;
+    let mut max_y = 0;
+
+    for (offset, (previous, current)) in previous.iter().zip(current).enumerate() {
+        if previous.channels() != current.channels() {
+            let x = offset % width;
+            let y = offset / width;
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+
+    if min_x == usize::MAX {
+        return None;
+    }
+
+    Some((min_x as u32, min_y as u32, (max_x - min_x + 1) as u32, (max_y - min_y + 1) as u32))
+}
+
+#[inline]
+fn changed_rect_variant_inner<C: ChannelValue>(previous: &ColorVariant<C, ColorVecDataInner>, current: &ColorVariant<C, ColorVecDataInner>, width: u32) -> Result<Option<(u32, u32, u32, u32)>, InvalidParams> {
+    match (previous, current) {
+        (ColorVariant::L   (previous), ColorVariant::L   (current)) => Ok(changed_rect_inner(previous, current, width)),
+        (ColorVariant::La  (previous), ColorVariant::La  (current)) => Ok(changed_rect_inner(previous, current, width)),
+        (ColorVariant::Rgb (previous), ColorVariant::Rgb (current)) => Ok(changed_rect_inner(previous, current, width)),
+        (ColorVariant::Rgba(previous), ColorVariant::Rgba(current)) => Ok(changed_rect_inner(previous, current, width)),
+        (previous, current) => Err(InvalidParams::with_message(
+            format!("cannot compare {} colors to {} colors", current.color_type(), previous.color_type()))),
+    }
+}
+
+/// The bounding box (x, y, width, height) of all colors that differ between
+/// two images of the same size and format that are `width` colors wide.
+/// `None` if the images are the same.
+pub fn changed_rect(previous: &ColorList, current: &ColorList, width: u32) -> Result<Option<(u32, u32, u32, u32)>, InvalidParams> {
+    match (previous, current) {
+        (ChannelVariant::U8  (previous), ChannelVariant::U8  (current)) => changed_rect_variant_inner(previous, current, width),
+        (ChannelVariant::U16 (previous), ChannelVariant::U16 (current)) => changed_rect_variant_inner(previous, current, width),
+        (ChannelVariant::U32 (previous), ChannelVariant::U32 (current)) => changed_rect_variant_inner(previous, current, width),
+        (ChannelVariant::U64 (previous), ChannelVariant::U64 (current)) => changed_rect_variant_inner(previous, current, width),
+        (ChannelVariant::U128(previous), ChannelVariant::U128(current)) => changed_rect_variant_inner(previous, current, width),
+        (ChannelVariant::F32 (previous), ChannelVariant::F32 (current)) => changed_rect_variant_inner(previous, current, width),
+        (ChannelVariant::F64 (previous), ChannelVariant::F64 (current)) => changed_rect_variant_inner(previous, current, width),
+        (previous, current) => Err(InvalidParams::with_message(
+            format!("cannot compare {} colors to {} colors", current.channel_value_type(), previous.channel_value_type()))),
+    }
+}
+
+#[inline]
+fn crop_inner<C: ChannelValue, Col: Color<C>>(colors: &[Col], colors_width: u32, x: u32, y: u32, width: u32, height: u32) -> Vec<Col> {
+    let colors_width = colors_width as usize;
+    let mut cropped = Vec::with_capacity(width as usize * height as usize);
+    for row in y as usize..(y + height) as usize {
+        let offset = row * colors_width + x as usize;
+        cropped.extend_from_slice(&colors[offset..offset + width as usize]);
+    }
+    cropped
+}
+
+#[inline]
+fn crop_variant_inner<C: ChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>, colors_width: u32, x: u32, y: u32, width: u32, height: u32) -> ColorVariant<C, ColorVecDataInner> {
+    match colors {
+        ColorVariant::L   (colors) => ColorVariant::L   (crop_inner(colors, colors_width, x, y, width, height)),
+        ColorVariant::La  (colors) => ColorVariant::La  (crop_inner(colors, colors_width, x, y, width, height)),
+        ColorVariant::Rgb (colors) => ColorVariant::Rgb (crop_inner(colors, colors_width, x, y, width, height)),
+        ColorVariant::Rgba(colors) => ColorVariant::Rgba(crop_inner(colors, colors_width, x, y, width, height)),
+    }
+}
+
+/// The colors of a rectangle of an image that is `colors_width` colors wide.
+/// The rectangle has to lie within the image.
+pub fn crop(colors: &ColorList, colors_width: u32, x: u32, y: u32, width: u32, height: u32) -> ColorList {
+    match colors {
+        ChannelVariant::U8  (colors) => ChannelVariant::U8  (crop_variant_inner(colors, colors_width, x, y, width, height)),
+        ChannelVariant::U16 (colors) => ChannelVariant::U16 (crop_variant_inner(colors, colors_width, x, y, width, height)),
+        ChannelVariant::U32 (colors) => ChannelVariant::U32 (crop_variant_inner(colors, colors_width, x, y, width, height)),
+        ChannelVariant::U64 (colors) => ChannelVariant::U64 (crop_variant_inner(colors, colors_width, x, y, width, height)),
+        ChannelVariant::U128(colors) => ChannelVariant::U128(crop_variant_inner(colors, colors_width, x, y, width, height)),
+        ChannelVariant::F32 (colors) => ChannelVariant::F32 (crop_variant_inner(colors, colors_width, x, y, width, height)),
+        ChannelVariant::F64 (colors) => ChannelVariant::F64 (crop_variant_inner(colors, colors_width, x, y, width, height)),
+    }
+}
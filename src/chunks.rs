@@ -1,15 +1,33 @@
+pub mod thmb;
+pub mod iccp;
+pub mod colr;
 pub mod indx;
 pub mod meta;
 pub mod xmet;
+pub mod xpkt;
+pub mod exif;
+pub mod anim;
 pub mod body;
+pub mod fram;
+pub mod layr;
+pub mod levl;
 pub mod foot;
 
 use std::io::Write;
 
+pub use thmb::Thmb;
+pub use iccp::Iccp;
+pub use colr::Colr;
 pub use indx::Indx;
 pub use meta::Meta;
 pub use xmet::Xmet;
+pub use xpkt::Xpkt;
+pub use exif::Exif;
+pub use anim::Anim;
 pub use body::Body;
+pub use fram::Fram;
+pub use layr::Layr;
+pub use levl::Levl;
 pub use foot::Foot;
 
 use crate::{error::WriteError, Head};
@@ -0,0 +1,75 @@
+use std::io::Write;
+
+use crate::{error::{ReadError, ReadErrorKind, WriteError}, Head};
+
+use super::ChunkWrite;
+
+/// Animation control. Required if there are any FRAM chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Anim {
+    loop_count: u32,
+    default_delay: u32,
+}
+
+impl Anim {
+    pub const FOURCC: [u8; 4] = *b"ANIM";
+    pub const SIZE: usize = 8;
+
+    /// `loop_count` 0 means loop forever. `default_delay` is in milliseconds.
+    #[inline]
+    pub fn new(loop_count: u32, default_delay: u32) -> Self {
+        Self { loop_count, default_delay }
+    }
+
+    /// How often the animation is played. 0 means forever.
+    #[inline]
+    pub fn loop_count(&self) -> u32 {
+        self.loop_count
+    }
+
+    #[inline]
+    pub fn set_loop_count(&mut self, loop_count: u32) {
+        self.loop_count = loop_count;
+    }
+
+    /// Delay in milliseconds of the frame in the BODY chunk and of FRAM chunks
+    /// that don't define their own delay.
+    #[inline]
+    pub fn default_delay(&self) -> u32 {
+        self.default_delay
+    }
+
+    #[inline]
+    pub fn set_default_delay(&mut self, default_delay: u32) {
+        self.default_delay = default_delay;
+    }
+
+    pub fn read(bytes: &[u8]) -> Result<Self, ReadError> {
+        if bytes.len() < Self::SIZE {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("truncated ANIM chunk: {} < {}", bytes.len(), Self::SIZE)));
+        }
+
+        let loop_count    = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let default_delay = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+
+        Ok(Self { loop_count, default_delay })
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), WriteError> {
+        writer.write_all(&self.loop_count.to_le_bytes())?;
+        writer.write_all(&self.default_delay.to_le_bytes())?;
+
+        Ok(())
+    }
+}
+
+impl ChunkWrite for Anim {
+    const FOURCC: [u8; 4] = Self::FOURCC;
+
+    #[inline]
+    fn write(&self, _head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        self.write(writer)
+    }
+}
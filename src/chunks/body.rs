@@ -1,6 +1,6 @@
 use std::io::Write;
 
-use crate::{color::{read_colors_variant, write_colors_variant, ChannelValue, ChannelVariant, ColorList, ColorVariant, ColorVecDataInner, IntChannelValue, Rgb, Rgba}, error::{ReadError, ReadErrorKind, WriteError, WriteErrorKind}, format::{ChannelValueType, Format}, Head};
+use crate::{color::{read_colors_variant, write_colors_variant, ChannelValue, ChannelVariant, ColorList, ColorVariant, ColorVecDataInner, IntChannelValue, Rgb, Rgba}, error::{ReadError, ReadErrorKind, WriteError, WriteErrorKind}, format::{ChromaSubsampling, Format}, Head, XZIB};
 
 use super::ChunkWrite;
 
@@ -38,13 +38,27 @@ impl Body {
     }
 
     pub fn read(bytes: &[u8], head: &Head) -> Result<Self, ReadError> {
-        if !head.is_interleaved() {
+        if head.is_chroma_subsampled() {
             return Ok(Self {
-                data: read_colors_variant(bytes, head.is_float(), head.planes(), head.channels())?
+                data: read_subsampled_colors(bytes, head)?
             });
         }
 
-        let data = read_interleaved_colors(bytes, head.is_float(), head.planes(), head.channels(), head.width(), head.height())?;
+        // the channels in the header are the channels of the palette
+        let channels = if head.is_indexed() { 1 } else { head.channels() };
+
+        let mut data = if head.is_interleaved() {
+            read_interleaved_colors(bytes, head.body_number_type().is_float(), head.planes(), channels, head.width(), head.height())?
+        } else {
+            let mut data = read_colors_variant(bytes, head.body_number_type().is_float(), head.planes(), channels)?;
+            // the last byte of packed 1 and 4 bit data may contain padding
+            data.truncate(head.width() as usize * head.height() as usize);
+            data
+        };
+
+        if head.is_indexed() {
+            shift_indices_right(&mut data, head.planes());
+        }
 
         Ok(Self {
             data
@@ -52,11 +66,25 @@ impl Body {
     }
 
     pub fn write(&self, head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
-        let channel_value_type = ChannelValueType::from_planes(head.number_type(), head.planes())?;
+        if head.is_chroma_subsampled() {
+            return write_subsampled_colors(&self.data, head, writer);
+        }
+
+        let data_channel_value_type = self.data.channel_value_type();
+        if head.is_indexed() && head.planes() < data_channel_value_type.planes() {
+            let indices = shift_indices_left(&self.data, head.planes());
+            return Body::with_data(indices).write_colors(head, writer);
+        }
+
+        self.write_colors(head, writer)
+    }
+
+    fn write_colors(&self, head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        let channel_value_type = head.channel_value_type()?;
 
         let data_channel_value_type = self.data.channel_value_type();
         if head.is_interleaved() {
-            if data_channel_value_type.number_type() != head.number_type() || data_channel_value_type.planes() < head.planes() {
+            if data_channel_value_type.number_type() != head.body_number_type() || data_channel_value_type.planes() < head.planes() {
                 return Err(WriteError::with_message(
                     WriteErrorKind::InvalidParams,
                     format!("actual channel value type of {} isn't compatible to the channel value type of {} as defined in the header for interleaved files",
@@ -138,7 +166,7 @@ pub fn write_interleaved_colors_varant(data: &ColorList, head: &Head, writer: &m
         ChannelVariant::F64(data) if planes == 64 => write_interleaved_float_colors_variant_inner(data, head, writer)?,
         _ => return Err(WriteError::with_message(
             WriteErrorKind::InvalidParams,
-            format!("unsupported color format: {} {planes}", if head.is_float() { "float" } else { "int" })))
+            format!("unsupported color format: {} {planes}", if head.body_number_type().is_float() { "float" } else { "int" })))
     }
     Ok(())
 }
@@ -180,6 +208,7 @@ where ChannelValue: crate::color::ChannelValue,
                 for x in 0..width {
                     if bit == 8 {
                         writer.write_all(&[byte])?;
+                        byte = 0;
                         bit = 0;
                     }
                     let color_byte = buf[x * color_len + ChannelValue::SIZE as usize * channel + byte_index as usize];
@@ -239,10 +268,11 @@ where ChannelValue: crate::color::IntChannelValue,
                 for color in row {
                     if bit == 8 {
                         writer.write_all(&[byte])?;
+                        byte = 0;
                         bit = 0;
                     }
                     let value = color.channels()[channel] >> shift;
-                    let value = (value >> (ChannelValue::BITS - 1 - plane as u32)).least_significant_byte() & 1u8;
+                    let value = (value >> (planes - 1 - plane) as u32).least_significant_byte() & 1u8;
                     byte |= value << (7 - bit);
                     bit += 1;
                 }
@@ -392,14 +422,13 @@ pub fn read_interleaved_bytes<C: ChannelValue>(bytes: &[u8], planes: u8, channel
 #[inline]
 pub fn read_interleaved_bytes_chunk<C: ChannelValue>(bytes: &[u8], planes: u8, plane_len: usize, x: usize, output: &mut [u8]) -> Option<C> {
     let byte_offset = x / 8;
-    let bit_offset = x % 8;
-    let byte_offset = byte_offset;
+    let bit_offset = 7 - x % 8;
     output.fill(0);
     for plane in 0..planes as usize {
         let output_index = plane / 8;
         let output_bit = plane % 8;
         let plane_index = plane_len * plane;
-        output[output_index] |= (bytes[plane_index + byte_offset] >> bit_offset) << output_bit;
+        output[output_index] |= ((bytes[plane_index + byte_offset] >> bit_offset) & 1) << output_bit;
     }
 
     C::from_bytes(output)
@@ -471,11 +500,11 @@ pub fn read_interleaved_int_colors<C: IntChannelValue>(bytes: &[u8], planes: u8,
 pub fn read_interleaved_int_color<C: IntChannelValue>(bytes: &[u8], planes: u8, plane_len: usize, x: usize) -> C {
     let mut value = C::ZERO;
     let byte_offset = x / 8;
-    let bit_offset = x % 8;
-    let byte_offset = byte_offset;
+    let bit_offset = 7 - x % 8;
     for plane in 0..planes as usize {
         let plane_index = plane_len * plane;
-        value |= ((bytes[plane_index + byte_offset] >> bit_offset) << plane).into();
+        let bit: C = ((bytes[plane_index + byte_offset] >> bit_offset) & 1).into();
+        value |= bit << (planes as usize - 1 - plane);
     }
     value
 }
@@ -544,8 +573,248 @@ pub fn read_1bit_colors(bytes: &[u8], channels: u8, width: u32, height: u32) ->
 #[inline]
 pub fn read_1bit_color(bytes: &[u8], x: usize) -> u8 {
     let byte_offset = x / 8;
-    let bit_offset = x % 8;
-    let byte_offset = byte_offset;
-    let value = bytes[byte_offset] >> bit_offset;
-    value
+    let bit_offset = 7 - x % 8;
+    (bytes[byte_offset] >> bit_offset) & 1
+}
+
+/// Header used to read and write one plane of a chroma subsampled image.
+#[inline]
+fn plane_head(head: &Head, width: u32, height: u32) -> Head {
+    Head {
+        flags: head.flags & !(XZIB::COLOR_MODEL_MASK | XZIB::CHROMA_SUBSAMPLING_MASK),
+        channels: 1,
+        width,
+        height,
+        ..*head
+    }
+}
+
+/// Number of bytes a single channel image of the given size uses in a BODY chunk.
+#[inline]
+fn plane_byte_len(head: &Head, width: u32, height: u32) -> usize {
+    let planes = head.planes() as usize;
+    if head.is_interleaved() {
+        height as usize * (width as usize).div_ceil(8) * planes
+    } else {
+        (width as usize * height as usize * planes).div_ceil(8)
+    }
+}
+
+// Chroma subsampled YCbCr images are stored as three consecutive single channel
+// images: Y in full resolution, followed by Cb and Cr in reduced resolution.
+pub fn read_subsampled_colors(bytes: &[u8], head: &Head) -> Result<ColorList, ReadError> {
+    let chroma_subsampling = match head.chroma_subsampling() {
+        Ok(chroma_subsampling) => chroma_subsampling,
+        Err(err) => return Err(ReadError::with_all(
+            ReadErrorKind::BrokenFile,
+            "illegal header flags",
+            Box::new(err)))
+    };
+
+    if head.channels() != 3 {
+        return Err(ReadError::with_message(
+            ReadErrorKind::BrokenFile,
+            format!("chroma subsampling requires 3 channels, but header defines: {}", head.channels())));
+    }
+
+    let width  = head.width();
+    let height = head.height();
+    let (chroma_width, chroma_height) = chroma_subsampling.chroma_size(width, height);
+
+    let luma_len   = plane_byte_len(head, width, height);
+    let chroma_len = plane_byte_len(head, chroma_width, chroma_height);
+
+    if bytes.len() < luma_len + chroma_len * 2 {
+        return Err(ReadError::with_message(ReadErrorKind::BrokenFile, "truncated BODY chunk"));
+    }
+
+    let luma_head   = plane_head(head, width, height);
+    let chroma_head = plane_head(head, chroma_width, chroma_height);
+
+    let (luma_bytes, bytes) = bytes.split_at(luma_len);
+    let (cb_bytes, bytes) = bytes.split_at(chroma_len);
+    let cr_bytes = &bytes[..chroma_len];
+
+    let luma = Body::read(luma_bytes, &luma_head)?.into_data();
+    let cb   = Body::read(cb_bytes,   &chroma_head)?.into_data();
+    let cr   = Body::read(cr_bytes,   &chroma_head)?.into_data();
+
+    let data = match (luma, cb, cr) {
+        (ChannelVariant::U8  (ColorVariant::L(y)), ChannelVariant::U8  (ColorVariant::L(cb)), ChannelVariant::U8  (ColorVariant::L(cr))) =>
+            ChannelVariant::U8  (ColorVariant::Rgb(merge_chroma(&y, &cb, &cr, width, height, chroma_subsampling)?)),
+        (ChannelVariant::U16 (ColorVariant::L(y)), ChannelVariant::U16 (ColorVariant::L(cb)), ChannelVariant::U16 (ColorVariant::L(cr))) =>
+            ChannelVariant::U16 (ColorVariant::Rgb(merge_chroma(&y, &cb, &cr, width, height, chroma_subsampling)?)),
+        (ChannelVariant::U32 (ColorVariant::L(y)), ChannelVariant::U32 (ColorVariant::L(cb)), ChannelVariant::U32 (ColorVariant::L(cr))) =>
+            ChannelVariant::U32 (ColorVariant::Rgb(merge_chroma(&y, &cb, &cr, width, height, chroma_subsampling)?)),
+        (ChannelVariant::U64 (ColorVariant::L(y)), ChannelVariant::U64 (ColorVariant::L(cb)), ChannelVariant::U64 (ColorVariant::L(cr))) =>
+            ChannelVariant::U64 (ColorVariant::Rgb(merge_chroma(&y, &cb, &cr, width, height, chroma_subsampling)?)),
+        (ChannelVariant::U128(ColorVariant::L(y)), ChannelVariant::U128(ColorVariant::L(cb)), ChannelVariant::U128(ColorVariant::L(cr))) =>
+            ChannelVariant::U128(ColorVariant::Rgb(merge_chroma(&y, &cb, &cr, width, height, chroma_subsampling)?)),
+        (ChannelVariant::F32 (ColorVariant::L(y)), ChannelVariant::F32 (ColorVariant::L(cb)), ChannelVariant::F32 (ColorVariant::L(cr))) =>
+            ChannelVariant::F32 (ColorVariant::Rgb(merge_chroma(&y, &cb, &cr, width, height, chroma_subsampling)?)),
+        (ChannelVariant::F64 (ColorVariant::L(y)), ChannelVariant::F64 (ColorVariant::L(cb)), ChannelVariant::F64 (ColorVariant::L(cr))) =>
+            ChannelVariant::F64 (ColorVariant::Rgb(merge_chroma(&y, &cb, &cr, width, height, chroma_subsampling)?)),
+        _ => return Err(ReadError::with_message(
+            ReadErrorKind::BrokenFile,
+            "inconsistent planes in chroma subsampled BODY chunk"))
+    };
+
+    Ok(data)
+}
+
+pub fn merge_chroma<C: ChannelValue>(luma: &[C], cb: &[C], cr: &[C], width: u32, height: u32, chroma_subsampling: ChromaSubsampling) -> Result<Vec<Rgb<C>>, ReadError> {
+    let width  = width  as usize;
+    let height = height as usize;
+    let (x_factor, y_factor) = chroma_subsampling.factors();
+    let (x_factor, y_factor) = (x_factor as usize, y_factor as usize);
+    let chroma_width  = width.div_ceil(x_factor);
+    let chroma_height = height.div_ceil(y_factor);
+
+    if luma.len() < width * height || cb.len() < chroma_width * chroma_height || cr.len() < chroma_width * chroma_height {
+        return Err(ReadError::with_message(ReadErrorKind::BrokenFile, "truncated BODY chunk"));
+    }
+
+    let mut data = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let chroma_row = (y / y_factor) * chroma_width;
+        for x in 0..width {
+            let chroma_index = chroma_row + x / x_factor;
+            data.push(Rgb([luma[y * width + x], cb[chroma_index], cr[chroma_index]]));
+        }
+    }
+
+    Ok(data)
+}
+
+pub fn write_subsampled_colors(data: &ColorList, head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+    let chroma_subsampling = head.chroma_subsampling()?;
+    let width  = head.width();
+    let height = head.height();
+    let (chroma_width, chroma_height) = chroma_subsampling.chroma_size(width, height);
+
+    let (luma, cb, cr) = match data {
+        ChannelVariant::U8(ColorVariant::Rgb(data)) => {
+            let (y, cb, cr) = split_chroma(data, width, height, chroma_subsampling);
+            (ChannelVariant::U8(ColorVariant::L(y)), ChannelVariant::U8(ColorVariant::L(cb)), ChannelVariant::U8(ColorVariant::L(cr)))
+        }
+        ChannelVariant::U16(ColorVariant::Rgb(data)) => {
+            let (y, cb, cr) = split_chroma(data, width, height, chroma_subsampling);
+            (ChannelVariant::U16(ColorVariant::L(y)), ChannelVariant::U16(ColorVariant::L(cb)), ChannelVariant::U16(ColorVariant::L(cr)))
+        }
+        ChannelVariant::U32(ColorVariant::Rgb(data)) => {
+            let (y, cb, cr) = split_chroma(data, width, height, chroma_subsampling);
+            (ChannelVariant::U32(ColorVariant::L(y)), ChannelVariant::U32(ColorVariant::L(cb)), ChannelVariant::U32(ColorVariant::L(cr)))
+        }
+        ChannelVariant::U64(ColorVariant::Rgb(data)) => {
+            let (y, cb, cr) = split_chroma(data, width, height, chroma_subsampling);
+            (ChannelVariant::U64(ColorVariant::L(y)), ChannelVariant::U64(ColorVariant::L(cb)), ChannelVariant::U64(ColorVariant::L(cr)))
+        }
+        ChannelVariant::U128(ColorVariant::Rgb(data)) => {
+            let (y, cb, cr) = split_chroma(data, width, height, chroma_subsampling);
+            (ChannelVariant::U128(ColorVariant::L(y)), ChannelVariant::U128(ColorVariant::L(cb)), ChannelVariant::U128(ColorVariant::L(cr)))
+        }
+        ChannelVariant::F32(ColorVariant::Rgb(data)) => {
+            let (y, cb, cr) = split_chroma(data, width, height, chroma_subsampling);
+            (ChannelVariant::F32(ColorVariant::L(y)), ChannelVariant::F32(ColorVariant::L(cb)), ChannelVariant::F32(ColorVariant::L(cr)))
+        }
+        ChannelVariant::F64(ColorVariant::Rgb(data)) => {
+            let (y, cb, cr) = split_chroma(data, width, height, chroma_subsampling);
+            (ChannelVariant::F64(ColorVariant::L(y)), ChannelVariant::F64(ColorVariant::L(cb)), ChannelVariant::F64(ColorVariant::L(cr)))
+        }
+        _ => return Err(WriteError::with_message(
+            WriteErrorKind::InvalidParams,
+            format!("chroma subsampling requires RGB (YCbCr) data, but was: {}", data.color_type())))
+    };
+
+    let luma_head   = plane_head(head, width, height);
+    let chroma_head = plane_head(head, chroma_width, chroma_height);
+
+    Body::with_data(luma).write(&luma_head,   writer)?;
+    Body::with_data(cb)  .write(&chroma_head, writer)?;
+    Body::with_data(cr)  .write(&chroma_head, writer)?;
+
+    Ok(())
+}
+
+/// Splits YCbCr colors into the Y, Cb, and Cr planes. The chroma planes are
+/// downsampled by averaging the covered pixels.
+pub fn split_chroma<C: ChannelValue>(data: &[Rgb<C>], width: u32, height: u32, chroma_subsampling: ChromaSubsampling) -> (Vec<C>, Vec<C>, Vec<C>) {
+    let width  = width  as usize;
+    let height = height as usize;
+    let (x_factor, y_factor) = chroma_subsampling.factors();
+    let (x_factor, y_factor) = (x_factor as usize, y_factor as usize);
+    let chroma_width  = width.div_ceil(x_factor);
+    let chroma_height = height.div_ceil(y_factor);
+
+    let luma = data.iter().map(|&Rgb([y, _, _])| y).collect();
+    let mut cb = Vec::with_capacity(chroma_width * chroma_height);
+    let mut cr = Vec::with_capacity(chroma_width * chroma_height);
+
+    for chroma_y in 0..chroma_height {
+        for chroma_x in 0..chroma_width {
+            let mut cb_sum = 0.0;
+            let mut cr_sum = 0.0;
+            let mut count = 0;
+            for y in (chroma_y * y_factor)..((chroma_y + 1) * y_factor).min(height) {
+                for x in (chroma_x * x_factor)..((chroma_x + 1) * x_factor).min(width) {
+                    if let Some(&Rgb([_, pixel_cb, pixel_cr])) = data.get(y * width + x) {
+                        cb_sum += pixel_cb.as_f64();
+                        cr_sum += pixel_cr.as_f64();
+                        count += 1;
+                    }
+                }
+            }
+            let count = count.max(1) as f64;
+            cb.push(C::from_f64(cb_sum / count));
+            cr.push(C::from_f64(cr_sum / count));
+        }
+    }
+
+    (luma, cb, cr)
+}
+
+// Channel values are scaled when written with fewer planes than their type
+// has, but indices must be stored as they are. Therefore they are moved into
+// the most significant bits before writing and back after reading.
+fn shift_indices_left(data: &ColorList, planes: u8) -> ColorList {
+    match data {
+        ChannelVariant::U8  (ColorVariant::L(data)) => ChannelVariant::U8  (ColorVariant::L(shift_left(data, planes))),
+        ChannelVariant::U16 (ColorVariant::L(data)) => ChannelVariant::U16 (ColorVariant::L(shift_left(data, planes))),
+        ChannelVariant::U32 (ColorVariant::L(data)) => ChannelVariant::U32 (ColorVariant::L(shift_left(data, planes))),
+        ChannelVariant::U64 (ColorVariant::L(data)) => ChannelVariant::U64 (ColorVariant::L(shift_left(data, planes))),
+        ChannelVariant::U128(ColorVariant::L(data)) => ChannelVariant::U128(ColorVariant::L(shift_left(data, planes))),
+        _ => data.clone(),
+    }
+}
+
+fn shift_indices_right(data: &mut ColorList, planes: u8) {
+    match data {
+        ChannelVariant::U8  (ColorVariant::L(data)) => shift_right(data, planes),
+        ChannelVariant::U16 (ColorVariant::L(data)) => shift_right(data, planes),
+        ChannelVariant::U32 (ColorVariant::L(data)) => shift_right(data, planes),
+        ChannelVariant::U64 (ColorVariant::L(data)) => shift_right(data, planes),
+        ChannelVariant::U128(ColorVariant::L(data)) => shift_right(data, planes),
+        _ => {}
+    }
+}
+
+#[inline]
+fn shift_left<C: IntChannelValue>(data: &[C], planes: u8) -> Vec<C> {
+    let planes = planes as u32;
+    if planes == 0 || planes >= C::BITS {
+        return data.to_vec();
+    }
+    data.iter().map(|&index| index.extend(planes as u8)).collect()
+}
+
+#[inline]
+fn shift_right<C: IntChannelValue>(data: &mut [C], planes: u8) {
+    let planes = planes as u32;
+    if planes == 0 || planes >= C::BITS {
+        return;
+    }
+    let shift = C::BITS - planes;
+    for index in data {
+        *index >>= shift;
+    }
 }
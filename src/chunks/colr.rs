@@ -0,0 +1,262 @@
+use std::io::Write;
+
+use crate::{error::{InvalidParams, ReadError, ReadErrorKind, WriteError, WriteErrorKind}, Head};
+
+use super::ChunkWrite;
+
+/// How the color values are encoded relative to linear light.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransferFunction {
+    /// IEC 61966-2-1 sRGB.
+    #[default]
+    Srgb   = 0,
+    /// Linear light.
+    Linear = 1,
+    /// SMPTE ST 2084 perceptual quantizer, used for HDR.
+    Pq     = 2,
+    /// ARIB STD-B67 hybrid log-gamma, used for HDR.
+    Hlg    = 3,
+    /// A pure power function with the gamma of the COLR chunk.
+    Gamma  = 4,
+}
+
+impl TransferFunction {
+    const SRGB:   u8 = TransferFunction::Srgb   as u8;
+    const LINEAR: u8 = TransferFunction::Linear as u8;
+    const PQ:     u8 = TransferFunction::Pq     as u8;
+    const HLG:    u8 = TransferFunction::Hlg    as u8;
+    const GAMMA:  u8 = TransferFunction::Gamma  as u8;
+
+    #[inline]
+    pub fn new(transfer_function: u8) -> Option<Self> {
+        match transfer_function {
+            Self::SRGB   => Some(Self::Srgb),
+            Self::LINEAR => Some(Self::Linear),
+            Self::PQ     => Some(Self::Pq),
+            Self::HLG    => Some(Self::Hlg),
+            Self::GAMMA  => Some(Self::Gamma),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for TransferFunction {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Srgb   => "sRGB".fmt(f),
+            Self::Linear => "linear".fmt(f),
+            Self::Pq     => "PQ".fmt(f),
+            Self::Hlg    => "HLG".fmt(f),
+            Self::Gamma  => "gamma".fmt(f),
+        }
+    }
+}
+
+/// CIE 1931 xy chromaticity coordinates of the primaries and the white point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chromaticities {
+    red:   [f32; 2],
+    green: [f32; 2],
+    blue:  [f32; 2],
+    white: [f32; 2],
+}
+
+impl Chromaticities {
+    const D65: [f32; 2] = [0.3127, 0.3290];
+
+    /// ITU-R BT.709, as used by sRGB.
+    pub const SRGB: Self = Self::new([0.640, 0.330], [0.300, 0.600], [0.150, 0.060], Self::D65);
+    pub const DISPLAY_P3: Self = Self::new([0.680, 0.320], [0.265, 0.690], [0.150, 0.060], Self::D65);
+    pub const BT2020: Self = Self::new([0.708, 0.292], [0.170, 0.797], [0.131, 0.046], Self::D65);
+    pub const ADOBE_RGB: Self = Self::new([0.640, 0.330], [0.210, 0.710], [0.150, 0.060], Self::D65);
+
+    #[inline]
+    pub const fn new(red: [f32; 2], green: [f32; 2], blue: [f32; 2], white: [f32; 2]) -> Self {
+        Self { red, green, blue, white }
+    }
+
+    #[inline]
+    pub fn red(&self) -> [f32; 2] {
+        self.red
+    }
+
+    #[inline]
+    pub fn green(&self) -> [f32; 2] {
+        self.green
+    }
+
+    #[inline]
+    pub fn blue(&self) -> [f32; 2] {
+        self.blue
+    }
+
+    #[inline]
+    pub fn white(&self) -> [f32; 2] {
+        self.white
+    }
+
+    /// Name of well known chromaticities, e.g. `"sRGB"`.
+    pub fn name(&self) -> Option<&'static str> {
+        if *self == Self::SRGB {
+            Some("sRGB")
+        } else if *self == Self::DISPLAY_P3 {
+            Some("Display P3")
+        } else if *self == Self::BT2020 {
+            Some("BT.2020")
+        } else if *self == Self::ADOBE_RGB {
+            Some("Adobe RGB")
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for Chromaticities {
+    #[inline]
+    fn default() -> Self {
+        Self::SRGB
+    }
+}
+
+impl std::fmt::Display for Chromaticities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(name) = self.name() {
+            return name.fmt(f);
+        }
+
+        let Self { red: [rx, ry], green: [gx, gy], blue: [bx, by], white: [wx, wy] } = *self;
+        write!(f, "red: {rx}, {ry}, green: {gx}, {gy}, blue: {bx}, {by}, white: {wx}, {wy}")
+    }
+}
+
+/// Lightweight color space information: how the colors are encoded and the
+/// chromaticities of the RGB color space. Without a COLR or an ICCP chunk the
+/// colors are assumed to be sRGB. If both exist the ICCP chunk takes
+/// precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Colr {
+    transfer_function: TransferFunction,
+    gamma: f32,
+    chromaticities: Chromaticities,
+}
+
+impl Colr {
+    pub const FOURCC: [u8; 4] = *b"COLR";
+    pub const SIZE: usize = 37;
+
+    #[inline]
+    pub fn new(transfer_function: TransferFunction, chromaticities: Chromaticities) -> Self {
+        Self { transfer_function, gamma: 0.0, chromaticities }
+    }
+
+    #[inline]
+    pub fn transfer_function(&self) -> TransferFunction {
+        self.transfer_function
+    }
+
+    #[inline]
+    pub fn set_transfer_function(&mut self, transfer_function: TransferFunction) {
+        self.transfer_function = transfer_function;
+    }
+
+    /// Exponent of [`TransferFunction::Gamma`], linear light is the encoded
+    /// value to the power of gamma, e.g. 2.2. Ignored for other transfer
+    /// functions.
+    #[inline]
+    pub fn gamma(&self) -> f32 {
+        self.gamma
+    }
+
+    #[inline]
+    pub fn set_gamma(&mut self, gamma: f32) {
+        self.gamma = gamma;
+    }
+
+    #[inline]
+    pub fn chromaticities(&self) -> &Chromaticities {
+        &self.chromaticities
+    }
+
+    #[inline]
+    pub fn set_chromaticities(&mut self, chromaticities: Chromaticities) {
+        self.chromaticities = chromaticities;
+    }
+
+    /// Checks that the gamma is positive if it is used and that all
+    /// chromaticities are finite.
+    pub fn check(&self) -> Result<(), InvalidParams> {
+        if self.transfer_function == TransferFunction::Gamma && !(self.gamma.is_finite() && self.gamma > 0.0) {
+            return Err(InvalidParams::with_message(
+                format!("gamma must be a positive number, but was: {}", self.gamma)));
+        }
+
+        let Chromaticities { red, green, blue, white } = self.chromaticities;
+        if [red, green, blue, white].iter().flatten().any(|value| !value.is_finite()) {
+            return Err(InvalidParams::with_message(
+                format!("chromaticities must be finite, but were: {}", self.chromaticities)));
+        }
+
+        Ok(())
+    }
+
+    pub fn read(bytes: &[u8]) -> Result<Self, ReadError> {
+        if bytes.len() < Self::SIZE {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("truncated COLR chunk: {} < {}", bytes.len(), Self::SIZE)));
+        }
+
+        let Some(transfer_function) = TransferFunction::new(bytes[0]) else {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("illegal transfer function: {}", bytes[0])));
+        };
+
+        let read_f32 = |offset: usize| f32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]);
+        let read_xy = |offset: usize| [read_f32(offset), read_f32(offset + 4)];
+
+        let colr = Self {
+            transfer_function,
+            gamma: read_f32(1),
+            chromaticities: Chromaticities::new(read_xy(5), read_xy(13), read_xy(21), read_xy(29)),
+        };
+
+        if let Err(err) = colr.check() {
+            return Err(ReadError::with_all(
+                ReadErrorKind::BrokenFile,
+                "illegal COLR chunk",
+                Box::new(err)));
+        }
+
+        Ok(colr)
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), WriteError> {
+        if let Err(err) = self.check() {
+            return Err(WriteError::with_all(
+                WriteErrorKind::InvalidParams,
+                "illegal COLR chunk",
+                Box::new(err)));
+        }
+
+        writer.write_all(&[self.transfer_function as u8])?;
+        writer.write_all(&self.gamma.to_le_bytes())?;
+
+        let Chromaticities { red, green, blue, white } = self.chromaticities;
+        for value in [red, green, blue, white].iter().flatten() {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ChunkWrite for Colr {
+    const FOURCC: [u8; 4] = Self::FOURCC;
+
+    #[inline]
+    fn write(&self, _head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        self.write(writer)
+    }
+}
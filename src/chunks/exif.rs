@@ -0,0 +1,652 @@
+use std::io::Write;
+
+use crate::{error::{InvalidParams, ReadError, ReadErrorKind, WriteError}, Head};
+
+use super::ChunkWrite;
+
+const TAG_MAKE:                 u16 = 0x010F;
+const TAG_MODEL:                u16 = 0x0110;
+const TAG_ORIENTATION:          u16 = 0x0112;
+const TAG_SOFTWARE:             u16 = 0x0131;
+const TAG_DATE_TIME:            u16 = 0x0132;
+const TAG_ARTIST:               u16 = 0x013B;
+const TAG_COPYRIGHT:            u16 = 0x8298;
+const TAG_EXIF_IFD:             u16 = 0x8769;
+const TAG_GPS_IFD:              u16 = 0x8825;
+
+const TAG_EXPOSURE_TIME:        u16 = 0x829A;
+const TAG_F_NUMBER:             u16 = 0x829D;
+const TAG_ISO:                  u16 = 0x8827;
+const TAG_DATE_TIME_ORIGINAL:   u16 = 0x9003;
+const TAG_FOCAL_LENGTH:         u16 = 0x920A;
+const TAG_LENS_MODEL:           u16 = 0xA434;
+
+const TAG_GPS_LATITUDE_REF:     u16 = 0x0001;
+const TAG_GPS_LATITUDE:         u16 = 0x0002;
+const TAG_GPS_LONGITUDE_REF:    u16 = 0x0003;
+const TAG_GPS_LONGITUDE:        u16 = 0x0004;
+const TAG_GPS_ALTITUDE_REF:     u16 = 0x0005;
+const TAG_GPS_ALTITUDE:         u16 = 0x0006;
+
+/// The image file directory a field is stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ifd {
+    /// IFD0, the tags of the main image.
+    Primary,
+    /// The Exif IFD with the camera settings.
+    Exif,
+    /// The GPS IFD.
+    Gps,
+}
+
+/// A decoded TIFF field value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExifValue {
+    Byte(Vec<u8>),
+    Ascii(String),
+    Short(Vec<u16>),
+    Long(Vec<u32>),
+    /// Numerator and denominator.
+    Rational(Vec<[u32; 2]>),
+    SByte(Vec<i8>),
+    Undefined(Vec<u8>),
+    SShort(Vec<i16>),
+    SLong(Vec<i32>),
+    /// Numerator and denominator.
+    SRational(Vec<[i32; 2]>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+}
+
+impl ExifValue {
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        if let Self::Ascii(value) = self {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// The numeric value at `index`, rationals are divided. `None` for
+    /// strings, undefined bytes and zero denominators.
+    pub fn get_f64(&self, index: usize) -> Option<f64> {
+        let value = match self {
+            Self::Byte     (values) => *values.get(index)? as f64,
+            Self::Short    (values) => *values.get(index)? as f64,
+            Self::Long     (values) => *values.get(index)? as f64,
+            Self::SByte    (values) => *values.get(index)? as f64,
+            Self::SShort   (values) => *values.get(index)? as f64,
+            Self::SLong    (values) => *values.get(index)? as f64,
+            Self::Float    (values) => *values.get(index)? as f64,
+            Self::Double   (values) => *values.get(index)?,
+            Self::Rational (values) => {
+                let [numerator, denominator] = *values.get(index)?;
+                if denominator == 0 {
+                    return None;
+                }
+                numerator as f64 / denominator as f64
+            }
+            Self::SRational(values) => {
+                let [numerator, denominator] = *values.get(index)?;
+                if denominator == 0 {
+                    return None;
+                }
+                numerator as f64 / denominator as f64
+            }
+            Self::Ascii(_) | Self::Undefined(_) => return None,
+        };
+
+        Some(value)
+    }
+
+    /// The unsigned integer value at `index`.
+    pub fn get_u32(&self, index: usize) -> Option<u32> {
+        match self {
+            Self::Byte (values) => values.get(index).map(|&value| value as u32),
+            Self::Short(values) => values.get(index).map(|&value| value as u32),
+            Self::Long (values) => values.get(index).copied(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExifField {
+    ifd: Ifd,
+    tag: u16,
+    value: ExifValue,
+}
+
+impl ExifField {
+    #[inline]
+    pub fn ifd(&self) -> Ifd {
+        self.ifd
+    }
+
+    #[inline]
+    pub fn tag(&self) -> u16 {
+        self.tag
+    }
+
+    #[inline]
+    pub fn value(&self) -> &ExifValue {
+        &self.value
+    }
+}
+
+/// Reads values of a TIFF structure in its byte order.
+struct TiffReader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl TiffReader<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        self.data.get(offset..offset.checked_add(N)?)?.try_into().ok()
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.bytes(offset)?;
+        Some(if self.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.bytes(offset)?;
+        Some(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    fn u64(&self, offset: usize) -> Option<u64> {
+        let bytes = self.bytes(offset)?;
+        Some(if self.big_endian { u64::from_be_bytes(bytes) } else { u64::from_le_bytes(bytes) })
+    }
+
+    /// Reads the entries of the IFD at `offset` into `fields`. Entries of
+    /// unknown types or with values outside of the data are skipped.
+    fn read_ifd(&self, offset: u32, ifd: Ifd, fields: &mut Vec<ExifField>) -> Result<(), InvalidParams> {
+        let offset = offset as usize;
+        let Some(count) = self.u16(offset) else {
+            return Err(InvalidParams::with_message(
+                format!("{ifd:?} IFD offset out of bounds: {offset}")));
+        };
+
+        for index in 0..count as usize {
+            let entry = offset + 2 + index * 12;
+            let (Some(tag), Some(value_type), Some(value_count)) = (self.u16(entry), self.u16(entry + 2), self.u32(entry + 4)) else {
+                return Err(InvalidParams::with_message(
+                    format!("truncated {ifd:?} IFD at offset {offset}")));
+            };
+
+            if let Some(value) = self.read_value(entry + 8, value_type, value_count as usize) {
+                fields.push(ExifField { ifd, tag, value });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_value(&self, value_offset: usize, value_type: u16, count: usize) -> Option<ExifValue> {
+        let type_size = match value_type {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 | 11 => 4,
+            5 | 10 | 12 => 8,
+            _ => return None,
+        };
+
+        let size = count.checked_mul(type_size)?;
+        let offset = if size <= 4 { value_offset } else { self.u32(value_offset)? as usize };
+        let bytes = self.data.get(offset..offset.checked_add(size)?)?;
+
+        let at = |index: usize| offset + index * type_size;
+        let value = match value_type {
+            1 => ExifValue::Byte(bytes.to_vec()),
+            2 => {
+                let end = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+                ExifValue::Ascii(String::from_utf8_lossy(&bytes[..end]).into_owned())
+            }
+            3  => ExifValue::Short    ((0..count).map(|index| self.u16(at(index))).collect::<Option<_>>()?),
+            4  => ExifValue::Long     ((0..count).map(|index| self.u32(at(index))).collect::<Option<_>>()?),
+            5  => ExifValue::Rational ((0..count).map(|index| Some([self.u32(at(index))?, self.u32(at(index) + 4)?])).collect::<Option<_>>()?),
+            6  => ExifValue::SByte    (bytes.iter().map(|&byte| byte as i8).collect()),
+            7  => ExifValue::Undefined(bytes.to_vec()),
+            8  => ExifValue::SShort   ((0..count).map(|index| self.u16(at(index)).map(|value| value as i16)).collect::<Option<_>>()?),
+            9  => ExifValue::SLong    ((0..count).map(|index| self.u32(at(index)).map(|value| value as i32)).collect::<Option<_>>()?),
+            10 => ExifValue::SRational((0..count).map(|index| Some([self.u32(at(index))? as i32, self.u32(at(index) + 4)? as i32])).collect::<Option<_>>()?),
+            11 => ExifValue::Float    ((0..count).map(|index| self.u32(at(index)).map(f32::from_bits)).collect::<Option<_>>()?),
+            12 => ExifValue::Double   ((0..count).map(|index| self.u64(at(index)).map(f64::from_bits)).collect::<Option<_>>()?),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+}
+
+/// Raw EXIF metadata in TIFF structure, as found in the APP1 segment of JPEG
+/// files after the `Exif\0\0` marker. The primary, Exif and GPS IFDs are
+/// parsed for the accessors, everything else is only kept as is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exif {
+    data: Vec<u8>,
+    fields: Vec<ExifField>,
+}
+
+impl Exif {
+    pub const FOURCC: [u8; 4] = *b"EXIF";
+
+    pub fn new(data: Vec<u8>) -> Result<Self, InvalidParams> {
+        let fields = Self::parse(&data)?;
+
+        Ok(Self { data, fields })
+    }
+
+    fn parse(data: &[u8]) -> Result<Vec<ExifField>, InvalidParams> {
+        if data.len() < 8 {
+            return Err(InvalidParams::with_message(
+                format!("truncated EXIF data: {} < 8", data.len())));
+        }
+
+        let big_endian = match &data[..4] {
+            b"II*\0" => false,
+            b"MM\0*" => true,
+            magic => return Err(InvalidParams::with_message(
+                format!("illegal TIFF header: {magic:?}"))),
+        };
+
+        let reader = TiffReader { data, big_endian };
+        let mut fields = Vec::new();
+
+        // checked above
+        let offset = reader.u32(4).unwrap_or(0);
+        reader.read_ifd(offset, Ifd::Primary, &mut fields)?;
+
+        for (ifd, tag) in [(Ifd::Exif, TAG_EXIF_IFD), (Ifd::Gps, TAG_GPS_IFD)] {
+            let offset = fields.iter()
+                .find(|field| field.ifd == Ifd::Primary && field.tag == tag)
+                .and_then(|field| field.value.get_u32(0));
+
+            if let Some(offset) = offset {
+                reader.read_ifd(offset, ifd, &mut fields)?;
+            }
+        }
+
+        Ok(fields)
+    }
+
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    #[inline]
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// All parsed fields in file order.
+    #[inline]
+    pub fn fields(&self) -> &[ExifField] {
+        &self.fields
+    }
+
+    pub fn get(&self, ifd: Ifd, tag: u16) -> Option<&ExifValue> {
+        self.fields.iter()
+            .find(|field| field.ifd == ifd && field.tag == tag)
+            .map(|field| &field.value)
+    }
+
+    #[inline]
+    fn get_str(&self, ifd: Ifd, tag: u16) -> Option<&str> {
+        self.get(ifd, tag)?.as_str()
+    }
+
+    #[inline]
+    fn get_f64(&self, ifd: Ifd, tag: u16) -> Option<f64> {
+        self.get(ifd, tag)?.get_f64(0)
+    }
+
+    /// Camera manufacturer.
+    #[inline]
+    pub fn make(&self) -> Option<&str> {
+        self.get_str(Ifd::Primary, TAG_MAKE)
+    }
+
+    /// Camera model.
+    #[inline]
+    pub fn model(&self) -> Option<&str> {
+        self.get_str(Ifd::Primary, TAG_MODEL)
+    }
+
+    #[inline]
+    pub fn lens_model(&self) -> Option<&str> {
+        self.get_str(Ifd::Exif, TAG_LENS_MODEL)
+    }
+
+    #[inline]
+    pub fn software(&self) -> Option<&str> {
+        self.get_str(Ifd::Primary, TAG_SOFTWARE)
+    }
+
+    #[inline]
+    pub fn artist(&self) -> Option<&str> {
+        self.get_str(Ifd::Primary, TAG_ARTIST)
+    }
+
+    #[inline]
+    pub fn copyright(&self) -> Option<&str> {
+        self.get_str(Ifd::Primary, TAG_COPYRIGHT)
+    }
+
+    /// How the image has to be transformed for display, 1 to 8 as defined by
+    /// TIFF, 1 being no transformation.
+    pub fn orientation(&self) -> Option<u16> {
+        let orientation = self.get(Ifd::Primary, TAG_ORIENTATION)?.get_u32(0)?;
+        if (1..=8).contains(&orientation) {
+            Some(orientation as u16)
+        } else {
+            None
+        }
+    }
+
+    /// Date and time of the last modification as `"YYYY:MM:DD HH:MM:SS"`.
+    #[inline]
+    pub fn date_time(&self) -> Option<&str> {
+        self.get_str(Ifd::Primary, TAG_DATE_TIME)
+    }
+
+    /// Date and time the photo was taken as `"YYYY:MM:DD HH:MM:SS"`.
+    #[inline]
+    pub fn date_time_original(&self) -> Option<&str> {
+        self.get_str(Ifd::Exif, TAG_DATE_TIME_ORIGINAL)
+    }
+
+    /// Exposure time in seconds.
+    #[inline]
+    pub fn exposure_time(&self) -> Option<f64> {
+        self.get_f64(Ifd::Exif, TAG_EXPOSURE_TIME)
+    }
+
+    #[inline]
+    pub fn f_number(&self) -> Option<f64> {
+        self.get_f64(Ifd::Exif, TAG_F_NUMBER)
+    }
+
+    /// ISO speed rating.
+    #[inline]
+    pub fn iso(&self) -> Option<u32> {
+        self.get(Ifd::Exif, TAG_ISO)?.get_u32(0)
+    }
+
+    /// Focal length in millimeters.
+    #[inline]
+    pub fn focal_length(&self) -> Option<f64> {
+        self.get_f64(Ifd::Exif, TAG_FOCAL_LENGTH)
+    }
+
+    fn gps_coordinate(&self, tag: u16, ref_tag: u16, negative_ref: &str) -> Option<f64> {
+        let value = self.get(Ifd::Gps, tag)?;
+        let degrees = value.get_f64(0)? + value.get_f64(1).unwrap_or(0.0) / 60.0 + value.get_f64(2).unwrap_or(0.0) / 3600.0;
+
+        if self.get_str(Ifd::Gps, ref_tag) == Some(negative_ref) {
+            Some(-degrees)
+        } else {
+            Some(degrees)
+        }
+    }
+
+    /// Latitude in degrees, negative for south.
+    #[inline]
+    pub fn gps_latitude(&self) -> Option<f64> {
+        self.gps_coordinate(TAG_GPS_LATITUDE, TAG_GPS_LATITUDE_REF, "S")
+    }
+
+    /// Longitude in degrees, negative for west.
+    #[inline]
+    pub fn gps_longitude(&self) -> Option<f64> {
+        self.gps_coordinate(TAG_GPS_LONGITUDE, TAG_GPS_LONGITUDE_REF, "W")
+    }
+
+    /// Altitude in meters, negative for below sea level.
+    pub fn gps_altitude(&self) -> Option<f64> {
+        let altitude = self.get_f64(Ifd::Gps, TAG_GPS_ALTITUDE)?;
+
+        if self.get(Ifd::Gps, TAG_GPS_ALTITUDE_REF).and_then(|value| value.get_u32(0)) == Some(1) {
+            Some(-altitude)
+        } else {
+            Some(altitude)
+        }
+    }
+
+    pub fn read(bytes: &[u8]) -> Result<Self, ReadError> {
+        match Self::new(bytes.to_vec()) {
+            Ok(exif) => Ok(exif),
+            Err(err) => Err(ReadError::with_all(
+                ReadErrorKind::BrokenFile,
+                "illegal EXIF chunk",
+                Box::new(err))),
+        }
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), WriteError> {
+        writer.write_all(&self.data)?;
+        Ok(())
+    }
+}
+
+impl ChunkWrite for Exif {
+    const FOURCC: [u8; 4] = Self::FOURCC;
+
+    #[inline]
+    fn write(&self, _head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        self.write(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum Entry {
+        /// Tag, type, count and the encoded values.
+        Value(u16, u16, u32, Vec<u8>),
+        /// Tag of a pointer to the IFD with the given index.
+        Ifd(u16, usize),
+    }
+
+    struct TiffWriter {
+        big_endian: bool,
+    }
+
+    impl TiffWriter {
+        fn u16(&self, value: u16) -> [u8; 2] {
+            if self.big_endian { value.to_be_bytes() } else { value.to_le_bytes() }
+        }
+
+        fn u32(&self, value: u32) -> [u8; 4] {
+            if self.big_endian { value.to_be_bytes() } else { value.to_le_bytes() }
+        }
+
+        fn ascii(&self, tag: u16, value: &str) -> Entry {
+            let mut bytes = value.as_bytes().to_vec();
+            bytes.push(0);
+            Entry::Value(tag, 2, bytes.len() as u32, bytes)
+        }
+
+        fn short(&self, tag: u16, values: &[u16]) -> Entry {
+            Entry::Value(tag, 3, values.len() as u32, values.iter().flat_map(|&value| self.u16(value)).collect())
+        }
+
+        fn long(&self, tag: u16, values: &[u32]) -> Entry {
+            Entry::Value(tag, 4, values.len() as u32, values.iter().flat_map(|&value| self.u32(value)).collect())
+        }
+
+        fn rational(&self, tag: u16, values: &[[u32; 2]]) -> Entry {
+            Entry::Value(tag, 5, values.len() as u32, values.iter().flatten().flat_map(|&value| self.u32(value)).collect())
+        }
+
+        /// The IFDs in order, each followed by its values that don't fit
+        /// into the entry.
+        fn write(&self, ifds: &[Vec<Entry>]) -> Vec<u8> {
+            let entry_size = |entries: &[Entry]| 2 + entries.len() * 12 + 4;
+            let extra_size = |entry: &Entry| match entry {
+                Entry::Value(_, _, _, bytes) if bytes.len() > 4 => bytes.len(),
+                _ => 0,
+            };
+
+            let mut offsets = Vec::new();
+            let mut offset = 8;
+            for entries in ifds {
+                offsets.push(offset);
+                offset += entry_size(entries) + entries.iter().map(extra_size).sum::<usize>();
+            }
+
+            let mut data = if self.big_endian { b"MM\0*".to_vec() } else { b"II*\0".to_vec() };
+            data.extend(self.u32(8));
+
+            for (entries, &offset) in ifds.iter().zip(&offsets) {
+                let mut extra = Vec::new();
+                let mut extra_offset = offset + entry_size(entries);
+
+                data.extend(self.u16(entries.len() as u16));
+                for entry in entries {
+                    let (tag, value_type, count, bytes) = match entry {
+                        Entry::Value(tag, value_type, count, bytes) => (*tag, *value_type, *count, bytes.clone()),
+                        Entry::Ifd(tag, index) => (*tag, 4, 1, self.u32(offsets[*index] as u32).to_vec()),
+                    };
+
+                    data.extend(self.u16(tag));
+                    data.extend(self.u16(value_type));
+                    data.extend(self.u32(count));
+                    if bytes.len() <= 4 {
+                        data.extend(&bytes);
+                        data.resize(data.len() + 4 - bytes.len(), 0);
+                    } else {
+                        data.extend(self.u32(extra_offset as u32));
+                        extra_offset += bytes.len();
+                        extra.extend(bytes);
+                    }
+                }
+
+                // no next IFD
+                data.extend(self.u32(0));
+                data.extend(extra);
+            }
+
+            data
+        }
+    }
+
+    fn camera(big_endian: bool) -> Vec<u8> {
+        let writer = TiffWriter { big_endian };
+        writer.write(&[
+            vec![
+                // 6 bytes, stored at an offset
+                writer.ascii(TAG_MAKE, "Canon"),
+                // 4 bytes, stored inline
+                writer.ascii(TAG_MODEL, "EOS"),
+                writer.short(TAG_ORIENTATION, &[6]),
+                writer.short(0x0102, &[8, 8, 8]),
+                Entry::Ifd(TAG_EXIF_IFD, 1),
+                Entry::Ifd(TAG_GPS_IFD, 2),
+            ],
+            vec![
+                writer.rational(TAG_EXPOSURE_TIME, &[[1, 250]]),
+                writer.rational(TAG_F_NUMBER, &[[28, 10]]),
+                writer.short(TAG_ISO, &[400]),
+                writer.rational(TAG_FOCAL_LENGTH, &[[50, 0]]),
+                writer.long(0x9999, &[0xDEADBEEF]),
+                writer.ascii(TAG_LENS_MODEL, "EF50mm f/1.8"),
+            ],
+            vec![
+                writer.ascii(TAG_GPS_LATITUDE_REF, "S"),
+                writer.rational(TAG_GPS_LATITUDE, &[[33, 1], [52, 1], [4, 1]]),
+                writer.ascii(TAG_GPS_LONGITUDE_REF, "E"),
+                writer.rational(TAG_GPS_LONGITUDE, &[[151, 1], [12, 1], [36, 1]]),
+                Entry::Value(TAG_GPS_ALTITUDE_REF, 1, 1, vec![1]),
+                writer.rational(TAG_GPS_ALTITUDE, &[[125, 10]]),
+            ],
+        ])
+    }
+
+    #[test]
+    fn byte_orders_and_value_offsets() {
+        for big_endian in [false, true] {
+            let exif = Exif::new(camera(big_endian)).unwrap();
+
+            assert_eq!(exif.make(), Some("Canon"));
+            assert_eq!(exif.model(), Some("EOS"));
+            assert_eq!(exif.orientation(), Some(6));
+            assert_eq!(exif.get(Ifd::Primary, 0x0102), Some(&ExifValue::Short(vec![8, 8, 8])));
+            assert_eq!(exif.exposure_time(), Some(1.0 / 250.0));
+            assert_eq!(exif.f_number(), Some(2.8));
+            assert_eq!(exif.iso(), Some(400));
+            // zero denominator
+            assert_eq!(exif.focal_length(), None);
+            assert_eq!(exif.get(Ifd::Exif, 0x9999), Some(&ExifValue::Long(vec![0xDEADBEEF])));
+            assert_eq!(exif.lens_model(), Some("EF50mm f/1.8"));
+            assert_eq!(exif.fields().len(), 18);
+        }
+    }
+
+    #[test]
+    fn gps_refs() {
+        let exif = Exif::new(camera(false)).unwrap();
+        assert!((exif.gps_latitude().unwrap() + (33.0 + 52.0 / 60.0 + 4.0 / 3600.0)).abs() < 1e-12);
+        assert!((exif.gps_longitude().unwrap() - (151.0 + 12.0 / 60.0 + 36.0 / 3600.0)).abs() < 1e-12);
+        assert_eq!(exif.gps_altitude(), Some(-12.5));
+
+        let writer = TiffWriter { big_endian: true };
+        let data = writer.write(&[
+            vec![Entry::Ifd(TAG_GPS_IFD, 1)],
+            vec![
+                writer.ascii(TAG_GPS_LATITUDE_REF, "N"),
+                writer.rational(TAG_GPS_LATITUDE, &[[45, 1], [30, 1]]),
+                writer.ascii(TAG_GPS_LONGITUDE_REF, "W"),
+                writer.rational(TAG_GPS_LONGITUDE, &[[1225, 10]]),
+                Entry::Value(TAG_GPS_ALTITUDE_REF, 1, 1, vec![0]),
+                writer.rational(TAG_GPS_ALTITUDE, &[[100, 1]]),
+            ],
+        ]);
+
+        let exif = Exif::new(data).unwrap();
+        assert_eq!(exif.gps_latitude(), Some(45.5));
+        assert_eq!(exif.gps_longitude(), Some(-122.5));
+        assert_eq!(exif.gps_altitude(), Some(100.0));
+    }
+
+    #[test]
+    fn truncated_data() {
+        for big_endian in [false, true] {
+            let data = camera(big_endian);
+            // header, entry count, 6 entries
+            let primary_end = 8 + 2 + 6 * 12;
+
+            for len in 0..data.len() {
+                let result = Exif::new(data[..len].to_vec());
+                if len < primary_end {
+                    assert!(result.is_err(), "{len}");
+                }
+            }
+
+            // IFD offset out of bounds
+            let mut data = data;
+            let offset = if big_endian { u32::MAX.to_be_bytes() } else { u32::MAX.to_le_bytes() };
+            data[4..8].copy_from_slice(&offset);
+            assert!(Exif::new(data).is_err());
+        }
+
+        // value offset out of bounds and an unknown type are skipped
+        let writer = TiffWriter { big_endian: false };
+        let mut data = writer.write(&[vec![
+            writer.rational(TAG_F_NUMBER, &[[28, 10]]),
+            Entry::Value(0x1234, 99, 1, vec![0]),
+            writer.long(TAG_ORIENTATION, &[1]),
+        ]]);
+        data[8 + 2 + 8..8 + 2 + 12].copy_from_slice(&u32::MAX.to_le_bytes());
+        data[8 + 2 + 4..8 + 2 + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let exif = Exif::new(data).unwrap();
+        assert_eq!(exif.fields().len(), 1);
+        assert_eq!(exif.orientation(), Some(1));
+    }
+}
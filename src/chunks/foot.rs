@@ -1,10 +1,14 @@
 use std::io::Write;
 
+#[cfg(feature = "serde")]
+use crate::error::InvalidParams;
 use crate::{error::{ReadError, ReadErrorKind, WriteError}, Head};
 
 use super::ChunkWrite;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "ChecksumRepr", try_from = "ChecksumRepr"))]
 pub enum Checksum {
     Crc32(u32),
     Sha1  (Box<[u8; 20]>),
@@ -19,22 +23,22 @@ impl Checksum {
     pub fn from_bytes(checksum_type: ChecksumType, bytes: &[u8]) -> Option<Self> {
         match checksum_type {
             ChecksumType::Crc32 => {
-                Some(Checksum::Crc32(u32::from_le_bytes(*bytes[1..].first_chunk()?)))
+                Some(Checksum::Crc32(u32::from_le_bytes(*bytes.first_chunk()?)))
             }
             ChecksumType::Sha1 => {
-                Some(Checksum::Sha1(Box::new(*bytes[1..].first_chunk()?)))
+                Some(Checksum::Sha1(Box::new(*bytes.first_chunk()?)))
             }
             ChecksumType::Sha224 => {
-                Some(Checksum::Sha224(Box::new(*bytes[1..].first_chunk()?)))
+                Some(Checksum::Sha224(Box::new(*bytes.first_chunk()?)))
             }
             ChecksumType::Sha256 => {
-                Some(Checksum::Sha256(Box::new(*bytes[1..].first_chunk()?)))
+                Some(Checksum::Sha256(Box::new(*bytes.first_chunk()?)))
             }
             ChecksumType::Sha384 => {
-                Some(Checksum::Sha384(Box::new(*bytes[1..].first_chunk()?)))
+                Some(Checksum::Sha384(Box::new(*bytes.first_chunk()?)))
             }
             ChecksumType::Sha512 => {
-                Some(Checksum::Sha512(Box::new(*bytes[1..].first_chunk()?)))
+                Some(Checksum::Sha512(Box::new(*bytes.first_chunk()?)))
             }
         }
     }
@@ -51,6 +55,19 @@ impl Checksum {
         }
     }
 
+    /// The checksum as it is stored in the FOOT chunk, CRC32 in little
+    /// endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Crc32(value) => value.to_le_bytes().to_vec(),
+            Self::Sha1(data)   => data.to_vec(),
+            Self::Sha224(data) => data.to_vec(),
+            Self::Sha256(data) => data.to_vec(),
+            Self::Sha384(data) => data.to_vec(),
+            Self::Sha512(data) => data.to_vec(),
+        }
+    }
+
     #[inline]
     pub fn byte_size(&self) -> usize {
         match self {
@@ -65,6 +82,8 @@ impl Checksum {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum ChecksumType {
     Crc32  = 1,
     Sha1   = 2,
@@ -110,7 +129,50 @@ impl std::fmt::Display for ChecksumType {
     }
 }
 
+/// Serialized form of [`Checksum`], the value as hex string of the bytes
+/// stored in the FOOT chunk.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ChecksumRepr {
+    #[serde(rename = "type")]
+    checksum_type: ChecksumType,
+    value: String,
+}
+
+#[cfg(feature = "serde")]
+impl From<Checksum> for ChecksumRepr {
+    fn from(checksum: Checksum) -> Self {
+        let value = checksum.to_bytes().iter().map(|byte| format!("{byte:02x}")).collect();
+        Self { checksum_type: checksum.checksum_type(), value }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ChecksumRepr> for Checksum {
+    type Error = InvalidParams;
+
+    fn try_from(repr: ChecksumRepr) -> Result<Self, Self::Error> {
+        let illegal = || InvalidParams::with_message(
+            format!("illegal {} checksum: {:?}", repr.checksum_type, repr.value));
+
+        if !repr.value.is_ascii() || !repr.value.len().is_multiple_of(2) {
+            return Err(illegal());
+        }
+
+        let bytes = (0..repr.value.len()).step_by(2)
+            .map(|index| u8::from_str_radix(&repr.value[index..index + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| illegal())?;
+
+        match Checksum::from_bytes(repr.checksum_type, &bytes) {
+            Some(checksum) if checksum.byte_size() == bytes.len() => Ok(checksum),
+            _ => Err(illegal()),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Foot {
     checksum: Checksum,
 }
@@ -118,6 +180,11 @@ pub struct Foot {
 impl Foot {
     pub const FOURCC: [u8; 4] = *b"FOOT";
 
+    #[inline]
+    pub fn new(checksum: Checksum) -> Self {
+        Self { checksum }
+    }
+
     #[inline]
     pub fn checksum(&self) -> &Checksum {
         &self.checksum
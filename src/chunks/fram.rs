@@ -0,0 +1,280 @@
+use std::io::Write;
+
+use crate::{error::{ReadError, ReadErrorKind, WriteError, WriteErrorKind}, Head};
+
+use super::{Body, ChunkWrite};
+
+/// What happens to the frame's rectangle before the next frame is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisposeOp {
+    /// Leave the canvas as it is.
+    #[default]
+    None       = 0,
+    /// Clear the rectangle to transparent black.
+    Background = 1,
+    /// Restore the rectangle to what it was before the frame was drawn.
+    Previous   = 2,
+}
+
+impl DisposeOp {
+    const NONE:       u8 = DisposeOp::None       as u8;
+    const BACKGROUND: u8 = DisposeOp::Background as u8;
+    const PREVIOUS:   u8 = DisposeOp::Previous   as u8;
+
+    #[inline]
+    pub fn new(dispose_op: u8) -> Option<Self> {
+        match dispose_op {
+            Self::NONE       => Some(Self::None),
+            Self::BACKGROUND => Some(Self::Background),
+            Self::PREVIOUS   => Some(Self::Previous),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for DisposeOp {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None       => "none".fmt(f),
+            Self::Background => "background".fmt(f),
+            Self::Previous   => "previous".fmt(f),
+        }
+    }
+}
+
+/// How the frame's pixels are combined with the canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendOp {
+    /// Replace the pixels of the rectangle, including alpha.
+    #[default]
+    Source = 0,
+    /// Alpha composite the frame over the canvas.
+    Over   = 1,
+}
+
+impl BlendOp {
+    const SOURCE: u8 = BlendOp::Source as u8;
+    const OVER:   u8 = BlendOp::Over   as u8;
+
+    #[inline]
+    pub fn new(blend_op: u8) -> Option<Self> {
+        match blend_op {
+            Self::SOURCE => Some(Self::Source),
+            Self::OVER   => Some(Self::Over),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for BlendOp {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Source => "source".fmt(f),
+            Self::Over   => "over".fmt(f),
+        }
+    }
+}
+
+/// An additional animation frame. The frame covers a rectangle of the canvas
+/// and its pixels are encoded like the BODY chunk, using the header of the
+/// file with the width and height of the rectangle. The BODY chunk is the
+/// first frame of the animation.
+#[derive(Debug, Clone)]
+pub struct Fram {
+    delay: Option<u32>,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    dispose_op: DisposeOp,
+    blend_op: BlendOp,
+    body: Body,
+}
+
+impl Fram {
+    pub const FOURCC: [u8; 4] = *b"FRAM";
+    pub const HEAD_SIZE: usize = 22;
+    /// Delay value meaning the default delay of the ANIM chunk is used.
+    pub const DEFAULT_DELAY: u32 = u32::MAX;
+
+    /// `body` has to contain `width * height` colors.
+    #[inline]
+    pub fn new(x: u32, y: u32, width: u32, height: u32, body: Body) -> Self {
+        Self {
+            delay: None,
+            x,
+            y,
+            width,
+            height,
+            dispose_op: DisposeOp::None,
+            blend_op: BlendOp::Source,
+            body,
+        }
+    }
+
+    /// Delay in milliseconds. `None` means the default delay of the ANIM chunk.
+    #[inline]
+    pub fn delay(&self) -> Option<u32> {
+        self.delay
+    }
+
+    /// `Some(Fram::DEFAULT_DELAY)` is the same as `None`.
+    #[inline]
+    pub fn set_delay(&mut self, delay: Option<u32>) {
+        self.delay = delay.filter(|&delay| delay != Self::DEFAULT_DELAY);
+    }
+
+    #[inline]
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    #[inline]
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn dispose_op(&self) -> DisposeOp {
+        self.dispose_op
+    }
+
+    #[inline]
+    pub fn set_dispose_op(&mut self, dispose_op: DisposeOp) {
+        self.dispose_op = dispose_op;
+    }
+
+    #[inline]
+    pub fn blend_op(&self) -> BlendOp {
+        self.blend_op
+    }
+
+    #[inline]
+    pub fn set_blend_op(&mut self, blend_op: BlendOp) {
+        self.blend_op = blend_op;
+    }
+
+    #[inline]
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    #[inline]
+    pub fn body_mut(&mut self) -> &mut Body {
+        &mut self.body
+    }
+
+    #[inline]
+    pub fn into_body(self) -> Body {
+        self.body
+    }
+
+    /// Whether the frame's rectangle lies within the canvas.
+    #[inline]
+    pub fn is_within(&self, head: &Head) -> bool {
+        self.x as u64 + self.width  as u64 <= head.width()  as u64 &&
+        self.y as u64 + self.height as u64 <= head.height() as u64
+    }
+
+    /// The header of the file with the size of the frame's rectangle.
+    #[inline]
+    pub fn frame_head(&self, head: &Head) -> Head {
+        head.with_size(self.width, self.height)
+    }
+
+    pub fn read(bytes: &[u8], head: &Head) -> Result<Self, ReadError> {
+        if bytes.len() < Self::HEAD_SIZE {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("truncated FRAM chunk: {} < {}", bytes.len(), Self::HEAD_SIZE)));
+        }
+
+        let delay  = u32::from_le_bytes([bytes[ 0], bytes[ 1], bytes[ 2], bytes[ 3]]);
+        let x      = u32::from_le_bytes([bytes[ 4], bytes[ 5], bytes[ 6], bytes[ 7]]);
+        let y      = u32::from_le_bytes([bytes[ 8], bytes[ 9], bytes[10], bytes[11]]);
+        let width  = u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]);
+        let height = u32::from_le_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]);
+
+        let Some(dispose_op) = DisposeOp::new(bytes[20]) else {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("illegal dispose op: {}", bytes[20])));
+        };
+
+        let Some(blend_op) = BlendOp::new(bytes[21]) else {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("illegal blend op: {}", bytes[21])));
+        };
+
+        if x as u64 + width as u64 > head.width() as u64 || y as u64 + height as u64 > head.height() as u64 {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("frame x: {x}, y: {y}, width: {width}, height: {height} exceeds the canvas of {} x {}",
+                    head.width(), head.height())));
+        }
+
+        let body = Body::read(&bytes[Self::HEAD_SIZE..], &head.with_size(width, height))?;
+
+        let mut fram = Self {
+            delay: None,
+            x,
+            y,
+            width,
+            height,
+            dispose_op,
+            blend_op,
+            body,
+        };
+        fram.set_delay(Some(delay));
+
+        Ok(fram)
+    }
+
+    pub fn write(&self, head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        if !self.is_within(head) {
+            return Err(WriteError::with_message(
+                WriteErrorKind::InvalidParams,
+                format!("frame x: {}, y: {}, width: {}, height: {} exceeds the canvas of {} x {}",
+                    self.x, self.y, self.width, self.height, head.width(), head.height())));
+        }
+
+        let color_count = self.width as usize * self.height as usize;
+        if self.body.data().len() != color_count {
+            return Err(WriteError::with_message(
+                WriteErrorKind::InvalidParams,
+                format!("frame of {} x {} needs {color_count} colors, but has: {}",
+                    self.width, self.height, self.body.data().len())));
+        }
+
+        writer.write_all(&self.delay.unwrap_or(Self::DEFAULT_DELAY).to_le_bytes())?;
+        writer.write_all(&self.x.to_le_bytes())?;
+        writer.write_all(&self.y.to_le_bytes())?;
+        writer.write_all(&self.width.to_le_bytes())?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        writer.write_all(&[self.dispose_op as u8, self.blend_op as u8])?;
+
+        self.body.write(&self.frame_head(head), writer)
+    }
+}
+
+impl ChunkWrite for Fram {
+    const FOURCC: [u8; 4] = Self::FOURCC;
+
+    #[inline]
+    fn write(&self, head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        self.write(head, writer)
+    }
+}
@@ -0,0 +1,98 @@
+use std::io::Write;
+
+use crate::{error::{InvalidParams, ReadError, ReadErrorKind, WriteError}, Head};
+
+use super::ChunkWrite;
+
+/// An embedded ICC color profile describing the color space of the pixels.
+/// The profile is stored as is, only its header is checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Iccp {
+    profile: Vec<u8>,
+}
+
+impl Iccp {
+    pub const FOURCC: [u8; 4] = *b"ICCP";
+    /// Size of the header of an ICC profile.
+    pub const HEAD_SIZE: usize = 128;
+    pub const SIGNATURE: [u8; 4] = *b"acsp";
+
+    pub fn new(profile: Vec<u8>) -> Result<Self, InvalidParams> {
+        Self::check(&profile)?;
+
+        Ok(Self { profile })
+    }
+
+    fn check(profile: &[u8]) -> Result<(), InvalidParams> {
+        if profile.len() < Self::HEAD_SIZE {
+            return Err(InvalidParams::with_message(
+                format!("truncated ICC profile: {} < {}", profile.len(), Self::HEAD_SIZE)));
+        }
+
+        if profile[36..40] != Self::SIGNATURE {
+            return Err(InvalidParams::with_message(
+                format!("illegal ICC profile signature: {:?}", &profile[36..40])));
+        }
+
+        let size = u32::from_be_bytes([profile[0], profile[1], profile[2], profile[3]]);
+        if size as usize > profile.len() {
+            return Err(InvalidParams::with_message(
+                format!("truncated ICC profile: {} < {size}", profile.len())));
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn profile(&self) -> &[u8] {
+        &self.profile
+    }
+
+    #[inline]
+    pub fn into_profile(self) -> Vec<u8> {
+        self.profile
+    }
+
+    /// Major and minor version of the profile format.
+    #[inline]
+    pub fn version(&self) -> (u8, u8) {
+        (self.profile[8], self.profile[9] >> 4)
+    }
+
+    /// Profile class signature, e.g. `b"mntr"` for display devices.
+    #[inline]
+    pub fn device_class(&self) -> [u8; 4] {
+        [self.profile[12], self.profile[13], self.profile[14], self.profile[15]]
+    }
+
+    /// Data color space signature, e.g. `b"RGB "`.
+    #[inline]
+    pub fn color_space(&self) -> [u8; 4] {
+        [self.profile[16], self.profile[17], self.profile[18], self.profile[19]]
+    }
+
+    pub fn read(bytes: &[u8]) -> Result<Self, ReadError> {
+        if let Err(err) = Self::check(bytes) {
+            return Err(ReadError::with_all(
+                ReadErrorKind::BrokenFile,
+                "illegal ICCP chunk",
+                Box::new(err)));
+        }
+
+        Ok(Self { profile: bytes.to_vec() })
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), WriteError> {
+        writer.write_all(&self.profile)?;
+        Ok(())
+    }
+}
+
+impl ChunkWrite for Iccp {
+    const FOURCC: [u8; 4] = Self::FOURCC;
+
+    #[inline]
+    fn write(&self, _head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        self.write(writer)
+    }
+}
@@ -12,6 +12,11 @@ pub struct Indx {
 impl Indx {
     pub const FOURCC: [u8; 4] = *b"INDX";
 
+    #[inline]
+    pub fn new(colors: ColorList) -> Self {
+        Self { colors }
+    }
+
     #[inline]
     pub fn colors(&self) -> &ColorList {
         &self.colors
@@ -22,6 +27,11 @@ impl Indx {
         &mut self.colors
     }
 
+    #[inline]
+    pub fn into_colors(self) -> ColorList {
+        self.colors
+    }
+
     pub fn read(data: &[u8], head: &Head) -> Result<Self, ReadError> {
         let index_planes = head.index_planes();
         if index_planes == 0 {
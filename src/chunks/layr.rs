@@ -0,0 +1,290 @@
+use core::str;
+use std::io::Write;
+
+use crate::{error::{ReadError, ReadErrorKind, WriteError, WriteErrorKind}, Head};
+
+use super::{Body, ChunkWrite};
+
+/// How the colors of a layer are combined with the layers below it. These are
+/// the separable blend modes of the W3C compositing specification, except for
+/// [`BlendMode::Add`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    #[default]
+    Normal     = 0,
+    Multiply   = 1,
+    Screen     = 2,
+    Overlay    = 3,
+    Darken     = 4,
+    Lighten    = 5,
+    ColorDodge = 6,
+    ColorBurn  = 7,
+    HardLight  = 8,
+    SoftLight  = 9,
+    Difference = 10,
+    Exclusion  = 11,
+    /// Sum of the colors, clamped to 1.
+    Add        = 12,
+}
+
+impl BlendMode {
+    const NORMAL:      u8 = BlendMode::Normal     as u8;
+    const MULTIPLY:    u8 = BlendMode::Multiply   as u8;
+    const SCREEN:      u8 = BlendMode::Screen     as u8;
+    const OVERLAY:     u8 = BlendMode::Overlay    as u8;
+    const DARKEN:      u8 = BlendMode::Darken     as u8;
+    const LIGHTEN:     u8 = BlendMode::Lighten    as u8;
+    const COLOR_DODGE: u8 = BlendMode::ColorDodge as u8;
+    const COLOR_BURN:  u8 = BlendMode::ColorBurn  as u8;
+    const HARD_LIGHT:  u8 = BlendMode::HardLight  as u8;
+    const SOFT_LIGHT:  u8 = BlendMode::SoftLight  as u8;
+    const DIFFERENCE:  u8 = BlendMode::Difference as u8;
+    const EXCLUSION:   u8 = BlendMode::Exclusion  as u8;
+    const ADD:         u8 = BlendMode::Add        as u8;
+
+    #[inline]
+    pub fn new(blend_mode: u8) -> Option<Self> {
+        match blend_mode {
+            Self::NORMAL      => Some(Self::Normal),
+            Self::MULTIPLY    => Some(Self::Multiply),
+            Self::SCREEN      => Some(Self::Screen),
+            Self::OVERLAY     => Some(Self::Overlay),
+            Self::DARKEN      => Some(Self::Darken),
+            Self::LIGHTEN     => Some(Self::Lighten),
+            Self::COLOR_DODGE => Some(Self::ColorDodge),
+            Self::COLOR_BURN  => Some(Self::ColorBurn),
+            Self::HARD_LIGHT  => Some(Self::HardLight),
+            Self::SOFT_LIGHT  => Some(Self::SoftLight),
+            Self::DIFFERENCE  => Some(Self::Difference),
+            Self::EXCLUSION   => Some(Self::Exclusion),
+            Self::ADD         => Some(Self::Add),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for BlendMode {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Normal     => "normal".fmt(f),
+            Self::Multiply   => "multiply".fmt(f),
+            Self::Screen     => "screen".fmt(f),
+            Self::Overlay    => "overlay".fmt(f),
+            Self::Darken     => "darken".fmt(f),
+            Self::Lighten    => "lighten".fmt(f),
+            Self::ColorDodge => "color-dodge".fmt(f),
+            Self::ColorBurn  => "color-burn".fmt(f),
+            Self::HardLight  => "hard-light".fmt(f),
+            Self::SoftLight  => "soft-light".fmt(f),
+            Self::Difference => "difference".fmt(f),
+            Self::Exclusion  => "exclusion".fmt(f),
+            Self::Add        => "add".fmt(f),
+        }
+    }
+}
+
+/// A named layer of a layered document. Its pixels are encoded like the BODY
+/// chunk, using the header of the file with the width and height of the layer.
+/// The layer may extend beyond the canvas, which clips it. Layers are stacked
+/// bottom to top in the order of the chunks.
+#[derive(Debug, Clone)]
+pub struct Layr {
+    name: String,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    opacity: f32,
+    blend_mode: BlendMode,
+    visible: bool,
+    body: Body,
+}
+
+impl Layr {
+    pub const FOURCC: [u8; 4] = *b"LAYR";
+    pub const HEAD_SIZE: usize = 22;
+    pub const VISIBLE: u8 = 1;
+
+    /// A visible, fully opaque layer with [`BlendMode::Normal`]. `body` has to
+    /// contain `width * height` colors.
+    #[inline]
+    pub fn new(name: impl Into<String>, x: i32, y: i32, width: u32, height: u32, body: Body) -> Self {
+        Self {
+            name: name.into(),
+            x,
+            y,
+            width,
+            height,
+            opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+            visible: true,
+            body,
+        }
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = name.into();
+    }
+
+    #[inline]
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    #[inline]
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    #[inline]
+    pub fn set_offset(&mut self, x: i32, y: i32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// 0.0 is fully transparent, 1.0 is fully opaque.
+    #[inline]
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Clamped to 0.0 to 1.0.
+    #[inline]
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = if opacity.is_nan() { 1.0 } else { opacity.clamp(0.0, 1.0) };
+    }
+
+    #[inline]
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    #[inline]
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    #[inline]
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    #[inline]
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    #[inline]
+    pub fn body_mut(&mut self) -> &mut Body {
+        &mut self.body
+    }
+
+    #[inline]
+    pub fn into_body(self) -> Body {
+        self.body
+    }
+
+    pub fn read(bytes: &[u8], head: &Head) -> Result<Self, ReadError> {
+        if bytes.len() < Self::HEAD_SIZE {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("truncated LAYR chunk: {} < {}", bytes.len(), Self::HEAD_SIZE)));
+        }
+
+        let x       = i32::from_le_bytes([bytes[ 0], bytes[ 1], bytes[ 2], bytes[ 3]]);
+        let y       = i32::from_le_bytes([bytes[ 4], bytes[ 5], bytes[ 6], bytes[ 7]]);
+        let width   = u32::from_le_bytes([bytes[ 8], bytes[ 9], bytes[10], bytes[11]]);
+        let height  = u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]);
+        let opacity = f32::from_le_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]);
+
+        let Some(blend_mode) = BlendMode::new(bytes[20]) else {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("illegal blend mode: {}", bytes[20])));
+        };
+
+        let visible = bytes[21] & Self::VISIBLE != 0;
+
+        let data = &bytes[Self::HEAD_SIZE..];
+        let Some(name_len) = data.iter().position(|&byte| byte == 0) else {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                "layer name not NUL terminated"));
+        };
+
+        let name = match str::from_utf8(&data[..name_len]) {
+            Ok(name) => name.to_owned(),
+            Err(err) => return Err(ReadError::with_all(
+                ReadErrorKind::BrokenFile,
+                format!("illegal UTF-8 bytes in layer name: {:?}", &data[..name_len]),
+                Box::new(err)))
+        };
+
+        let body = Body::read(&data[name_len + 1..], &head.with_size(width, height))?;
+
+        let mut layr = Self::new(name, x, y, width, height, body);
+        layr.set_opacity(opacity);
+        layr.blend_mode = blend_mode;
+        layr.visible = visible;
+
+        Ok(layr)
+    }
+
+    pub fn write(&self, head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        if self.name.contains('\0') {
+            return Err(WriteError::with_message(
+                WriteErrorKind::InvalidParams,
+                format!("layer name may not contain NUL: {:?}", self.name)));
+        }
+
+        let color_count = self.width as usize * self.height as usize;
+        if self.body.data().len() != color_count {
+            return Err(WriteError::with_message(
+                WriteErrorKind::InvalidParams,
+                format!("layer of {} x {} needs {color_count} colors, but has: {}",
+                    self.width, self.height, self.body.data().len())));
+        }
+
+        writer.write_all(&self.x.to_le_bytes())?;
+        writer.write_all(&self.y.to_le_bytes())?;
+        writer.write_all(&self.width.to_le_bytes())?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        writer.write_all(&self.opacity.to_le_bytes())?;
+        writer.write_all(&[self.blend_mode as u8, if self.visible { Self::VISIBLE } else { 0 }])?;
+        writer.write_all(self.name.as_bytes())?;
+        writer.write_all(&[0])?;
+
+        self.body.write(&head.with_size(self.width, self.height), writer)
+    }
+}
+
+impl ChunkWrite for Layr {
+    const FOURCC: [u8; 4] = Self::FOURCC;
+
+    #[inline]
+    fn write(&self, head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        self.write(head, writer)
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::Write;
+
+use crate::{error::{ReadError, ReadErrorKind, WriteError, WriteErrorKind}, resize::Filter, Head};
+
+use super::{Body, ChunkWrite};
+
+/// A downscaled level of a multi-resolution image (mipmap). Its pixels are
+/// encoded like the BODY chunk, using the header of the file with the width
+/// and height of the level. Levels are ordered from largest to smallest, the
+/// BODY chunk being level 0.
+#[derive(Debug, Clone)]
+pub struct Levl {
+    width: u32,
+    height: u32,
+    filter: Filter,
+    body: Body,
+}
+
+impl Levl {
+    pub const FOURCC: [u8; 4] = *b"LEVL";
+    pub const HEAD_SIZE: usize = 9;
+
+    /// `body` has to contain `width * height` colors. `filter` is only
+    /// informational.
+    #[inline]
+    pub fn new(width: u32, height: u32, filter: Filter, body: Body) -> Self {
+        Self { width, height, filter, body }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The filter the level was created with.
+    #[inline]
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    #[inline]
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    #[inline]
+    pub fn body_mut(&mut self) -> &mut Body {
+        &mut self.body
+    }
+
+    #[inline]
+    pub fn into_body(self) -> Body {
+        self.body
+    }
+
+    /// Whether the level isn't larger than the image.
+    #[inline]
+    pub fn is_within(&self, head: &Head) -> bool {
+        self.width <= head.width() && self.height <= head.height()
+    }
+
+    pub fn read(bytes: &[u8], head: &Head) -> Result<Self, ReadError> {
+        if bytes.len() < Self::HEAD_SIZE {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("truncated LEVL chunk: {} < {}", bytes.len(), Self::HEAD_SIZE)));
+        }
+
+        let width  = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let height = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+
+        let Some(filter) = Filter::new(bytes[8]) else {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("illegal filter: {}", bytes[8])));
+        };
+
+        if width > head.width() || height > head.height() {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("level of {width} x {height} is larger than the image of {} x {}",
+                    head.width(), head.height())));
+        }
+
+        let body = Body::read(&bytes[Self::HEAD_SIZE..], &head.with_size(width, height))?;
+
+        Ok(Self { width, height, filter, body })
+    }
+
+    pub fn write(&self, head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        if !self.is_within(head) {
+            return Err(WriteError::with_message(
+                WriteErrorKind::InvalidParams,
+                format!("level of {} x {} is larger than the image of {} x {}",
+                    self.width, self.height, head.width(), head.height())));
+        }
+
+        let color_count = self.width as usize * self.height as usize;
+        if self.body.data().len() != color_count {
+            return Err(WriteError::with_message(
+                WriteErrorKind::InvalidParams,
+                format!("level of {} x {} needs {color_count} colors, but has: {}",
+                    self.width, self.height, self.body.data().len())));
+        }
+
+        writer.write_all(&self.width.to_le_bytes())?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        writer.write_all(&[self.filter as u8])?;
+
+        self.body.write(&head.with_size(self.width, self.height), writer)
+    }
+}
+
+impl ChunkWrite for Levl {
+    const FOURCC: [u8; 4] = Self::FOURCC;
+
+    #[inline]
+    fn write(&self, head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        self.write(head, writer)
+    }
+}
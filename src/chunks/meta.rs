@@ -1,19 +1,26 @@
 use core::str;
 use std::io::Write;
 
-use crate::{error::{IllegalMetaKey, ReadError, ReadErrorKind, WriteError}, Date, Head};
+use crate::{error::{IllegalLicense, IllegalMetaKey, ReadError, ReadErrorKind, WriteError, WriteErrorKind}, lang::{best_match_text, is_language_tag}, spdx::LicenseExpression, Date, Head};
 
 use super::ChunkWrite;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(u8)]
 pub enum MetaKey {
-    Title     = 1,
-    CreatedAt = 2,
-    Author    = 3,
-    License   = 4,
-    Links     = 5,
-    Comment   = 6,
+    Title      = 1,
+    CreatedAt  = 2,
+    Author     = 3,
+    License    = 4,
+    Links      = 5,
+    Comment    = 6,
+    Keywords   = 7,
+    Software   = 8,
+    Copyright  = 9,
+    ModifiedAt = 10,
+    Source     = 11,
+    LocalizedTitle   = 12,
+    LocalizedComment = 13,
 }
 
 impl TryFrom<u8> for MetaKey {
@@ -32,13 +39,29 @@ impl TryFrom<u8> for MetaKey {
             Ok(MetaKey::Links)
         } else if value == MetaKey::Comment as u8 {
             Ok(MetaKey::Comment)
+        } else if value == MetaKey::Keywords as u8 {
+            Ok(MetaKey::Keywords)
+        } else if value == MetaKey::Software as u8 {
+            Ok(MetaKey::Software)
+        } else if value == MetaKey::Copyright as u8 {
+            Ok(MetaKey::Copyright)
+        } else if value == MetaKey::ModifiedAt as u8 {
+            Ok(MetaKey::ModifiedAt)
+        } else if value == MetaKey::Source as u8 {
+            Ok(MetaKey::Source)
+        } else if value == MetaKey::LocalizedTitle as u8 {
+            Ok(MetaKey::LocalizedTitle)
+        } else if value == MetaKey::LocalizedComment as u8 {
+            Ok(MetaKey::LocalizedComment)
         } else {
             Err(IllegalMetaKey::with_message(value, format!("illegal meta key: {value}")))
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Meta {
     title: String,
     created_at: Date,
@@ -46,6 +69,18 @@ pub struct Meta {
     license: Vec<String>,
     links: Vec<String>,
     comment: String,
+    keywords: Vec<String>,
+    software: String,
+    copyright: String,
+    modified_at: Date,
+    source: String,
+    /// Titles in other languages as language tag and title.
+    localized_title: Vec<(String, String)>,
+    /// Comments in other languages as language tag and comment.
+    localized_comment: Vec<(String, String)>,
+    /// Entries with keys unknown to this version, kept so they can be written
+    /// back.
+    unknown: Vec<(u8, String)>,
 }
 
 impl Meta {
@@ -66,6 +101,8 @@ impl Meta {
         &self.author
     }
 
+    /// [SPDX license expressions](https://spdx.org/licenses/) like `MIT` or
+    /// `MIT OR Apache-2.0`, see [`Meta::check_licenses`].
     #[inline]
     pub fn license(&self) -> &[impl AsRef<str>] {
         &self.license
@@ -81,16 +118,123 @@ impl Meta {
         &self.comment
     }
 
+    #[inline]
+    pub fn keywords(&self) -> &[impl AsRef<str>] {
+        &self.keywords
+    }
+
+    /// Name and version of the program that wrote the image.
+    #[inline]
+    pub fn software(&self) -> &str {
+        &self.software
+    }
+
+    /// Copyright notice, e.g. `"© 2024 Jane Doe"`.
+    #[inline]
+    pub fn copyright(&self) -> &str {
+        &self.copyright
+    }
+
+    #[inline]
+    pub fn modified_at(&self) -> &Date {
+        &self.modified_at
+    }
+
+    /// Where the image was derived from, e.g. a URL.
+    #[inline]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Titles in other languages than the one of [`Meta::title`] as BCP 47
+    /// language tag and title.
+    #[inline]
+    pub fn localized_titles(&self) -> &[(String, String)] {
+        &self.localized_title
+    }
+
+    /// Comments in other languages than the one of [`Meta::comment`] as
+    /// BCP 47 language tag and comment.
+    #[inline]
+    pub fn localized_comments(&self) -> &[(String, String)] {
+        &self.localized_comment
+    }
+
+    /// The title best matching `locale`, see [`crate::lang::best_match`].
+    /// The untagged title is used if no localized title matches.
+    #[inline]
+    pub fn title_for(&self, locale: &str) -> &str {
+        best_match_text(locale, &self.title, &self.localized_title)
+    }
+
+    /// The comment best matching `locale`, see [`crate::lang::best_match`].
+    /// The untagged comment is used if no localized comment matches.
+    #[inline]
+    pub fn comment_for(&self, locale: &str) -> &str {
+        best_match_text(locale, &self.comment, &self.localized_comment)
+    }
+
+    /// Checks that every license entry is a valid SPDX license expression.
+    /// Reading only warns about invalid entries and writing doesn't check them,
+    /// use [`crate::XZIB::write_strict`] to reject them.
+    pub fn check_licenses(&self) -> Result<(), IllegalLicense> {
+        for license in &self.license {
+            LicenseExpression::parse(license)?;
+        }
+
+        Ok(())
+    }
+
+    /// Entries with unknown keys as key and value.
+    #[inline]
+    pub fn unknown(&self) -> &[(u8, String)] {
+        &self.unknown
+    }
+
     #[inline]
     pub fn set_title(&mut self, value: impl Into<String>) {
         self.title = value.into();
     }
 
+    /// Sets the title for the language `lang`, replacing an existing title of
+    /// the same language.
+    #[inline]
+    pub fn set_title_for(&mut self, lang: impl Into<String>, value: impl Into<String>) {
+        set_localized(&mut self.localized_title, lang.into(), value.into());
+    }
+
+    /// Sets the comment for the language `lang`, replacing an existing
+    /// comment of the same language.
+    #[inline]
+    pub fn set_comment_for(&mut self, lang: impl Into<String>, value: impl Into<String>) {
+        set_localized(&mut self.localized_comment, lang.into(), value.into());
+    }
+
     #[inline]
     pub fn set_created_at(&mut self, value: &Date) {
         self.created_at = *value;
     }
 
+    #[inline]
+    pub fn set_software(&mut self, value: impl Into<String>) {
+        self.software = value.into();
+    }
+
+    #[inline]
+    pub fn set_copyright(&mut self, value: impl Into<String>) {
+        self.copyright = value.into();
+    }
+
+    #[inline]
+    pub fn set_modified_at(&mut self, value: &Date) {
+        self.modified_at = *value;
+    }
+
+    #[inline]
+    pub fn set_source(&mut self, value: impl Into<String>) {
+        self.source = value.into();
+    }
+
     #[inline]
     pub fn title_mut(&mut self) -> &mut String {
         &mut self.title
@@ -121,6 +265,46 @@ impl Meta {
         &mut self.comment
     }
 
+    #[inline]
+    pub fn keywords_mut(&mut self) -> &mut Vec<String> {
+        &mut self.keywords
+    }
+
+    #[inline]
+    pub fn software_mut(&mut self) -> &mut String {
+        &mut self.software
+    }
+
+    #[inline]
+    pub fn copyright_mut(&mut self) -> &mut String {
+        &mut self.copyright
+    }
+
+    #[inline]
+    pub fn modified_at_mut(&mut self) -> &mut Date {
+        &mut self.modified_at
+    }
+
+    #[inline]
+    pub fn source_mut(&mut self) -> &mut String {
+        &mut self.source
+    }
+
+    #[inline]
+    pub fn localized_titles_mut(&mut self) -> &mut Vec<(String, String)> {
+        &mut self.localized_title
+    }
+
+    #[inline]
+    pub fn localized_comments_mut(&mut self) -> &mut Vec<(String, String)> {
+        &mut self.localized_comment
+    }
+
+    #[inline]
+    pub fn unknown_mut(&mut self) -> &mut Vec<(u8, String)> {
+        &mut self.unknown
+    }
+
     pub fn read(mut data: &[u8]) -> Result<Self, ReadError> {
         let mut title = String::new();
         let mut created_at = Date::default();
@@ -128,6 +312,14 @@ impl Meta {
         let mut license = Vec::new();
         let mut links = Vec::new();
         let mut comment = String::new();
+        let mut keywords = Vec::new();
+        let mut software = String::new();
+        let mut copyright = String::new();
+        let mut modified_at = Date::default();
+        let mut source = String::new();
+        let mut localized_title = Vec::new();
+        let mut localized_comment: Vec<(String, String)> = Vec::new();
+        let mut unknown = Vec::new();
 
         while !data.is_empty() {
             let Some(&key) = data.first() else {
@@ -154,12 +346,12 @@ impl Meta {
                     format!("illegal UTF-8 bytes in meta data value: {chunk:?}"),
                     Box::new(err)))
             };
-            let Ok(key) = MetaKey::try_from(key) else {
-                eprintln!("unknown meta key: {key}");
+            let Ok(meta_key) = MetaKey::try_from(key) else {
+                unknown.push((key, value.to_string()));
                 continue;
             };
 
-            match key {
+            match meta_key {
                 MetaKey::Title => {
                     title.clear();
                     title.push_str(value);
@@ -177,17 +369,59 @@ impl Meta {
                     created_at = match Date::parse(value) {
                         Ok(date) => date,
                         Err(err) => {
-                            eprint!("illegal created_at meta data value: {err}");
+                            eprintln!("illegal created_at meta data value: {err}");
                             continue;
                         }
                     };
                 }
                 MetaKey::License => {
+                    if let Err(err) = LicenseExpression::parse(value) {
+                        eprintln!("illegal license meta data value: {err}");
+                    }
                     license.push(value.to_string());
                 }
                 MetaKey::Links => {
                     links.push(value.to_string());
                 }
+                MetaKey::Keywords => {
+                    keywords.push(value.to_string());
+                }
+                MetaKey::Software => {
+                    software.clear();
+                    software.push_str(value);
+                }
+                MetaKey::Copyright => {
+                    copyright.clear();
+                    copyright.push_str(value);
+                }
+                MetaKey::ModifiedAt => {
+                    modified_at = match Date::parse(value) {
+                        Ok(date) => date,
+                        Err(err) => {
+                            eprintln!("illegal modified_at meta data value: {err}");
+                            continue;
+                        }
+                    };
+                }
+                MetaKey::Source => {
+                    source.clear();
+                    source.push_str(value);
+                }
+                MetaKey::LocalizedTitle | MetaKey::LocalizedComment => {
+                    let Some((lang, value)) = value.split_once('\t').filter(|(lang, _)| is_language_tag(lang)) else {
+                        eprintln!("illegal localized meta data value: {value:?}");
+                        continue;
+                    };
+
+                    if meta_key == MetaKey::LocalizedTitle {
+                        set_localized(&mut localized_title, lang.to_string(), value.to_string());
+                    } else if let Some((_, comment)) = localized_comment.iter_mut().find(|(other, _)| other.eq_ignore_ascii_case(lang)) {
+                        comment.push('\n');
+                        comment.push_str(value);
+                    } else {
+                        localized_comment.push((lang.to_string(), value.to_string()));
+                    }
+                }
             }
         }
 
@@ -198,6 +432,14 @@ impl Meta {
             comment,
             license,
             links,
+            keywords,
+            software,
+            copyright,
+            modified_at,
+            source,
+            localized_title,
+            localized_comment,
+            unknown,
         })
     }
     
@@ -208,6 +450,19 @@ impl Meta {
             writer.write_all(&[0])?;
         }
 
+        for (name, date) in [("created_at", &self.created_at), ("modified_at", &self.modified_at)] {
+            if date.is_null() {
+                continue;
+            }
+
+            if let Err(err) = date.validate() {
+                return Err(WriteError::with_all(
+                    WriteErrorKind::InvalidParams,
+                    format!("illegal {name} meta data value"),
+                    Box::new(err)));
+            }
+        }
+
         if !self.created_at.is_null() {
             writer.write_all(&[MetaKey::CreatedAt as u8])?;
             write!(writer, "{}", self.created_at)?;
@@ -238,10 +493,77 @@ impl Meta {
             writer.write_all(&[0])?;
         }
 
+        for keyword in &self.keywords {
+            writer.write_all(&[MetaKey::Keywords as u8])?;
+            writer.write_all(keyword.as_bytes())?;
+            writer.write_all(&[0])?;
+        }
+
+        if !self.software.is_empty() {
+            writer.write_all(&[MetaKey::Software as u8])?;
+            writer.write_all(self.software.as_bytes())?;
+            writer.write_all(&[0])?;
+        }
+
+        if !self.copyright.is_empty() {
+            writer.write_all(&[MetaKey::Copyright as u8])?;
+            writer.write_all(self.copyright.as_bytes())?;
+            writer.write_all(&[0])?;
+        }
+
+        if !self.modified_at.is_null() {
+            writer.write_all(&[MetaKey::ModifiedAt as u8])?;
+            write!(writer, "{}", self.modified_at)?;
+            writer.write_all(&[0])?;
+        }
+
+        if !self.source.is_empty() {
+            writer.write_all(&[MetaKey::Source as u8])?;
+            writer.write_all(self.source.as_bytes())?;
+            writer.write_all(&[0])?;
+        }
+
+        for (key, localized) in [(MetaKey::LocalizedTitle, &self.localized_title), (MetaKey::LocalizedComment, &self.localized_comment)] {
+            for (lang, value) in localized {
+                if !is_language_tag(lang) {
+                    return Err(WriteError::with_message(
+                        WriteErrorKind::InvalidParams,
+                        format!("illegal language tag of localized meta data value: {lang:?}")));
+                }
+
+                writer.write_all(&[key as u8])?;
+                writer.write_all(lang.as_bytes())?;
+                writer.write_all(b"\t")?;
+                writer.write_all(value.as_bytes())?;
+                writer.write_all(&[0])?;
+            }
+        }
+
+        for (key, value) in &self.unknown {
+            if *key == 0 || MetaKey::try_from(*key).is_ok() {
+                return Err(WriteError::with_message(
+                    WriteErrorKind::InvalidParams,
+                    format!("meta key {key} isn't an unknown key")));
+            }
+
+            writer.write_all(&[*key])?;
+            writer.write_all(value.as_bytes())?;
+            writer.write_all(&[0])?;
+        }
+
         Ok(())
     }
 }
 
+
+fn set_localized(localized: &mut Vec<(String, String)>, lang: String, value: String) {
+    if let Some(entry) = localized.iter_mut().find(|(other, _)| other.eq_ignore_ascii_case(&lang)) {
+        *entry = (lang, value);
+    } else {
+        localized.push((lang, value));
+    }
+}
+
 impl ChunkWrite for Meta {
     const FOURCC: [u8; 4] = Self::FOURCC;
 
@@ -250,3 +572,75 @@ impl ChunkWrite for Meta {
         self.write(writer)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(entries: &[(u8, &str)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (key, value) in entries {
+            data.push(*key);
+            data.extend_from_slice(value.as_bytes());
+            data.push(0);
+        }
+        data
+    }
+
+    fn rewrite(data: &[u8]) -> Vec<u8> {
+        let mut written = Vec::new();
+        Meta::read(data).unwrap().write(&mut written).unwrap();
+        written
+    }
+
+    #[test]
+    fn malformed_localized_values_are_dropped() {
+        let data = entries(&[
+            (MetaKey::Title as u8, "Title"),
+            (MetaKey::LocalizedTitle as u8, "no tab"),
+            (MetaKey::LocalizedTitle as u8, "not a tag	Titel"),
+            (MetaKey::LocalizedTitle as u8, "de	Titel"),
+            (MetaKey::LocalizedComment as u8, "	no tag"),
+            (200, "unknown"),
+        ]);
+
+        let meta = Meta::read(&data).unwrap();
+        assert_eq!(meta.localized_titles(), [("de".to_string(), "Titel".to_string())]);
+        assert!(meta.localized_comments().is_empty());
+        assert_eq!(meta.unknown(), [(200, "unknown".to_string())]);
+
+        let written = rewrite(&data);
+        assert_eq!(rewrite(&written), written);
+    }
+
+    #[test]
+    fn read_write_round_trip() {
+        // xorshift, so the test is reproducible without extra dependencies
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move |max: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % max
+        };
+
+        let alphabet = b"aZ09-_\t:+ T.";
+        for _ in 0..2000 {
+            let mut data = Vec::new();
+            for _ in 0..next(12) {
+                data.push(1 + next(16) as u8);
+                for _ in 0..next(24) {
+                    data.push(alphabet[next(alphabet.len() as u64) as usize]);
+                }
+                data.push(0);
+            }
+
+            let Ok(meta) = Meta::read(&data) else {
+                continue;
+            };
+            let mut written = Vec::new();
+            meta.write(&mut written).unwrap_or_else(|err| panic!("{data:?}: {err}"));
+            assert_eq!(rewrite(&written), written, "{data:?}");
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::io::Write;
+
+use crate::{color::{to_rgb_variant, ColorList}, error::{InvalidParams, ReadError, ReadErrorKind, WriteError, WriteErrorKind}, format::ColorModel, Head};
+
+use super::{Body, ChunkWrite};
+
+/// A small preview of the image. It has its own header, which describes its
+/// pixel format and size independent of the header of the file, followed by
+/// the pixels encoded like the BODY chunk. Thumbnails can't be indexed.
+#[derive(Debug, Clone)]
+pub struct Thmb {
+    head: Head,
+    body: Body,
+}
+
+impl Thmb {
+    pub const FOURCC: [u8; 4] = *b"THMB";
+    pub const HEAD_SIZE: usize = 12;
+
+    /// `body` has to contain `head.width() * head.height()` colors.
+    pub fn new(head: Head, body: Body) -> Result<Self, InvalidParams> {
+        if head.is_indexed() {
+            return Err(InvalidParams::with_message(
+                format!("thumbnails can't be indexed, but index_planes == {}", head.index_planes())));
+        }
+
+        let color_count = head.width() as u64 * head.height() as u64;
+        if body.data().len() as u64 != color_count {
+            return Err(InvalidParams::with_message(
+                format!("thumbnail of {} x {} needs {color_count} colors, but has: {}",
+                    head.width(), head.height(), body.data().len())));
+        }
+
+        Ok(Self { head, body })
+    }
+
+    /// The header of the thumbnail.
+    #[inline]
+    pub fn head(&self) -> &Head {
+        &self.head
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.head.width()
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.head.height()
+    }
+
+    #[inline]
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    #[inline]
+    pub fn into_body(self) -> Body {
+        self.body
+    }
+
+    /// The colors converted from the color model defined in the header of the
+    /// thumbnail to RGB (or L or RGBA) and with straight alpha.
+    pub fn rgb_data(&self) -> Result<ColorList, InvalidParams> {
+        let color_model = self.head.color_model()?;
+
+        let mut data = if color_model == ColorModel::Rgb {
+            self.body.data().clone()
+        } else {
+            to_rgb_variant(self.body.data(), color_model)?.into_owned()
+        };
+
+        if self.head.is_premultiplied() {
+            data.unpremultiply_alpha();
+        }
+
+        Ok(data)
+    }
+
+    pub fn read(bytes: &[u8]) -> Result<Self, ReadError> {
+        if bytes.len() < Self::HEAD_SIZE {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("truncated THMB chunk: {} < {}", bytes.len(), Self::HEAD_SIZE)));
+        }
+
+        let head = Head::read_fields(&mut &bytes[..Self::HEAD_SIZE])?;
+        head.validate()?;
+
+        if head.is_indexed() {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("thumbnails can't be indexed, but index_planes == {}", head.index_planes())));
+        }
+
+        let body = Body::read(&bytes[Self::HEAD_SIZE..], &head)?;
+
+        Ok(Self { head, body })
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), WriteError> {
+        let color_count = self.head.width() as u64 * self.head.height() as u64;
+        if self.body.data().len() as u64 != color_count {
+            return Err(WriteError::with_message(
+                WriteErrorKind::InvalidParams,
+                format!("thumbnail of {} x {} needs {color_count} colors, but has: {}",
+                    self.head.width(), self.head.height(), self.body.data().len())));
+        }
+
+        self.head.write_fields(writer)?;
+
+        self.body.write(&self.head, writer)
+    }
+}
+
+impl ChunkWrite for Thmb {
+    const FOURCC: [u8; 4] = Self::FOURCC;
+
+    #[inline]
+    fn write(&self, _head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        self.write(writer)
+    }
+}
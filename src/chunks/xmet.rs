@@ -1,31 +1,276 @@
 use core::str;
-use std::{collections::HashMap, io::Write};
+use std::{collections::BTreeMap, io::Write};
 
-use crate::{error::{ReadError, ReadErrorKind, WriteError}, Head};
+use crate::{error::{InvalidParams, ReadError, ReadErrorKind, WriteError}, lang::{best_match, is_language_tag}, Head};
 
 use super::ChunkWrite;
 
+macro_rules! dublin_core_text {
+    ($($(#[$attr:meta])* $getter:ident, $setter:ident, $name:literal;)*) => {
+        $(
+            $(#[$attr])*
+            #[inline]
+            pub fn $getter(&self) -> Option<&str> {
+                self.get_by_namespace(Self::DUBLIN_CORE, $name)?.first().map(String::as_str)
+            }
+
+            #[inline]
+            pub fn $setter(&mut self, value: impl Into<String>) {
+                self.set_by_namespace(Self::DUBLIN_CORE, $name, vec![value.into()]);
+            }
+        )*
+    };
+}
+
+macro_rules! dublin_core_list {
+    ($($(#[$attr:meta])* $getter:ident, $setter:ident, $name:literal;)*) => {
+        $(
+            $(#[$attr])*
+            #[inline]
+            pub fn $getter(&self) -> &[String] {
+                self.get_by_namespace(Self::DUBLIN_CORE, $name).unwrap_or_default()
+            }
 
-#[derive(Debug, Clone)]
+            #[inline]
+            pub fn $setter(&mut self, values: impl IntoIterator<Item = impl Into<String>>) {
+                self.set_by_namespace(Self::DUBLIN_CORE, $name, values.into_iter().map(Into::into).collect());
+            }
+        )*
+    };
+}
+
+/// Extensible metadata. Keys without a prefix are
+/// [Dublin Core](https://www.dublincore.org/specifications/dublin-core/dces/)
+/// elements, keys like `x:foo` use the namespace declared by the `xmlns:x`
+/// entry. Keys like `title@de` hold the value of `title` in the language of
+/// the BCP 47 tag after the `@`, the untagged key is the default value.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Xmet {
-    data: HashMap<String, Vec<String>>,
+    data: BTreeMap<String, Vec<String>>,
 }
 
 impl Xmet {
     pub const FOURCC: [u8; 4] = *b"XMET";
+    /// Namespace of the Dublin Core elements.
+    pub const DUBLIN_CORE: &str = "http://purl.org/dc/elements/1.1/";
 
     #[inline]
-    pub fn data(&self) -> &HashMap<String, Vec<String>> {
+    pub fn new(data: BTreeMap<String, Vec<String>>) -> Self {
+        Self { data }
+    }
+
+    #[inline]
+    pub fn data(&self) -> &BTreeMap<String, Vec<String>> {
         &self.data
     }
 
     #[inline]
-    pub fn data_mut(&mut self) -> &mut HashMap<String, Vec<String>> {
+    pub fn data_mut(&mut self) -> &mut BTreeMap<String, Vec<String>> {
         &mut self.data
     }
 
+    #[inline]
+    pub fn get(&self, key: &str) -> Option<&[String]> {
+        self.data.get(key).map(Vec::as_slice)
+    }
+
+    /// The values of `key` in the language best matching `locale`, see
+    /// [`best_match`]. Falls back to the untagged values.
+    pub fn get_localized(&self, key: &str, locale: &str) -> Option<&[String]> {
+        let candidates = self.data.iter().filter_map(|(other, values)| {
+            if other == key {
+                return Some((None, values.as_slice()));
+            }
+
+            let lang = other.strip_prefix(key)?.strip_prefix('@')?;
+            Some((Some(lang), values.as_slice()))
+        });
+
+        best_match(locale, candidates)
+    }
+
+    /// Sets the values of `key` in the language `lang`, or the untagged
+    /// values if `lang` is `None`. Removes the entry if `values` is empty.
+    pub fn set_localized(&mut self, key: &str, lang: Option<&str>, values: impl IntoIterator<Item = impl Into<String>>) {
+        let key = match lang {
+            Some(lang) => {
+                // replace the entry even if the case of the tag differs
+                let prefix = format!("{key}@");
+                let existing = self.data.keys()
+                    .find(|other| other.strip_prefix(&prefix).is_some_and(|other| other.eq_ignore_ascii_case(lang)))
+                    .cloned();
+                if let Some(existing) = existing {
+                    self.data.remove(&existing);
+                }
+                format!("{key}@{lang}")
+            }
+            None => key.to_owned(),
+        };
+
+        let values: Vec<String> = values.into_iter().map(Into::into).collect();
+        if values.is_empty() {
+            self.data.remove(&key);
+        } else {
+            self.data.insert(key, values);
+        }
+    }
+
+    /// The language tags of the language variants of `key`.
+    pub fn languages<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.data.keys().filter_map(move |other| other.strip_prefix(key)?.strip_prefix('@'))
+    }
+
+    /// The Dublin Core title best matching `locale`.
+    #[inline]
+    pub fn title_for(&self, locale: &str) -> Option<&str> {
+        self.get_localized("title", locale)?.first().map(String::as_str)
+    }
+
+    /// The Dublin Core description best matching `locale`.
+    #[inline]
+    pub fn description_for(&self, locale: &str) -> Option<&str> {
+        self.get_localized("description", locale)?.first().map(String::as_str)
+    }
+
+    /// The Dublin Core rights best matching `locale`.
+    #[inline]
+    pub fn rights_for(&self, locale: &str) -> Option<&str> {
+        self.get_localized("rights", locale)?.first().map(String::as_str)
+    }
+
+    /// The namespace declared for `prefix`. The empty prefix is Dublin Core.
+    pub fn namespace(&self, prefix: &str) -> Option<&str> {
+        if prefix.is_empty() {
+            return Some(Self::DUBLIN_CORE);
+        }
+
+        self.data.get(&format!("xmlns:{prefix}"))?.first().map(String::as_str)
+    }
+
+    /// All declared prefixes and their namespaces.
+    pub fn namespaces(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.data.iter().filter_map(|(key, values)| {
+            let prefix = key.strip_prefix("xmlns:")?;
+            Some((prefix, values.first()?.as_str()))
+        })
+    }
+
+    /// Namespace and local name of `key`. `None` for `xmlns:` entries and
+    /// undeclared prefixes.
+    pub fn resolve<'a>(&'a self, key: &'a str) -> Option<(&'a str, &'a str)> {
+        match key.split_once(':') {
+            Some(("xmlns", _)) => None,
+            Some((prefix, local)) => Some((self.namespace(prefix)?, local)),
+            None => Some((Self::DUBLIN_CORE, key)),
+        }
+    }
+
+    /// The values of the entry with the given namespace and local name,
+    /// independent of the prefix used for it.
+    pub fn get_by_namespace(&self, namespace: &str, local: &str) -> Option<&[String]> {
+        self.data.iter()
+            .find(|(key, _)| self.resolve(key) == Some((namespace, local)))
+            .map(|(_, values)| values.as_slice())
+    }
+
+    /// Replaces all entries with the given namespace and local name.
+    /// Declares a prefix like `ns1` if the namespace isn't declared yet.
+    /// Removes the entry if `values` is empty.
+    pub fn set_by_namespace(&mut self, namespace: &str, local: &str, values: Vec<String>) {
+        let keys: Vec<String> = self.data.keys()
+            .filter(|key| self.resolve(key) == Some((namespace, local)))
+            .cloned()
+            .collect();
+
+        for key in keys {
+            self.data.remove(&key);
+        }
+
+        if values.is_empty() {
+            return;
+        }
+
+        let key = if namespace == Self::DUBLIN_CORE {
+            local.to_owned()
+        } else {
+            let declared = self.namespaces()
+                .find(|&(_, declared)| declared == namespace)
+                .map(|(prefix, _)| prefix.to_owned());
+
+            let prefix = match declared {
+                Some(prefix) => prefix,
+                None => {
+                    let prefix = (1..).map(|index| format!("ns{index}"))
+                        .find(|prefix| !self.data.contains_key(&format!("xmlns:{prefix}")))
+                        .unwrap_or_default();
+                    self.data.insert(format!("xmlns:{prefix}"), vec![namespace.to_owned()]);
+                    prefix
+                }
+            };
+
+            format!("{prefix}:{local}")
+        };
+
+        self.data.insert(key, values);
+    }
+
+    /// Checks that every used prefix is declared by exactly one non-empty
+    /// `xmlns:` entry and that language variants have legal tags.
+    pub fn validate(&self) -> Result<(), InvalidParams> {
+        for (key, values) in &self.data {
+            if key.rsplit_once('@').is_some_and(|(name, lang)| name.starts_with("xmlns:") || !is_language_tag(lang)) {
+                return Err(InvalidParams::with_message(
+                    format!("illegal language variant key: {key:?}")));
+            }
+
+            if let Some(prefix) = key.strip_prefix("xmlns:") {
+                if prefix.is_empty() || prefix.contains(':') {
+                    return Err(InvalidParams::with_message(
+                        format!("illegal namespace prefix: {key:?}")));
+                }
+
+                if values.len() != 1 || values[0].is_empty() {
+                    return Err(InvalidParams::with_message(
+                        format!("{key} has to declare exactly one namespace, but has: {values:?}")));
+                }
+            } else if key.split_once(':').is_some_and(|(prefix, _)| !self.data.contains_key(&format!("xmlns:{prefix}"))) {
+                return Err(InvalidParams::with_message(
+                    format!("undeclared namespace prefix of key: {key:?}")));
+            }
+        }
+
+        Ok(())
+    }
+
+    dublin_core_text! {
+        title,       set_title,       "title";
+        description, set_description, "description";
+        rights,      set_rights,      "rights";
+        /// The MIME type, e.g. `"image/x-xzib"`.
+        format,      set_format,      "format";
+        identifier,  set_identifier,  "identifier";
+        source,      set_source,      "source";
+        coverage,    set_coverage,    "coverage";
+    }
+
+    dublin_core_list! {
+        creator,     set_creator,     "creator";
+        contributor, set_contributor, "contributor";
+        publisher,   set_publisher,   "publisher";
+        subject,     set_subject,     "subject";
+        /// Dates as [W3CDTF](https://www.w3.org/TR/NOTE-datetime), e.g. `"2024-05-06"`.
+        date,        set_date,        "date";
+        /// Languages as RFC 5646 tags, e.g. `"en-US"`.
+        language,    set_language,    "language";
+        relation,    set_relation,    "relation";
+        /// The `type` element, e.g. `"Image"`.
+        dc_type,     set_dc_type,     "type";
+    }
+
     pub fn read(mut bytes: &[u8]) -> Result<Self, ReadError> {
-        let mut data: HashMap<String, Vec<String>> = HashMap::new();
+        let mut data: BTreeMap<String, Vec<String>> = BTreeMap::new();
 
         while !bytes.is_empty() {
             let chunk = if let Some(chunk) = bytes.split(|b| *b == 0).next() {
@@ -74,6 +319,8 @@ impl Xmet {
         })
     }
 
+    /// Writes the entries sorted by key, so equal metadata always gives the
+    /// same bytes.
     pub fn write(&self, writer: &mut impl Write) -> Result<(), WriteError> {
         for (key, values) in &self.data {
             for value in values {
@@ -0,0 +1,55 @@
+use std::io::Write;
+
+use crate::{error::{ReadError, ReadErrorKind, WriteError}, Head};
+
+use super::ChunkWrite;
+
+/// A raw XMP packet (RDF/XML), stored as is. Use [`super::Xmet::from_xmp`]
+/// to read its properties.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xpkt {
+    packet: String,
+}
+
+impl Xpkt {
+    pub const FOURCC: [u8; 4] = *b"XPKT";
+
+    #[inline]
+    pub fn new(packet: impl Into<String>) -> Self {
+        Self { packet: packet.into() }
+    }
+
+    #[inline]
+    pub fn packet(&self) -> &str {
+        &self.packet
+    }
+
+    #[inline]
+    pub fn into_packet(self) -> String {
+        self.packet
+    }
+
+    pub fn read(bytes: &[u8]) -> Result<Self, ReadError> {
+        match std::str::from_utf8(bytes) {
+            Ok(packet) => Ok(Self { packet: packet.to_owned() }),
+            Err(err) => Err(ReadError::with_all(
+                ReadErrorKind::BrokenFile,
+                "illegal UTF-8 bytes in XPKT chunk",
+                Box::new(err))),
+        }
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), WriteError> {
+        writer.write_all(self.packet.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl ChunkWrite for Xpkt {
+    const FOURCC: [u8; 4] = Self::FOURCC;
+
+    #[inline]
+    fn write(&self, _head: &Head, writer: &mut impl Write) -> Result<(), WriteError> {
+        self.write(writer)
+    }
+}
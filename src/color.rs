@@ -1,6 +1,6 @@
-use std::{io::Write, ops::{Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, Mul, MulAssign, Shl, ShlAssign, Shr, ShrAssign, Sub, SubAssign}};
+use std::{borrow::Cow, io::Write, ops::{Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, Mul, MulAssign, Shl, ShlAssign, Shr, ShrAssign, Sub, SubAssign}};
 
-use crate::{error::{ReadError, ReadErrorKind, WriteError, WriteErrorKind}, format::{ChannelValueType, ColorType}};
+use crate::{error::{InvalidParams, ReadError, ReadErrorKind, WriteError, WriteErrorKind}, format::{ChannelValueType, ColorModel, ColorType}};
 
 
 pub trait ChannelValue
@@ -16,6 +16,7 @@ where Self: Sized,
       Self: std::default::Default,
       Self: Clone,
       Self: Copy,
+      Self: PartialEq,
 {
     const ZERO: Self;
     const ONE: Self;
@@ -26,6 +27,10 @@ where Self: Sized,
     fn from_bytes(bytes: &[u8]) -> Option<Self>;
     fn write_to(self, writer: impl Write) -> std::io::Result<()>;
     fn as_f32(self) -> f32;
+    fn as_f64(self) -> f64;
+
+    /// Inverse of [`ChannelValue::as_f64()`]. Integer values are clamped to `0.0 ..= 1.0`.
+    fn from_f64(value: f64) -> Self;
 }
 
 pub trait IntChannelValue
@@ -50,15 +55,29 @@ where Self: ChannelValue,
 {
     #[inline]
     fn extend(self, planes: u8) -> Self {
-        debug_assert!(planes as u32 >= (Self::BITS / 2) && planes as u32 <= Self::BITS);
+        debug_assert!(planes > 0 && planes as u32 <= Self::BITS);
+
+        let planes = planes as u32;
+        if planes >= Self::BITS {
+            return self;
+        }
 
-        let lshift = Self::BITS - planes as u32;
-        let rshift = (planes as u32 * 2) - Self::BITS;
+        // repeat the bit pattern until all bits are filled
+        let mut value = self << (Self::BITS - planes);
+        let mut shift = planes;
+        while shift < Self::BITS {
+            value |= value >> shift;
+            shift *= 2;
+        }
 
-        (self << lshift) | (self >> rshift)
+        value
     }
 
     fn least_significant_byte(self) -> u8;
+    fn as_u128(self) -> u128;
+
+    /// Truncates the value if it doesn't fit.
+    fn from_u128(value: u128) -> Self;
 }
 
 impl ChannelValue for u8 {
@@ -83,6 +102,16 @@ impl ChannelValue for u8 {
     fn as_f32(self) -> f32 {
         self as f32 / Self::MAX as f32
     }
+
+    #[inline]
+    fn as_f64(self) -> f64 {
+        self as f64 / Self::MAX as f64
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        (value.clamp(0.0, 1.0) * Self::MAX as f64).round() as Self
+    }
 }
 
 impl ChannelValue for u16 {
@@ -107,6 +136,16 @@ impl ChannelValue for u16 {
     fn as_f32(self) -> f32 {
         self as f32 / Self::MAX as f32
     }
+
+    #[inline]
+    fn as_f64(self) -> f64 {
+        self as f64 / Self::MAX as f64
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        (value.clamp(0.0, 1.0) * Self::MAX as f64).round() as Self
+    }
 }
 
 impl ChannelValue for u32 {
@@ -131,6 +170,16 @@ impl ChannelValue for u32 {
     fn as_f32(self) -> f32 {
         self as f32 / Self::MAX as f32
     }
+
+    #[inline]
+    fn as_f64(self) -> f64 {
+        self as f64 / Self::MAX as f64
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        (value.clamp(0.0, 1.0) * Self::MAX as f64).round() as Self
+    }
 }
 
 impl ChannelValue for u64 {
@@ -155,6 +204,16 @@ impl ChannelValue for u64 {
     fn as_f32(self) -> f32 {
         self as f32 / Self::MAX as f32
     }
+
+    #[inline]
+    fn as_f64(self) -> f64 {
+        self as f64 / Self::MAX as f64
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        (value.clamp(0.0, 1.0) * Self::MAX as f64).round() as Self
+    }
 }
 
 impl ChannelValue for u128 {
@@ -179,6 +238,16 @@ impl ChannelValue for u128 {
     fn as_f32(self) -> f32 {
         self as f32 / Self::MAX as f32
     }
+
+    #[inline]
+    fn as_f64(self) -> f64 {
+        self as f64 / Self::MAX as f64
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        (value.clamp(0.0, 1.0) * Self::MAX as f64).round() as Self
+    }
 }
 
 impl IntChannelValue for u8 {
@@ -207,6 +276,9 @@ impl IntChannelValue for u8 {
             7 => {
                 self << 1 | self >> 6
             }
+            8 => {
+                self
+            }
             _ => {
                 debug_assert!(false, "illegal planes for u8: {planes}");
                 self
@@ -218,6 +290,16 @@ impl IntChannelValue for u8 {
     fn least_significant_byte(self) -> u8 {
         self as u8
     }
+
+    #[inline]
+    fn as_u128(self) -> u128 {
+        self as u128
+    }
+
+    #[inline]
+    fn from_u128(value: u128) -> Self {
+        value as Self
+    }
 }
 
 impl IntChannelValue for u16 {
@@ -225,6 +307,16 @@ impl IntChannelValue for u16 {
     fn least_significant_byte(self) -> u8 {
         self as u8
     }
+
+    #[inline]
+    fn as_u128(self) -> u128 {
+        self as u128
+    }
+
+    #[inline]
+    fn from_u128(value: u128) -> Self {
+        value as Self
+    }
 }
 
 impl IntChannelValue for u32 {
@@ -232,6 +324,16 @@ impl IntChannelValue for u32 {
     fn least_significant_byte(self) -> u8 {
         self as u8
     }
+
+    #[inline]
+    fn as_u128(self) -> u128 {
+        self as u128
+    }
+
+    #[inline]
+    fn from_u128(value: u128) -> Self {
+        value as Self
+    }
 }
 
 impl IntChannelValue for u64 {
@@ -239,6 +341,16 @@ impl IntChannelValue for u64 {
     fn least_significant_byte(self) -> u8 {
         self as u8
     }
+
+    #[inline]
+    fn as_u128(self) -> u128 {
+        self as u128
+    }
+
+    #[inline]
+    fn from_u128(value: u128) -> Self {
+        value as Self
+    }
 }
 
 impl IntChannelValue for u128 {
@@ -246,6 +358,16 @@ impl IntChannelValue for u128 {
     fn least_significant_byte(self) -> u8 {
         self as u8
     }
+
+    #[inline]
+    fn as_u128(self) -> u128 {
+        self
+    }
+
+    #[inline]
+    fn from_u128(value: u128) -> Self {
+        value
+    }
 }
 
 // TODO: how to #[cfg()] check this?
@@ -274,6 +396,16 @@ impl ChannelValue for f32 {
     fn as_f32(self) -> f32 {
         self
     }
+
+    #[inline]
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
 }
 
 impl ChannelValue for f64 {
@@ -298,6 +430,16 @@ impl ChannelValue for f64 {
     fn as_f32(self) -> f32 {
         self as f32
     }
+
+    #[inline]
+    fn as_f64(self) -> f64 {
+        self
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        value
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -633,6 +775,172 @@ impl ColorList {
             ChannelVariant::F64 (data) => data.color_type(),
         }
     }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        match self {
+            ChannelVariant::U8  (data) => data.len(),
+            ChannelVariant::U16 (data) => data.len(),
+            ChannelVariant::U32 (data) => data.len(),
+            ChannelVariant::U64 (data) => data.len(),
+            ChannelVariant::U128(data) => data.len(),
+            ChannelVariant::F32 (data) => data.len(),
+            ChannelVariant::F64 (data) => data.len(),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Moves all colors of `other` to the end of `self`. Both need to have the
+    /// same channel value type and color type.
+    pub fn append(&mut self, other: &mut ColorList) -> Result<(), InvalidParams> {
+        match (self, other) {
+            (ChannelVariant::U8  (data), ChannelVariant::U8  (other)) => data.append(other),
+            (ChannelVariant::U16 (data), ChannelVariant::U16 (other)) => data.append(other),
+            (ChannelVariant::U32 (data), ChannelVariant::U32 (other)) => data.append(other),
+            (ChannelVariant::U64 (data), ChannelVariant::U64 (other)) => data.append(other),
+            (ChannelVariant::U128(data), ChannelVariant::U128(other)) => data.append(other),
+            (ChannelVariant::F32 (data), ChannelVariant::F32 (other)) => data.append(other),
+            (ChannelVariant::F64 (data), ChannelVariant::F64 (other)) => data.append(other),
+            (data, other) => Err(InvalidParams::with_message(
+                format!("cannot append {} colors to {} colors",
+                    other.channel_value_type(), data.channel_value_type()))),
+        }
+    }
+
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        match self {
+            ChannelVariant::U8  (data) => data.truncate(len),
+            ChannelVariant::U16 (data) => data.truncate(len),
+            ChannelVariant::U32 (data) => data.truncate(len),
+            ChannelVariant::U64 (data) => data.truncate(len),
+            ChannelVariant::U128(data) => data.truncate(len),
+            ChannelVariant::F32 (data) => data.truncate(len),
+            ChannelVariant::F64 (data) => data.truncate(len),
+        }
+    }
+
+    /// Converts straight alpha to premultiplied alpha. Does nothing for colors
+    /// without an alpha channel.
+    pub fn premultiply_alpha(&mut self) {
+        match self {
+            ChannelVariant::U8  (data) => data.premultiply_alpha(),
+            ChannelVariant::U16 (data) => data.premultiply_alpha(),
+            ChannelVariant::U32 (data) => data.premultiply_alpha(),
+            ChannelVariant::U64 (data) => data.premultiply_alpha(),
+            ChannelVariant::U128(data) => data.premultiply_alpha(),
+            ChannelVariant::F32 (data) => data.premultiply_alpha(),
+            ChannelVariant::F64 (data) => data.premultiply_alpha(),
+        }
+    }
+
+    /// Converts premultiplied alpha to straight alpha. Does nothing for colors
+    /// without an alpha channel.
+    pub fn unpremultiply_alpha(&mut self) {
+        match self {
+            ChannelVariant::U8  (data) => data.unpremultiply_alpha(),
+            ChannelVariant::U16 (data) => data.unpremultiply_alpha(),
+            ChannelVariant::U32 (data) => data.unpremultiply_alpha(),
+            ChannelVariant::U64 (data) => data.unpremultiply_alpha(),
+            ChannelVariant::U128(data) => data.unpremultiply_alpha(),
+            ChannelVariant::F32 (data) => data.unpremultiply_alpha(),
+            ChannelVariant::F64 (data) => data.unpremultiply_alpha(),
+        }
+    }
+}
+
+impl<C: ChannelValue> ColorVariant<C, ColorVecDataInner> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        match self {
+            Self::L   (data) => data.len(),
+            Self::La  (data) => data.len(),
+            Self::Rgb (data) => data.len(),
+            Self::Rgba(data) => data.len(),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn append(&mut self, other: &mut Self) -> Result<(), InvalidParams> {
+        match (self, other) {
+            (Self::L   (data), Self::L   (other)) => data.append(other),
+            (Self::La  (data), Self::La  (other)) => data.append(other),
+            (Self::Rgb (data), Self::Rgb (other)) => data.append(other),
+            (Self::Rgba(data), Self::Rgba(other)) => data.append(other),
+            (data, other) => return Err(InvalidParams::with_message(
+                format!("cannot append {} colors to {} colors",
+                    other.color_type(), data.color_type()))),
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        match self {
+            Self::L   (data) => data.truncate(len),
+            Self::La  (data) => data.truncate(len),
+            Self::Rgb (data) => data.truncate(len),
+            Self::Rgba(data) => data.truncate(len),
+        }
+    }
+
+    #[inline]
+    pub fn premultiply_alpha(&mut self) {
+        match self {
+            Self::La  (data) => premultiply_alpha(data),
+            Self::Rgba(data) => premultiply_alpha(data),
+            Self::L(_) | Self::Rgb(_) => {}
+        }
+    }
+
+    #[inline]
+    pub fn unpremultiply_alpha(&mut self) {
+        match self {
+            Self::La  (data) => unpremultiply_alpha(data),
+            Self::Rgba(data) => unpremultiply_alpha(data),
+            Self::L(_) | Self::Rgb(_) => {}
+        }
+    }
+}
+
+/// The last channel of the colors is the alpha channel.
+pub fn premultiply_alpha<C: ChannelValue, Col: Color<C>>(colors: &mut [Col]) {
+    for color in colors {
+        let channels = color.channels_mut();
+        let Some((&mut alpha, channels)) = channels.split_last_mut() else {
+            continue;
+        };
+        let alpha = alpha.as_f64();
+        for channel in channels {
+            *channel = C::from_f64(channel.as_f64() * alpha);
+        }
+    }
+}
+
+/// The last channel of the colors is the alpha channel.
+pub fn unpremultiply_alpha<C: ChannelValue, Col: Color<C>>(colors: &mut [Col]) {
+    for color in colors {
+        let channels = color.channels_mut();
+        let Some((&mut alpha, channels)) = channels.split_last_mut() else {
+            continue;
+        };
+        let alpha = alpha.as_f64();
+        for channel in channels {
+            *channel = if alpha == 0.0 {
+                C::ZERO
+            } else {
+                C::from_f64(channel.as_f64() / alpha)
+            };
+        }
+    }
 }
 
 pub fn read_colors_into<Color, ChannelValue>(mut bytes: &[u8], colors: &mut Vec<Color>)
@@ -849,8 +1157,9 @@ where Color: crate::color::Color<u8>
             if bit == 8 {
                 writer.write_all(std::slice::from_ref(&byte))?;
                 byte = 0;
+                bit = 0;
             }
-            byte |= channel << bit;
+            byte |= (channel >> 7) << bit;
             bit += 1;
         }
     }
@@ -883,10 +1192,10 @@ where Color: crate::color::Color<u8>
         for &channel in color.channels() {
             if bit == 8 {
                 writer.write_all(std::slice::from_ref(&byte))?;
-                byte = channel << 4;
-            } else {
-                byte |= channel;
+                byte = 0;
+                bit = 0;
             }
+            byte |= (channel >> 4) << bit;
             bit += 4;
         }
     }
@@ -919,6 +1228,8 @@ where ChannelValue: crate::color::ChannelValue,
     Ok(())
 }
 
+/// Out of range indices are mapped to the default color. Use
+/// [`find_invalid_index()`] to validate the indices first.
 pub fn apply_palette<ChannelValue: self::ChannelValue, Color: self::Color<ChannelValue>>(img: &[impl TryInto<usize> + Copy], palette: &[Color]) -> Vec<Color> {
     let mut output = Vec::with_capacity(img.len());
 
@@ -957,3 +1268,170 @@ pub fn apply_palette_variant(img: &[impl TryInto<usize> + Copy], palette: &Color
         ChannelVariant::F64 (palette) => ChannelVariant::F64 (apply_palette_variant_inner(img, palette)),
     }
 }
+
+/// Position and value of the first index that is out of range for a palette
+/// of `color_count` colors.
+pub fn find_invalid_index<I: IntChannelValue>(indices: &[I], color_count: usize) -> Option<(usize, u128)> {
+    let color_count = color_count as u128;
+    indices.iter().enumerate()
+        .map(|(offset, index)| (offset, index.as_u128()))
+        .find(|&(_, index)| index >= color_count)
+}
+
+/// Like [`find_invalid_index()`], but for the data of a BODY chunk. Only
+/// integer L colors are considered to be indices.
+pub fn find_invalid_index_variant(indices: &ColorList, color_count: usize) -> Option<(usize, u128)> {
+    match indices {
+        ChannelVariant::U8  (ColorVariant::L(indices)) => find_invalid_index(indices, color_count),
+        ChannelVariant::U16 (ColorVariant::L(indices)) => find_invalid_index(indices, color_count),
+        ChannelVariant::U32 (ColorVariant::L(indices)) => find_invalid_index(indices, color_count),
+        ChannelVariant::U64 (ColorVariant::L(indices)) => find_invalid_index(indices, color_count),
+        ChannelVariant::U128(ColorVariant::L(indices)) => find_invalid_index(indices, color_count),
+        _ => None,
+    }
+}
+
+/// Replaces all indices that are out of range for a palette of `color_count`
+/// colors.
+pub fn replace_invalid_indices<I: IntChannelValue>(indices: &mut [I], color_count: usize, replacement: I) {
+    let color_count = color_count as u128;
+    for index in indices {
+        if index.as_u128() >= color_count {
+            *index = replacement;
+        }
+    }
+}
+
+/// Like [`replace_invalid_indices()`], but for the data of a BODY chunk. The
+/// replacement is truncated to the channel value type of the indices.
+pub fn replace_invalid_indices_variant(indices: &mut ColorList, color_count: usize, replacement: usize) {
+    let replacement = replacement as u128;
+    match indices {
+        ChannelVariant::U8  (ColorVariant::L(indices)) => replace_invalid_indices(indices, color_count, u8::from_u128(replacement)),
+        ChannelVariant::U16 (ColorVariant::L(indices)) => replace_invalid_indices(indices, color_count, u16::from_u128(replacement)),
+        ChannelVariant::U32 (ColorVariant::L(indices)) => replace_invalid_indices(indices, color_count, u32::from_u128(replacement)),
+        ChannelVariant::U64 (ColorVariant::L(indices)) => replace_invalid_indices(indices, color_count, u64::from_u128(replacement)),
+        ChannelVariant::U128(ColorVariant::L(indices)) => replace_invalid_indices(indices, color_count, replacement),
+        _ => {}
+    }
+}
+
+// YCbCr as used by JPEG/JFIF (full range ITU-R BT.601). Cb and Cr are offset
+// by 0.5 for integer and floating point values alike.
+#[inline]
+pub fn ycbcr_to_rgb<C: ChannelValue>(colors: &[Rgb<C>]) -> Vec<Rgb<C>> {
+    colors.iter().map(|&Rgb([y, cb, cr])| {
+        let y  = y.as_f64();
+        let cb = cb.as_f64() - 0.5;
+        let cr = cr.as_f64() - 0.5;
+
+        Rgb([
+            C::from_f64(y + 1.402 * cr),
+            C::from_f64(y - 0.344136 * cb - 0.714136 * cr),
+            C::from_f64(y + 1.772 * cb),
+        ])
+    }).collect()
+}
+
+#[inline]
+pub fn rgb_to_ycbcr<C: ChannelValue>(colors: &[Rgb<C>]) -> Vec<Rgb<C>> {
+    colors.iter().map(|&Rgb([r, g, b])| {
+        let r = r.as_f64();
+        let g = g.as_f64();
+        let b = b.as_f64();
+
+        Rgb([
+            C::from_f64(        0.299    * r + 0.587    * g + 0.114    * b),
+            C::from_f64(0.5 -   0.168736 * r - 0.331264 * g + 0.5      * b),
+            C::from_f64(0.5 +   0.5      * r - 0.418688 * g - 0.081312 * b),
+        ])
+    }).collect()
+}
+
+#[inline]
+pub fn cmyk_to_rgb<C: ChannelValue>(colors: &[Rgba<C>]) -> Vec<Rgb<C>> {
+    colors.iter().map(|&Rgba([c, m, y, k])| {
+        let k = 1.0 - k.as_f64();
+
+        Rgb([
+            C::from_f64((1.0 - c.as_f64()) * k),
+            C::from_f64((1.0 - m.as_f64()) * k),
+            C::from_f64((1.0 - y.as_f64()) * k),
+        ])
+    }).collect()
+}
+
+#[inline]
+pub fn rgb_to_cmyk<C: ChannelValue>(colors: &[Rgb<C>]) -> Vec<Rgba<C>> {
+    colors.iter().map(|&Rgb([r, g, b])| {
+        let r = r.as_f64();
+        let g = g.as_f64();
+        let b = b.as_f64();
+        let max = r.max(g).max(b);
+
+        if max <= 0.0 {
+            return Rgba([C::ZERO, C::ZERO, C::ZERO, C::MAX_VALUE]);
+        }
+
+        Rgba([
+            C::from_f64(1.0 - r / max),
+            C::from_f64(1.0 - g / max),
+            C::from_f64(1.0 - b / max),
+            C::from_f64(1.0 - max),
+        ])
+    }).collect()
+}
+
+pub fn to_rgb_variant_inner<C: ChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>, color_model: ColorModel) -> Result<ColorVariant<C, ColorVecDataInner>, InvalidParams> {
+    match (color_model, colors) {
+        (ColorModel::Cmyk,  ColorVariant::Rgba(colors)) => Ok(ColorVariant::Rgb(cmyk_to_rgb(colors))),
+        (ColorModel::YCbCr, ColorVariant::Rgb (colors)) => Ok(ColorVariant::Rgb(ycbcr_to_rgb(colors))),
+        _ => Err(InvalidParams::with_message(
+            format!("cannot convert {} data in the {color_model} color model to RGB", colors.color_type())))
+    }
+}
+
+/// Converts colors in the given color model to RGB. The data is borrowed if
+/// it already is RGB (or L or RGBA).
+pub fn to_rgb_variant(colors: &ColorList, color_model: ColorModel) -> Result<Cow<'_, ColorList>, InvalidParams> {
+    if color_model == ColorModel::Rgb {
+        return Ok(Cow::Borrowed(colors));
+    }
+
+    Ok(Cow::Owned(match colors {
+        ChannelVariant::U8  (colors) => ChannelVariant::U8  (to_rgb_variant_inner(colors, color_model)?),
+        ChannelVariant::U16 (colors) => ChannelVariant::U16 (to_rgb_variant_inner(colors, color_model)?),
+        ChannelVariant::U32 (colors) => ChannelVariant::U32 (to_rgb_variant_inner(colors, color_model)?),
+        ChannelVariant::U64 (colors) => ChannelVariant::U64 (to_rgb_variant_inner(colors, color_model)?),
+        ChannelVariant::U128(colors) => ChannelVariant::U128(to_rgb_variant_inner(colors, color_model)?),
+        ChannelVariant::F32 (colors) => ChannelVariant::F32 (to_rgb_variant_inner(colors, color_model)?),
+        ChannelVariant::F64 (colors) => ChannelVariant::F64 (to_rgb_variant_inner(colors, color_model)?),
+    }))
+}
+
+pub fn from_rgb_variant_inner<C: ChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>, color_model: ColorModel) -> Result<ColorVariant<C, ColorVecDataInner>, InvalidParams> {
+    match (color_model, colors) {
+        (ColorModel::Cmyk,  ColorVariant::Rgb(colors)) => Ok(ColorVariant::Rgba(rgb_to_cmyk(colors))),
+        (ColorModel::YCbCr, ColorVariant::Rgb(colors)) => Ok(ColorVariant::Rgb(rgb_to_ycbcr(colors))),
+        _ => Err(InvalidParams::with_message(
+            format!("cannot convert {} data to the {color_model} color model", colors.color_type())))
+    }
+}
+
+/// Converts RGB colors into the given color model. The data is borrowed if
+/// the color model is RGB.
+pub fn from_rgb_variant(colors: &ColorList, color_model: ColorModel) -> Result<Cow<'_, ColorList>, InvalidParams> {
+    if color_model == ColorModel::Rgb {
+        return Ok(Cow::Borrowed(colors));
+    }
+
+    Ok(Cow::Owned(match colors {
+        ChannelVariant::U8  (colors) => ChannelVariant::U8  (from_rgb_variant_inner(colors, color_model)?),
+        ChannelVariant::U16 (colors) => ChannelVariant::U16 (from_rgb_variant_inner(colors, color_model)?),
+        ChannelVariant::U32 (colors) => ChannelVariant::U32 (from_rgb_variant_inner(colors, color_model)?),
+        ChannelVariant::U64 (colors) => ChannelVariant::U64 (from_rgb_variant_inner(colors, color_model)?),
+        ChannelVariant::U128(colors) => ChannelVariant::U128(from_rgb_variant_inner(colors, color_model)?),
+        ChannelVariant::F32 (colors) => ChannelVariant::F32 (from_rgb_variant_inner(colors, color_model)?),
+        ChannelVariant::F64 (colors) => ChannelVariant::F64 (from_rgb_variant_inner(colors, color_model)?),
+    }))
+}
@@ -0,0 +1,211 @@
+use crate::{chunks::colr::TransferFunction, color::{ChannelValue, ChannelVariant, Color, ColorList, ColorVariant, ColorVecDataInner}, error::InvalidParams, format::{ColorModel, NumberType}, XZIB};
+
+/// sRGB transfer function, from encoded to linear light. Negative values are
+/// mirrored.
+#[inline]
+pub fn srgb_to_linear(value: f64) -> f64 {
+    let abs = value.abs();
+    let linear = if abs <= 0.04045 {
+        abs / 12.92
+    } else {
+        ((abs + 0.055) / 1.055).powf(2.4)
+    };
+    linear.copysign(value)
+}
+
+/// Inverse sRGB transfer function, from linear light to encoded. Negative
+/// values are mirrored.
+#[inline]
+pub fn linear_to_srgb(value: f64) -> f64 {
+    let abs = value.abs();
+    let encoded = if abs <= 0.0031308 {
+        abs * 12.92
+    } else {
+        1.055 * abs.powf(1.0 / 2.4) - 0.055
+    };
+    encoded.copysign(value)
+}
+
+const PQ_M1: f64 = 2610.0 / 16384.0;
+const PQ_M2: f64 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f64 = 3424.0 / 4096.0;
+const PQ_C2: f64 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f64 = 2392.0 / 4096.0 * 32.0;
+
+/// SMPTE ST 2084 (PQ) EOTF. 1.0 in linear light is 10000 cd/m².
+#[inline]
+pub fn pq_to_linear(value: f64) -> f64 {
+    let power = value.max(0.0).powf(1.0 / PQ_M2);
+    ((power - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * power)).powf(1.0 / PQ_M1)
+}
+
+/// Inverse SMPTE ST 2084 (PQ) EOTF. 1.0 in linear light is 10000 cd/m².
+#[inline]
+pub fn linear_to_pq(value: f64) -> f64 {
+    let power = value.max(0.0).powf(PQ_M1);
+    ((PQ_C1 + PQ_C2 * power) / (1.0 + PQ_C3 * power)).powf(PQ_M2)
+}
+
+const HLG_A: f64 = 0.17883277;
+const HLG_B: f64 = 1.0 - 4.0 * HLG_A;
+const HLG_C: f64 = 0.55991073; // 0.5 - a * ln(4 * a)
+
+/// Inverse of the ARIB STD-B67 (HLG) OETF, from encoded to scene linear light.
+#[inline]
+pub fn hlg_to_linear(value: f64) -> f64 {
+    let value = value.max(0.0);
+    if value <= 0.5 {
+        value * value / 3.0
+    } else {
+        (((value - HLG_C) / HLG_A).exp() + HLG_B) / 12.0
+    }
+}
+
+/// ARIB STD-B67 (HLG) OETF, from scene linear light to encoded.
+#[inline]
+pub fn linear_to_hlg(value: f64) -> f64 {
+    let value = value.max(0.0);
+    if value <= 1.0 / 12.0 {
+        (3.0 * value).sqrt()
+    } else {
+        HLG_A * (12.0 * value - HLG_B).ln() + HLG_C
+    }
+}
+
+/// Encoded to linear light for the given transfer function. `gamma` is only
+/// used for [`TransferFunction::Gamma`].
+#[inline]
+pub fn to_linear(value: f64, transfer_function: TransferFunction, gamma: f32) -> f64 {
+    match transfer_function {
+        TransferFunction::Srgb   => srgb_to_linear(value),
+        TransferFunction::Linear => value,
+        TransferFunction::Pq     => pq_to_linear(value),
+        TransferFunction::Hlg    => hlg_to_linear(value),
+        TransferFunction::Gamma  => value.abs().powf(gamma as f64).copysign(value),
+    }
+}
+
+/// Linear light to encoded for the given transfer function. `gamma` is only
+/// used for [`TransferFunction::Gamma`].
+#[inline]
+pub fn from_linear(value: f64, transfer_function: TransferFunction, gamma: f32) -> f64 {
+    match transfer_function {
+        TransferFunction::Srgb   => linear_to_srgb(value),
+        TransferFunction::Linear => value,
+        TransferFunction::Pq     => linear_to_pq(value),
+        TransferFunction::Hlg    => linear_to_hlg(value),
+        TransferFunction::Gamma  => value.abs().powf(1.0 / gamma as f64).copysign(value),
+    }
+}
+
+#[inline]
+fn map_colors_inner<C: ChannelValue, Col: Color<C>>(colors: &mut [Col], f: &impl Fn(f64) -> f64) {
+    // alpha is always linear
+    let color_channels = if Col::CHANNELS < 3 { 1 } else { 3 };
+    for color in colors {
+        for channel in &mut color.channels_mut()[..color_channels] {
+            *channel = C::from_f64(f(channel.as_f64()));
+        }
+    }
+}
+
+#[inline]
+fn map_colors_variant_inner<C: ChannelValue>(colors: &mut ColorVariant<C, ColorVecDataInner>, f: &impl Fn(f64) -> f64) {
+    match colors {
+        ColorVariant::L   (colors) => map_colors_inner(colors, f),
+        ColorVariant::La  (colors) => map_colors_inner(colors, f),
+        ColorVariant::Rgb (colors) => map_colors_inner(colors, f),
+        ColorVariant::Rgba(colors) => map_colors_inner(colors, f),
+    }
+}
+
+/// Applies `f` to the color channels, but not to alpha, of floating-point
+/// colors.
+fn map_float_colors(data: &mut ColorList, f: impl Fn(f64) -> f64) -> Result<(), InvalidParams> {
+    match data {
+        ChannelVariant::F32(colors) => map_colors_variant_inner(colors, &f),
+        ChannelVariant::F64(colors) => map_colors_variant_inner(colors, &f),
+        _ => return Err(InvalidParams::with_message(
+            format!("transfer functions can only be applied to floating-point colors, but they were: {}",
+                data.channel_value_type()))),
+    }
+
+    Ok(())
+}
+
+/// Converts sRGB encoded floating-point colors with straight alpha to linear
+/// light.
+#[inline]
+pub fn srgb_to_linear_colors(data: &mut ColorList) -> Result<(), InvalidParams> {
+    map_float_colors(data, srgb_to_linear)
+}
+
+/// Converts linear light floating-point colors with straight alpha to sRGB
+/// encoding.
+#[inline]
+pub fn linear_to_srgb_colors(data: &mut ColorList) -> Result<(), InvalidParams> {
+    map_float_colors(data, linear_to_srgb)
+}
+
+impl XZIB {
+    /// Re-encodes all floating-point colors of the image (the palette for
+    /// indexed images) from the transfer function of the COLR chunk to the
+    /// given one and updates or adds the COLR chunk. Without a COLR chunk the
+    /// colors are assumed to be sRGB encoded. `gamma` is only used for
+    /// [`TransferFunction::Gamma`]. Only supported for the RGB color model.
+    pub fn convert_transfer_function(&mut self, transfer_function: TransferFunction, gamma: f32) -> Result<(), InvalidParams> {
+        if self.head.number_type() != NumberType::Float {
+            return Err(InvalidParams::with_message(
+                "transfer functions can only be converted for floating-point images"));
+        }
+
+        let color_model = self.head.color_model()?;
+        if color_model != ColorModel::Rgb {
+            return Err(InvalidParams::with_message(
+                format!("transfer functions can only be converted for the RGB color model, but it was: {color_model}")));
+        }
+
+        let mut target = self.colr.unwrap_or_default();
+        target.set_transfer_function(transfer_function);
+        target.set_gamma(gamma);
+        target.check()?;
+
+        let source = self.colr.unwrap_or_default();
+        let convert = |value| from_linear(to_linear(value, source.transfer_function(), source.gamma()), transfer_function, gamma);
+        let premultiplied = self.head.is_premultiplied();
+        let convert_data = |data: &mut ColorList| -> Result<(), InvalidParams> {
+            if premultiplied {
+                data.unpremultiply_alpha();
+            }
+            map_float_colors(data, convert)?;
+            if premultiplied {
+                data.premultiply_alpha();
+            }
+            Ok(())
+        };
+
+        if let Some(indx) = &mut self.indx {
+            convert_data(indx.colors_mut())?;
+        } else {
+            if let Some(body) = &mut self.body {
+                convert_data(body.data_mut())?;
+            }
+
+            for fram in &mut self.frames {
+                convert_data(fram.body_mut().data_mut())?;
+            }
+
+            for layr in &mut self.layers {
+                convert_data(layr.body_mut().data_mut())?;
+            }
+
+            for levl in &mut self.levels {
+                convert_data(levl.body_mut().data_mut())?;
+            }
+        }
+
+        self.colr = Some(target);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{color::{ChannelVariant, Color, ColorList, ColorVariant, ColorVecDataInner, IntChannelValue}, error::InvalidParams, reduce::{planes_candidates, read_back}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dithering {
+    /// Round to the nearest level, which causes banding.
+    #[default]
+    None,
+    /// 8x8 Bayer matrix.
+    Ordered,
+    /// Floyd–Steinberg error diffusion.
+    Diffusion,
+}
+
+impl std::fmt::Display for Dithering {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None      => "none".fmt(f),
+            Self::Ordered   => "ordered".fmt(f),
+            Self::Diffusion => "diffusion".fmt(f),
+        }
+    }
+}
+
+const BAYER: [[u8; 8]; 8] = [
+    [ 0, 32,  8, 40,  2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44,  4, 36, 14, 46,  6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [ 3, 35, 11, 43,  1, 33,  9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47,  7, 39, 13, 45,  5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// The values representable with `planes` planes that are the closest below
+/// and above of the given value. The values are what is read back from a file
+/// after the value was written with `planes` planes, see [`read_back()`].
+#[inline]
+fn levels<C: IntChannelValue>(value: C, planes: u8) -> (u128, u128) {
+    let shift = C::BITS - planes as u32;
+    let max_level = C::MAX_VALUE.as_u128() >> shift;
+    let value = value.as_u128();
+    let level = value >> shift;
+    let extended = read_back::<C>(level, planes);
+
+    if extended == value {
+        (value, value)
+    } else if extended < value {
+        let above = if level < max_level { read_back::<C>(level + 1, planes) } else { extended };
+        (extended, above)
+    } else {
+        let below = if level > 0 { read_back::<C>(level - 1, planes) } else { extended };
+        (below, extended)
+    }
+}
+
+#[inline]
+fn nearest(value: u128, below: u128, above: u128) -> u128 {
+    if value - below <= above - value { below } else { above }
+}
+
+#[inline]
+fn is_within<C: IntChannelValue>(value: C, planes: u8, max_error: u128) -> bool {
+    let (below, above) = levels(value, planes);
+    let value = value.as_u128();
+    let level = nearest(value, below, above);
+    value.abs_diff(level) <= max_error
+}
+
+fn is_within_colors<C: IntChannelValue, Col: Color<C>>(colors: &[Col], planes: u8, max_error: u128) -> bool {
+    colors.iter().all(|color| color.channels().iter().all(|&value| is_within(value, planes, max_error)))
+}
+
+#[inline]
+fn is_within_inner<C: IntChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>, planes: u8, max_error: u128) -> bool {
+    match colors {
+        ColorVariant::L   (colors) => is_within_colors(colors, planes, max_error),
+        ColorVariant::La  (colors) => is_within_colors(colors, planes, max_error),
+        ColorVariant::Rgb (colors) => is_within_colors(colors, planes, max_error),
+        ColorVariant::Rgba(colors) => is_within_colors(colors, planes, max_error),
+    }
+}
+
+/// The smallest number of planes for which rounding every channel value to the
+/// nearest representable value causes an error of at most `max_error`. The
+/// error is given in units of the channel value type, e.g. `2` means ±2 levels
+/// for `u8` colors. Only supported for integer colors.
+pub fn near_lossless_planes(data: &ColorList, interleaved: bool, max_error: u128) -> Result<u8, InvalidParams> {
+    let channel_value_type = data.channel_value_type();
+    if channel_value_type.number_type().is_float() {
+        return Err(InvalidParams::with_message(
+            format!("near-lossless encoding is only supported for integer colors, not {channel_value_type}")));
+    }
+
+    let candidates = planes_candidates(channel_value_type.planes() as u32, interleaved);
+    for &planes in &candidates {
+        let is_within = match data {
+            ChannelVariant::U8  (colors) => is_within_inner(colors, planes, max_error),
+            ChannelVariant::U16 (colors) => is_within_inner(colors, planes, max_error),
+            ChannelVariant::U32 (colors) => is_within_inner(colors, planes, max_error),
+            ChannelVariant::U64 (colors) => is_within_inner(colors, planes, max_error),
+            ChannelVariant::U128(colors) => is_within_inner(colors, planes, max_error),
+            ChannelVariant::F32 (_) | ChannelVariant::F64 (_) => true,
+        };
+
+        if is_within {
+            return Ok(planes);
+        }
+    }
+
+    Ok(channel_value_type.planes())
+}
+
+/// Picks one of `below` and `above` for the value with the dither offset added,
+/// but never one that is further than `max_error` away from the value itself.
+#[inline]
+fn pick(value: u128, target: f64, below: u128, above: u128, max_error: Option<u128>) -> u128 {
+    let middle = below as f64 + (above - below) as f64 * 0.5;
+    let level = if target < middle { below } else { above };
+
+    match max_error {
+        Some(max_error) if value.abs_diff(level) > max_error => nearest(value, below, above),
+        _ => level,
+    }
+}
+
+fn dither_colors<C: IntChannelValue, Col: Color<C>>(colors: &mut [Col], width: usize, planes: u8, dithering: Dithering, max_error: Option<u128>) {
+    let channels = Col::CHANNELS as usize;
+    let mut errors      = vec![0.0; (width + 2) * channels];
+    let mut next_errors = vec![0.0; (width + 2) * channels];
+
+    for (y, row) in colors.chunks_mut(width).enumerate() {
+        for (x, color) in row.iter_mut().enumerate() {
+            for (channel, value) in color.channels_mut().iter_mut().enumerate() {
+                let (below, above) = levels(*value, planes);
+                let current = value.as_u128();
+                if below == above {
+                    continue;
+                }
+
+                let level = match dithering {
+                    Dithering::None => nearest(current, below, above),
+                    Dithering::Ordered => {
+                        let threshold = (BAYER[y % 8][x % 8] as f64 + 0.5) / 64.0 - 0.5;
+                        let target = current as f64 + threshold * (above - below) as f64;
+                        pick(current, target, below, above, max_error)
+                    }
+                    Dithering::Diffusion => {
+                        let target = current as f64 + errors[(x + 1) * channels + channel];
+                        let level = pick(current, target, below, above, max_error);
+
+                        // keep the error in range so it can't accumulate if a
+                        // level got rejected because of the error bound
+                        let range = (above - below) as f64;
+                        let error = (target - level as f64).clamp(-range, range);
+                        errors[(x + 2) * channels + channel]      += error * 7.0 / 16.0;
+                        next_errors[x * channels + channel]       += error * 3.0 / 16.0;
+                        next_errors[(x + 1) * channels + channel] += error * 5.0 / 16.0;
+                        next_errors[(x + 2) * channels + channel] += error * 1.0 / 16.0;
+                        level
+                    }
+                };
+
+                *value = C::from_u128(level);
+            }
+        }
+
+        if dithering == Dithering::Diffusion {
+            std::mem::swap(&mut errors, &mut next_errors);
+            next_errors.fill(0.0);
+        }
+    }
+}
+
+#[inline]
+fn dither_inner<C: IntChannelValue>(colors: &mut ColorVariant<C, ColorVecDataInner>, width: usize, planes: u8, dithering: Dithering, max_error: Option<u128>) {
+    match colors {
+        ColorVariant::L   (colors) => dither_colors(colors, width, planes, dithering, max_error),
+        ColorVariant::La  (colors) => dither_colors(colors, width, planes, dithering, max_error),
+        ColorVariant::Rgb (colors) => dither_colors(colors, width, planes, dithering, max_error),
+        ColorVariant::Rgba(colors) => dither_colors(colors, width, planes, dithering, max_error),
+    }
+}
+
+/// Reduces integer colors to values that are exactly representable with
+/// `planes` planes, so that writing them with that many planes doesn't round
+/// them any further. The colors keep their channel value type, but the values
+/// are the ones read back from such a file, which may use a smaller channel
+/// value type, see [`crate::reduce::narrow()`].
+///
+/// If `max_error` is given no channel value is changed by more than that,
+/// even if the dithering would pick a level that is further away. Rounding
+/// to the nearest level has to be within that bound, see
+/// [`near_lossless_planes()`]. Float colors are not changed.
+pub fn dither_planes(data: &mut ColorList, width: u32, planes: u8, dithering: Dithering, max_error: Option<u128>) -> Result<(), InvalidParams> {
+    if width == 0 {
+        return Err(InvalidParams::with_message("width must be non-zero for dithering"));
+    }
+
+    let bits = data.channel_value_type().planes();
+    if planes == 0 || planes > bits {
+        return Err(InvalidParams::with_message(
+            format!("planes must be in the range 1 to {bits}, but was: {planes}")));
+    }
+
+    let width = width as usize;
+    match data {
+        ChannelVariant::U8  (colors) => dither_inner(colors, width, planes, dithering, max_error),
+        ChannelVariant::U16 (colors) => dither_inner(colors, width, planes, dithering, max_error),
+        ChannelVariant::U32 (colors) => dither_inner(colors, width, planes, dithering, max_error),
+        ChannelVariant::U64 (colors) => dither_inner(colors, width, planes, dithering, max_error),
+        ChannelVariant::U128(colors) => dither_inner(colors, width, planes, dithering, max_error),
+        ChannelVariant::F32 (_) | ChannelVariant::F64 (_) => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chunks::Body, format::{ColorType, NumberType}, reduce::narrow, Head};
+
+    use super::*;
+
+    /// Encodes like `xzib encode --max-error` and returns the decoded values
+    /// scaled to 16 bit.
+    fn round_trip(values: &[u16], interleaved: bool, max_error: u128, dithering: Dithering) -> (u8, Vec<u16>) {
+        let width = 256;
+        let height = values.len() as u32 / width;
+        let mut data = ChannelVariant::U16(ColorVariant::L(values.to_vec()));
+
+        let planes = near_lossless_planes(&data, interleaved, max_error).unwrap();
+        dither_planes(&mut data, width, planes, dithering, Some(max_error)).unwrap();
+        let data = narrow(&data, planes);
+
+        let head = Head::new(NumberType::Integer, interleaved, ColorType::L, planes, 0, width, height).unwrap();
+        let mut bytes = Vec::new();
+        Body::with_data(data).write(&head, &mut bytes).unwrap();
+
+        let decoded = match Body::read(&bytes, &head).unwrap().into_data() {
+            ChannelVariant::U8(ColorVariant::L(values)) => values.into_iter().map(|value| value as u16 * 257).collect(),
+            ChannelVariant::U16(ColorVariant::L(values)) => values,
+            data => panic!("unexpected data: {}", data.channel_value_type()),
+        };
+
+        (planes, decoded)
+    }
+
+    #[test]
+    fn max_error_is_kept_after_writing() {
+        let values: Vec<u16> = (0..=u16::MAX).collect();
+
+        for interleaved in [false, true] {
+            for max_error in [0, 1, 7, 100, 300, 1000, 5000] {
+                for dithering in [Dithering::None, Dithering::Ordered, Dithering::Diffusion] {
+                    let (planes, decoded) = round_trip(&values, interleaved, max_error, dithering);
+                    assert_eq!(decoded.len(), values.len());
+                    for (&value, &decoded) in values.iter().zip(&decoded) {
+                        assert!(value.abs_diff(decoded) as u128 <= max_error,
+                            "{value} decoded as {decoded} with {planes} planes, max error {max_error}, {dithering}, interleaved: {interleaved}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn max_error_reduces_planes() {
+        let values: Vec<u16> = (0..=u16::MAX).collect();
+
+        assert_eq!(round_trip(&values, true, 0, Dithering::None).0, 16);
+        assert!(round_trip(&values, true, 300, Dithering::None).0 < 16);
+        assert!(round_trip(&values, true, 5000, Dithering::None).0 <= 4);
+    }
+
+    #[test]
+    fn dithering_stays_within_levels() {
+        let values: Vec<u8> = (0..=u8::MAX).cycle().take(256 * 16).collect();
+
+        for planes in 1..=8 {
+            for dithering in [Dithering::None, Dithering::Ordered, Dithering::Diffusion] {
+                let mut data = ChannelVariant::U8(ColorVariant::L(values.clone()));
+                dither_planes(&mut data, 256, planes, dithering, None).unwrap();
+
+                let ChannelVariant::U8(ColorVariant::L(dithered)) = data else {
+                    panic!("channel value type changed");
+                };
+                for (&value, &dithered) in values.iter().zip(&dithered) {
+                    let (below, above) = levels(value, planes);
+                    assert!(dithered as u128 == below || dithered as u128 == above,
+                        "{value} dithered to {dithered} with {planes} planes, {dithering}");
+                    assert_eq!((dithered >> (8 - planes)).extend(planes), dithered);
+                }
+            }
+        }
+    }
+}
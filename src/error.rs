@@ -218,6 +218,11 @@ make_error! {
     struct IllegalDateInner {}
 }
 
+make_error! {
+    IllegalLicense;
+    struct IllegalLicenseInner {}
+}
+
 make_error! {
     WriteError;
     struct WriteErrorInner {}
@@ -227,6 +232,7 @@ make_error! {
     }
     impl IO: std::io::Error;
     impl InvalidParams: InvalidParams;
+    impl InvalidParams: IllegalLicense;
 }
 
 make_error! {
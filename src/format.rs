@@ -1,6 +1,8 @@
 use crate::{color::{ChannelValue, ChannelVariant, ColorList, ColorVariant, ColorVecDataInner}, error::InvalidParams};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum NumberType {
     Integer,
     Float,
@@ -178,3 +180,111 @@ impl std::fmt::Display for Format {
         write!(f, "{color_type} {channel_value_type}")
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ColorModel {
+    /// L, RGB, or RGBA, depending on the number of channels.
+    #[default]
+    Rgb   = 0,
+    Cmyk  = 1,
+    YCbCr = 2,
+}
+
+impl ColorModel {
+    const RGB:   u8 = ColorModel::Rgb   as u8;
+    const CMYK:  u8 = ColorModel::Cmyk  as u8;
+    const YCBCR: u8 = ColorModel::YCbCr as u8;
+
+    #[inline]
+    pub fn new(color_model: u8) -> Option<Self> {
+        match color_model {
+            Self::RGB   => Some(Self::Rgb),
+            Self::CMYK  => Some(Self::Cmyk),
+            Self::YCBCR => Some(Self::YCbCr),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_valid_channels(self, channels: u8) -> bool {
+        match self {
+            Self::Rgb   => matches!(channels, 1 | 3 | 4),
+            Self::Cmyk  => channels == 4,
+            Self::YCbCr => channels == 3,
+        }
+    }
+}
+
+impl std::fmt::Display for ColorModel {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rgb   => "RGB".fmt(f),
+            Self::Cmyk  => "CMYK".fmt(f),
+            Self::YCbCr => "YCbCr".fmt(f),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChromaSubsampling {
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "4:4:4"))]
+    Chroma444 = 0,
+    #[cfg_attr(feature = "serde", serde(rename = "4:2:2"))]
+    Chroma422 = 1,
+    #[cfg_attr(feature = "serde", serde(rename = "4:2:0"))]
+    Chroma420 = 2,
+}
+
+impl ChromaSubsampling {
+    const CHROMA444: u8 = ChromaSubsampling::Chroma444 as u8;
+    const CHROMA422: u8 = ChromaSubsampling::Chroma422 as u8;
+    const CHROMA420: u8 = ChromaSubsampling::Chroma420 as u8;
+
+    #[inline]
+    pub fn new(chroma_subsampling: u8) -> Option<Self> {
+        match chroma_subsampling {
+            Self::CHROMA444 => Some(Self::Chroma444),
+            Self::CHROMA422 => Some(Self::Chroma422),
+            Self::CHROMA420 => Some(Self::Chroma420),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_subsampled(self) -> bool {
+        !matches!(self, Self::Chroma444)
+    }
+
+    /// Horizontal and vertical subsampling factors.
+    #[inline]
+    pub fn factors(self) -> (u32, u32) {
+        match self {
+            Self::Chroma444 => (1, 1),
+            Self::Chroma422 => (2, 1),
+            Self::Chroma420 => (2, 2),
+        }
+    }
+
+    /// Dimensions of the Cb and Cr planes of an image of the given size.
+    #[inline]
+    pub fn chroma_size(self, width: u32, height: u32) -> (u32, u32) {
+        let (x_factor, y_factor) = self.factors();
+        (width.div_ceil(x_factor), height.div_ceil(y_factor))
+    }
+}
+
+impl std::fmt::Display for ChromaSubsampling {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Chroma444 => "4:4:4".fmt(f),
+            Self::Chroma422 => "4:2:2".fmt(f),
+            Self::Chroma420 => "4:2:0".fmt(f),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+/// Whether `tag` is a well-formed BCP 47 language tag like `en`, `de-AT` or
+/// `zh-Hant-TW`. Only the syntax of the subtags is checked, not whether they
+/// are registered.
+pub fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+
+    let Some(language) = subtags.next() else {
+        return false;
+    };
+
+    let private_use = language.eq_ignore_ascii_case("x") || language.eq_ignore_ascii_case("i");
+    if !private_use && !(2..=8).contains(&language.len()) {
+        return false;
+    }
+
+    if !language.bytes().all(|byte| byte.is_ascii_alphabetic()) {
+        return false;
+    }
+
+    subtags.all(|subtag| (1..=8).contains(&subtag.len()) && subtag.bytes().all(|byte| byte.is_ascii_alphanumeric()))
+}
+
+/// The primary language subtag, e.g. `de` for `de-AT`.
+#[inline]
+fn primary_language(tag: &str) -> &str {
+    tag.split('-').next().unwrap_or(tag)
+}
+
+/// Picks the value best matching `locale` from values tagged with a language
+/// or untagged (`None`). Tags are compared case-insensitively, first the whole
+/// locale and then with subtags removed from the end (`de-AT` before `de`),
+/// then any tag of the same primary language, then the untagged value, and
+/// finally the first value.
+pub fn best_match<'a, T>(locale: &str, candidates: impl IntoIterator<Item = (Option<&'a str>, T)>) -> Option<T> {
+    let mut candidates: Vec<(Option<&str>, T)> = candidates.into_iter().collect();
+    if candidates.is_empty() {
+        return None;
+    }
+
+    let mut position = None;
+    let mut range = locale;
+    while !range.is_empty() {
+        position = candidates.iter().position(|(tag, _)| tag.is_some_and(|tag| tag.eq_ignore_ascii_case(range)));
+        if position.is_some() {
+            break;
+        }
+
+        range = match range.rfind('-') {
+            // single letter subtags like extensions only make sense together
+            // with the following subtag
+            Some(index) if index >= 2 && range.as_bytes()[index - 2] == b'-' => &range[..index - 2],
+            Some(index) => &range[..index],
+            None => "",
+        };
+    }
+
+    let primary = primary_language(locale);
+    let position = position
+        .or_else(|| candidates.iter().position(|(tag, _)| tag.is_some_and(|tag| primary_language(tag).eq_ignore_ascii_case(primary))))
+        .or_else(|| candidates.iter().position(|(tag, _)| tag.is_none()))
+        .unwrap_or(0);
+
+    Some(candidates.swap_remove(position).1)
+}
+
+/// The text best matching `locale` from an untagged `default` and texts
+/// tagged with a language, see [`best_match`]. An empty `default` counts as
+/// missing.
+pub fn best_match_text<'a>(locale: &str, default: &'a str, localized: &'a [(String, String)]) -> &'a str {
+    let untagged = (!default.is_empty()).then_some((None, default));
+    let tagged = localized.iter().map(|(lang, text)| (Some(lang.as_str()), text.as_str()));
+
+    best_match(locale, untagged.into_iter().chain(tagged)).unwrap_or(default)
+}
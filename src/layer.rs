@@ -0,0 +1,159 @@
+use crate::{chunks::layr::BlendMode, color::{ChannelValue, ChannelVariant, Color, ColorList, ColorVariant, ColorVecDataInner}, error::InvalidParams, format::{ColorType, Format}, quantize::{from_pixels, Pixel}, XZIB};
+
+impl XZIB {
+    /// Composites all visible layers bottom to top onto a transparent canvas of
+    /// the size of the image. The result are RGBA colors with straight alpha
+    /// in the channel value type of the layers (of the palette for indexed
+    /// images), converted from the color model defined in the header. `None`
+    /// if there are no LAYR chunks.
+    pub fn flatten(&self) -> Result<Option<ColorList>, InvalidParams> {
+        let Some(first) = self.layers.first() else {
+            return Ok(None);
+        };
+
+        let width  = self.head.width() as i64;
+        let height = self.head.height() as i64;
+        let mut canvas: Vec<Pixel> = vec![[0.0; 4]; width as usize * height as usize];
+        // only used if no layer is visible
+        let mut channel_value_type = match &self.indx {
+            Some(indx) => indx.colors().channel_value_type(),
+            None => first.body().data().channel_value_type(),
+        };
+
+        for layr in &self.layers {
+            if !layr.is_visible() || layr.opacity() <= 0.0 {
+                continue;
+            }
+
+            let data = self.body_rgb_data(layr.body())?;
+            channel_value_type = data.channel_value_type();
+            let pixels = to_rgba_pixels(&data);
+            let opacity = layr.opacity() as f64;
+            let layer_width = layr.width() as i64;
+            if pixels.len() as u64 != layr.width() as u64 * layr.height() as u64 {
+                return Err(InvalidParams::with_message(
+                    format!("layer {:?} of {} x {} has {} colors", layr.name(), layr.width(), layr.height(), pixels.len())));
+            }
+
+            // clip the layer to the canvas
+            let min_x = (layr.x() as i64).max(0);
+            let min_y = (layr.y() as i64).max(0);
+            let max_x = (layr.x() as i64 + layer_width).min(width);
+            let max_y = (layr.y() as i64 + layr.height() as i64).min(height);
+
+            for y in min_y..max_y {
+                for x in min_x..max_x {
+                    let source = pixels[((y - layr.y() as i64) * layer_width + x - layr.x() as i64) as usize];
+                    composite(&mut canvas[(y * width + x) as usize], source, layr.blend_mode(), opacity);
+                }
+            }
+        }
+
+        let like = Format(channel_value_type, ColorType::Rgba).make_color_list();
+
+        Ok(Some(from_pixels(&like, &canvas)))
+    }
+}
+
+#[inline]
+fn hard_light(backdrop: f64, source: f64) -> f64 {
+    if source <= 0.5 {
+        backdrop * 2.0 * source
+    } else {
+        let source = 2.0 * source - 1.0;
+        backdrop + source - backdrop * source
+    }
+}
+
+/// Blends the channel of the layer with the channel below it.
+fn blend(blend_mode: BlendMode, backdrop: f64, source: f64) -> f64 {
+    match blend_mode {
+        BlendMode::Normal   => source,
+        BlendMode::Multiply => backdrop * source,
+        BlendMode::Screen   => backdrop + source - backdrop * source,
+        BlendMode::Overlay  => hard_light(source, backdrop),
+        BlendMode::Darken   => backdrop.min(source),
+        BlendMode::Lighten  => backdrop.max(source),
+        BlendMode::ColorDodge => {
+            if backdrop <= 0.0 {
+                0.0
+            } else if source >= 1.0 {
+                1.0
+            } else {
+                (backdrop / (1.0 - source)).min(1.0)
+            }
+        }
+        BlendMode::ColorBurn => {
+            if backdrop >= 1.0 {
+                1.0
+            } else if source <= 0.0 {
+                0.0
+            } else {
+                1.0 - ((1.0 - backdrop) / source).min(1.0)
+            }
+        }
+        BlendMode::HardLight => hard_light(backdrop, source),
+        BlendMode::SoftLight => {
+            if source <= 0.5 {
+                backdrop - (1.0 - 2.0 * source) * backdrop * (1.0 - backdrop)
+            } else {
+                let d = if backdrop <= 0.25 {
+                    ((16.0 * backdrop - 12.0) * backdrop + 4.0) * backdrop
+                } else {
+                    backdrop.sqrt()
+                };
+                backdrop + (2.0 * source - 1.0) * (d - backdrop)
+            }
+        }
+        BlendMode::Difference => (backdrop - source).abs(),
+        BlendMode::Exclusion  => backdrop + source - 2.0 * backdrop * source,
+        BlendMode::Add        => (backdrop + source).min(1.0),
+    }
+}
+
+/// Source over compositing of straight alpha colors, with the colors of the
+/// source blended with the backdrop where the backdrop is opaque.
+fn composite(backdrop: &mut Pixel, source: Pixel, blend_mode: BlendMode, opacity: f64) {
+    let source_alpha = source[3] * opacity;
+    if source_alpha <= 0.0 {
+        return;
+    }
+
+    let backdrop_alpha = backdrop[3];
+    let alpha = source_alpha + backdrop_alpha * (1.0 - source_alpha);
+
+    for channel in 0..3 {
+        let backdrop_value = backdrop[channel];
+        let source_value = (1.0 - backdrop_alpha) * source[channel] + backdrop_alpha * blend(blend_mode, backdrop_value, source[channel]);
+        backdrop[channel] = (source_alpha * source_value + backdrop_alpha * (1.0 - source_alpha) * backdrop_value) / alpha;
+    }
+    backdrop[3] = alpha;
+}
+
+#[inline]
+fn to_rgba_pixels_inner<C: ChannelValue, Col: Color<C>>(colors: &[Col]) -> Vec<Pixel> {
+    colors.iter().map(|color| color.to_rgba().0.map(ChannelValue::as_f64)).collect()
+}
+
+#[inline]
+fn to_rgba_pixels_variant_inner<C: ChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>) -> Vec<Pixel> {
+    match colors {
+        ColorVariant::L   (colors) => to_rgba_pixels_inner(colors),
+        ColorVariant::La  (colors) => to_rgba_pixels_inner(colors),
+        ColorVariant::Rgb (colors) => to_rgba_pixels_inner(colors),
+        ColorVariant::Rgba(colors) => to_rgba_pixels_inner(colors),
+    }
+}
+
+/// Normalized RGBA channel values.
+fn to_rgba_pixels(data: &ColorList) -> Vec<Pixel> {
+    match data {
+        ChannelVariant::U8  (colors) => to_rgba_pixels_variant_inner(colors),
+        ChannelVariant::U16 (colors) => to_rgba_pixels_variant_inner(colors),
+        ChannelVariant::U32 (colors) => to_rgba_pixels_variant_inner(colors),
+        ChannelVariant::U64 (colors) => to_rgba_pixels_variant_inner(colors),
+        ChannelVariant::U128(colors) => to_rgba_pixels_variant_inner(colors),
+        ChannelVariant::F32 (colors) => to_rgba_pixels_variant_inner(colors),
+        ChannelVariant::F64 (colors) => to_rgba_pixels_variant_inner(colors),
+    }
+}
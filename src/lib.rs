@@ -3,17 +3,36 @@ pub mod color;
 pub mod format;
 pub mod error;
 pub mod io;
+pub mod palette;
+pub mod dither;
+pub mod quantize;
+pub mod reduce;
+pub mod animation;
+pub mod layer;
+pub mod resize;
+pub mod thumbnail;
+pub mod mipmap;
+pub mod colorimetry;
+pub mod xmp;
+pub mod lang;
+pub mod spdx;
+pub mod metadata;
+pub mod raw;
 
 use std::{borrow::Cow, io::{Read, Seek, Write}};
 
-use chunks::{Body, ChunkWrite, Foot, Indx, Meta, Xmet};
-use color::{apply_palette_variant, ChannelVariant, ColorList, ColorVariant};
+use chunks::{Anim, Body, ChunkWrite, Colr, Exif, Foot, Fram, Iccp, Indx, Layr, Levl, Meta, Thmb, Xmet, Xpkt};
+use color::{apply_palette_variant, find_invalid_index_variant, replace_invalid_indices_variant, to_rgb_variant, ChannelVariant, ColorList, ColorVariant};
 use error::{IllegalDate, InvalidParams, ReadError, ReadErrorKind, WriteError, WriteErrorKind};
 use flate2::{bufread::ZlibDecoder, write::ZlibEncoder, Compression};
-use format::{ChannelValueType, ColorType, Format, NumberType};
+use format::{ChannelValueType, ChromaSubsampling, ColorModel, ColorType, Format, NumberType};
 use io::{read_fourcc, read_u32, read_u64, read_u8};
+use palette::{remap_indices, reorder_palette, PaletteOrder};
+use quantize::{from_pixels, Pixel};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "HeadRepr", try_from = "HeadRepr"))]
 pub struct Head {
     flags: u8,
     channels: u8,
@@ -23,6 +42,60 @@ pub struct Head {
     height: u32,
 }
 
+/// Serialized form of [`Head`], with the flags as named fields.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct HeadRepr {
+    number_type: NumberType,
+    interleaved: bool,
+    premultiplied: bool,
+    color_model: ColorModel,
+    chroma_subsampling: ChromaSubsampling,
+    channels: u8,
+    planes: u8,
+    index_planes: u8,
+    width: u32,
+    height: u32,
+}
+
+#[cfg(feature = "serde")]
+impl From<Head> for HeadRepr {
+    fn from(head: Head) -> Self {
+        // headers that are read or constructed are already validated
+        Self {
+            number_type: head.number_type(),
+            interleaved: head.is_interleaved(),
+            premultiplied: head.is_premultiplied(),
+            color_model: head.color_model().unwrap_or_default(),
+            chroma_subsampling: head.chroma_subsampling().unwrap_or_default(),
+            channels: head.channels,
+            planes: head.planes,
+            index_planes: head.index_planes,
+            width: head.width,
+            height: head.height,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<HeadRepr> for Head {
+    type Error = InvalidParams;
+
+    fn try_from(repr: HeadRepr) -> Result<Self, Self::Error> {
+        let color_type = ColorType::from_channels(repr.channels)?;
+        let mut head = Head::new(repr.number_type, repr.interleaved, color_type, repr.planes, repr.index_planes, repr.width, repr.height)?;
+        head.set_color_model(repr.color_model)?;
+        head.set_chroma_subsampling(repr.chroma_subsampling)?;
+        head.set_premultiplied(repr.premultiplied)?;
+
+        if let Err(err) = head.validate() {
+            return Err(InvalidParams::with_all("illegal header", Box::new(err)));
+        }
+
+        Ok(head)
+    }
+}
+
 #[inline]
 pub fn is_valid_planes(number_type: NumberType, interleaved: bool, planes: u8) -> bool {
     if number_type.is_float() {
@@ -36,7 +109,21 @@ pub fn is_valid_planes(number_type: NumberType, interleaved: bool, planes: u8) -
     return matches!(planes, 1 | 4 | 8 | 16 | 32 | 64 | 128);
 }
 
+/// Palette colors need at least 8 bits per channel, because the number of
+/// colors in the INDX chunk is derived from its length.
+#[inline]
+pub fn is_valid_index_planes(number_type: NumberType, index_planes: u8) -> bool {
+    if number_type.is_float() {
+        matches!(index_planes, 32 | 64)
+    } else {
+        matches!(index_planes, 8 | 16 | 32 | 64 | 128)
+    }
+}
+
 impl Head {
+    /// For indexed images `number_type` and `index_planes` describe the colors
+    /// of the palette, whereas `planes` describes the indices, which are
+    /// always integers.
     #[inline]
     pub fn new(number_type: NumberType, interleaved: bool, color_type: ColorType, planes: u8, index_planes: u8, width: u32, height: u32) -> Result<Self, InvalidParams> {
         let mut flags = 0;
@@ -48,19 +135,20 @@ impl Head {
             flags |= XZIB::INTERLEAVED;
         }
 
-        if !is_valid_planes(number_type, interleaved, planes) {
+        let body_number_type = if index_planes != 0 { NumberType::Integer } else { number_type };
+        if !is_valid_planes(body_number_type, interleaved, planes) {
             return Err(InvalidParams::with_message(
                 format!("invalid planes for {} {}: {}",
                     if interleaved { "interleaved" } else { "non-interleaved" },
-                    number_type, planes
+                    body_number_type, planes
                 )
             ));
         }
 
-        if index_planes != 0 && !is_valid_planes(number_type, false, index_planes) {
+        if index_planes != 0 && !is_valid_index_planes(number_type, index_planes) {
             return Err(InvalidParams::with_message(
                 format!("invalid index planes for {}: {}",
-                    number_type, planes
+                    number_type, index_planes
                 )
             ));
         }
@@ -100,6 +188,91 @@ impl Head {
         self.index_planes > 0
     }
 
+    #[inline]
+    pub fn color_model(&self) -> Result<ColorModel, InvalidParams> {
+        let color_model = (self.flags & XZIB::COLOR_MODEL_MASK) >> XZIB::COLOR_MODEL_SHIFT;
+        let Some(color_model) = ColorModel::new(color_model) else {
+            return Err(InvalidParams::with_message(
+                format!("invalid color model: {color_model}")));
+        };
+        Ok(color_model)
+    }
+
+    #[inline]
+    pub fn chroma_subsampling(&self) -> Result<ChromaSubsampling, InvalidParams> {
+        let chroma_subsampling = (self.flags & XZIB::CHROMA_SUBSAMPLING_MASK) >> XZIB::CHROMA_SUBSAMPLING_SHIFT;
+        let Some(chroma_subsampling) = ChromaSubsampling::new(chroma_subsampling) else {
+            return Err(InvalidParams::with_message(
+                format!("invalid chroma subsampling: {chroma_subsampling}")));
+        };
+        Ok(chroma_subsampling)
+    }
+
+    #[inline]
+    pub fn is_premultiplied(&self) -> bool {
+        self.flags & XZIB::PREMULTIPLIED != 0
+    }
+
+    pub fn set_premultiplied(&mut self, premultiplied: bool) -> Result<(), InvalidParams> {
+        if premultiplied {
+            if self.channels != 4 || self.color_model()? != ColorModel::Rgb {
+                return Err(InvalidParams::with_message(
+                    format!("premultiplied alpha requires RGBA colors, but header defines {} channels in the {} color model",
+                        self.channels, self.color_model()?)));
+            }
+            self.flags |= XZIB::PREMULTIPLIED;
+        } else {
+            self.flags &= !XZIB::PREMULTIPLIED;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_chroma_subsampled(&self) -> bool {
+        self.flags & XZIB::CHROMA_SUBSAMPLING_MASK != 0
+    }
+
+    pub fn set_color_model(&mut self, color_model: ColorModel) -> Result<(), InvalidParams> {
+        if !color_model.is_valid_channels(self.channels) {
+            return Err(InvalidParams::with_message(
+                format!("invalid number of channels for the {color_model} color model: {}", self.channels)));
+        }
+
+        if color_model != ColorModel::Rgb && self.is_premultiplied() {
+            return Err(InvalidParams::with_message(
+                format!("premultiplied alpha is not supported for the {color_model} color model")));
+        }
+
+        if color_model != ColorModel::YCbCr && self.is_chroma_subsampled() {
+            return Err(InvalidParams::with_message(
+                format!("chroma subsampling is only supported for the YCbCr color model, not {color_model}")));
+        }
+
+        self.flags = (self.flags & !XZIB::COLOR_MODEL_MASK) | ((color_model as u8) << XZIB::COLOR_MODEL_SHIFT);
+
+        Ok(())
+    }
+
+    pub fn set_chroma_subsampling(&mut self, chroma_subsampling: ChromaSubsampling) -> Result<(), InvalidParams> {
+        if chroma_subsampling.is_subsampled() {
+            if self.color_model()? != ColorModel::YCbCr {
+                return Err(InvalidParams::with_message(
+                    format!("chroma subsampling is only supported for the YCbCr color model, not {}", self.color_model()?)));
+            }
+
+            if self.is_indexed() {
+                return Err(InvalidParams::with_message(
+                    "chroma subsampling is not supported for indexed images"));
+            }
+        }
+
+        self.flags = (self.flags & !XZIB::CHROMA_SUBSAMPLING_MASK) | ((chroma_subsampling as u8) << XZIB::CHROMA_SUBSAMPLING_SHIFT);
+
+        Ok(())
+    }
+
+    /// Number type of the colors, meaning of the palette for indexed images.
     #[inline]
     pub fn number_type(&self) -> NumberType {
         if self.is_float() {
@@ -109,11 +282,24 @@ impl Head {
         }
     }
 
+    /// Number type of the values in the BODY chunk. Same as [`Head::number_type()`],
+    /// except for indexed images, where it is always integer.
+    #[inline]
+    pub fn body_number_type(&self) -> NumberType {
+        if self.is_indexed() {
+            NumberType::Integer
+        } else {
+            self.number_type()
+        }
+    }
+
+    /// Channel value type of the BODY chunk.
     #[inline]
     pub fn channel_value_type(&self) -> Result<ChannelValueType, InvalidParams> {
-        ChannelValueType::from_planes(self.number_type(), self.planes)
+        ChannelValueType::from_planes(self.body_number_type(), self.planes)
     }
 
+    /// Channel value type of the INDX chunk.
     #[inline]
     pub fn index_channel_value_type(&self) -> Result<Option<ChannelValueType>, InvalidParams> {
         if self.index_planes == 0 {
@@ -164,6 +350,13 @@ impl Head {
         self.height
     }
 
+    /// The same header for an image of a different size, e.g. an animation
+    /// frame.
+    #[inline]
+    pub fn with_size(&self, width: u32, height: u32) -> Self {
+        Self { width, height, ..*self }
+    }
+
     pub fn read(reader: &mut impl Read) -> Result<Self, ReadError> {
         let fourcc = read_fourcc(reader)?;
 
@@ -173,6 +366,74 @@ impl Head {
                 format!("unsupported fourcc: {:?}", fourcc)));
         }
 
+        Self::read_fields(reader)
+    }
+
+    /// Checks the header fields that can be checked without reading any chunks.
+    fn validate(&self) -> Result<(), ReadError> {
+        if self.channels() == 0 {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("channels == 0")
+            ));
+        }
+
+        if self.planes() == 0 {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("planes == 0")
+            ));
+        }
+
+        if self.is_indexed() && !is_valid_index_planes(self.number_type(), self.index_planes()) {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("illegal index planes for {}: {}", self.number_type(), self.index_planes())
+            ));
+        }
+
+        let color_model = match self.color_model() {
+            Ok(color_model) => color_model,
+            Err(err) => return Err(ReadError::with_all(
+                ReadErrorKind::BrokenFile,
+                "illegal header flags",
+                Box::new(err)))
+        };
+
+        if !color_model.is_valid_channels(self.channels()) {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("illegal number of channels for the {color_model} color model: {}", self.channels())
+            ));
+        }
+
+        if self.is_premultiplied() && color_model != ColorModel::Rgb {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                format!("premultiplied alpha is not supported for the {color_model} color model")
+            ));
+        }
+
+        match self.chroma_subsampling() {
+            Ok(chroma_subsampling) => {
+                if chroma_subsampling.is_subsampled() && (color_model != ColorModel::YCbCr || self.is_indexed()) {
+                    return Err(ReadError::with_message(
+                        ReadErrorKind::BrokenFile,
+                        format!("{chroma_subsampling} chroma subsampling is only supported for non-indexed YCbCr images")
+                    ));
+                }
+            }
+            Err(err) => return Err(ReadError::with_all(
+                ReadErrorKind::BrokenFile,
+                "illegal header flags",
+                Box::new(err)))
+        }
+
+        Ok(())
+    }
+
+    /// Reads the header without the fourcc.
+    fn read_fields(reader: &mut impl Read) -> Result<Self, ReadError> {
         let flags        = read_u8(reader)?;
         let channels     = read_u8(reader)?;
         let planes       = read_u8(reader)?;
@@ -193,6 +454,11 @@ impl Head {
     pub fn write(&self, writer: &mut impl Write) -> std::io::Result<()> {
         writer.write_all(&XZIB::FOURCC)?;
 
+        self.write_fields(writer)
+    }
+
+    /// Writes the header without the fourcc.
+    fn write_fields(&self, writer: &mut impl Write) -> std::io::Result<()> {
         writer.write_all(&[
             self.flags,
             self.channels,
@@ -207,30 +473,68 @@ impl Head {
     }
 }
 
+/// How to handle indices in the BODY chunk that are out of range for the
+/// colors in the INDX chunk when reading a file.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PaletteIndexMode {
+    /// Fail with [`ReadErrorKind::BrokenFile`]. This also fails if the INDX
+    /// chunk is missing even though `index_planes` is non-zero.
+    #[default]
+    Strict,
+
+    /// Append the given color (normalized channel values) to the palette and
+    /// map all invalid indices to it. If the INDX chunk is missing the palette
+    /// only consists of that color.
+    Lenient(Pixel),
+}
+
 #[derive(Debug)]
 pub struct XZIB {
     head: Head,
 
+    thmb: Option<Thmb>,
+    iccp: Option<Iccp>,
+    colr: Option<Colr>,
     indx: Option<Indx>,
     meta: Option<Meta>,
     xmet: Option<Xmet>,
+    xpkt: Option<Xpkt>,
+    exif: Option<Exif>,
+    anim: Option<Anim>,
     body: Option<Body>,
+    frames: Vec<Fram>,
+    layers: Vec<Layr>,
+    levels: Vec<Levl>,
     foot: Option<Foot>,
 }
 
 impl XZIB {
     pub const INTERLEAVED: u8 = 1;
     pub const FLOAT: u8 = 2;
+    pub const PREMULTIPLIED: u8 = 4;
+    pub const COLOR_MODEL_MASK: u8 = 0x18;
+    pub const COLOR_MODEL_SHIFT: u8 = 3;
+    pub const CHROMA_SUBSAMPLING_MASK: u8 = 0x60;
+    pub const CHROMA_SUBSAMPLING_SHIFT: u8 = 5;
     pub const FOURCC: [u8; 4] = *b"XZIB";
 
     #[inline]
     pub fn new(head: Head) -> Self {
         Self {
             head,
+            thmb: None,
+            iccp: None,
+            colr: None,
             indx: None,
             meta: None,
             xmet: None,
+            xpkt: None,
+            exif: None,
+            anim: None,
             body: None,
+            frames: Vec::new(),
+            layers: Vec::new(),
+            levels: Vec::new(),
             foot: None,
         }
     }
@@ -240,6 +544,21 @@ impl XZIB {
         &self.head
     }
 
+    #[inline]
+    pub fn thmb(&self) -> Option<&Thmb> {
+        self.thmb.as_ref()
+    }
+
+    #[inline]
+    pub fn iccp(&self) -> Option<&Iccp> {
+        self.iccp.as_ref()
+    }
+
+    #[inline]
+    pub fn colr(&self) -> Option<&Colr> {
+        self.colr.as_ref()
+    }
+
     #[inline]
     pub fn indx(&self) -> Option<&Indx> {
         self.indx.as_ref()
@@ -255,16 +574,64 @@ impl XZIB {
         self.xmet.as_ref()
     }
 
+    #[inline]
+    pub fn xpkt(&self) -> Option<&Xpkt> {
+        self.xpkt.as_ref()
+    }
+
+    #[inline]
+    pub fn exif(&self) -> Option<&Exif> {
+        self.exif.as_ref()
+    }
+
+    #[inline]
+    pub fn anim(&self) -> Option<&Anim> {
+        self.anim.as_ref()
+    }
+
     #[inline]
     pub fn body(&self) -> Option<&Body> {
         self.body.as_ref()
     }
 
+    /// Animation frames after the first one, which is in the BODY chunk.
+    #[inline]
+    pub fn frames(&self) -> &[Fram] {
+        &self.frames
+    }
+
+    /// Layers from bottom to top.
+    #[inline]
+    pub fn layers(&self) -> &[Layr] {
+        &self.layers
+    }
+
+    /// Downscaled levels, from largest to smallest.
+    #[inline]
+    pub fn levels(&self) -> &[Levl] {
+        &self.levels
+    }
+
     #[inline]
     pub fn foot(&self) -> Option<&Foot> {
         self.foot.as_ref()
     }
 
+    #[inline]
+    pub fn thmb_mut(&mut self) -> &mut Option<Thmb> {
+        &mut self.thmb
+    }
+
+    #[inline]
+    pub fn iccp_mut(&mut self) -> &mut Option<Iccp> {
+        &mut self.iccp
+    }
+
+    #[inline]
+    pub fn colr_mut(&mut self) -> &mut Option<Colr> {
+        &mut self.colr
+    }
+
     #[inline]
     pub fn indx_mut(&mut self) -> &mut Option<Indx> {
         &mut self.indx
@@ -285,55 +652,153 @@ impl XZIB {
         &mut self.xmet
     }
 
+    #[inline]
+    pub fn xpkt_mut(&mut self) -> &mut Option<Xpkt> {
+        &mut self.xpkt
+    }
+
+    #[inline]
+    pub fn exif_mut(&mut self) -> &mut Option<Exif> {
+        &mut self.exif
+    }
+
+    #[inline]
+    pub fn anim_mut(&mut self) -> &mut Option<Anim> {
+        &mut self.anim
+    }
+
     #[inline]
     pub fn body_mut(&mut self) -> &mut Option<Body> {
         &mut self.body
     }
 
+    #[inline]
+    pub fn frames_mut(&mut self) -> &mut Vec<Fram> {
+        &mut self.frames
+    }
+
+    #[inline]
+    pub fn layers_mut(&mut self) -> &mut Vec<Layr> {
+        &mut self.layers
+    }
+
+    #[inline]
+    pub fn levels_mut(&mut self) -> &mut Vec<Levl> {
+        &mut self.levels
+    }
+
     #[inline]
     pub fn foot_mut(&mut self) -> &mut Option<Foot> {
         &mut self.foot
     }
 
+    /// Reorders the colors of the INDX chunk and rewrites the indices in the
+    /// BODY, FRAM, and LAYR chunks accordingly. Does nothing for images without
+    /// a palette.
+    pub fn reorder_palette(&mut self, order: PaletteOrder) -> Result<(), InvalidParams> {
+        let (Some(indx), Some(body)) = (&mut self.indx, &mut self.body) else {
+            return Ok(());
+        };
+
+        let color_count = indx.colors().len();
+        for (index, fram) in self.frames.iter().enumerate() {
+            if let Some((_, palette_index)) = find_invalid_index_variant(fram.body().data(), color_count) {
+                return Err(InvalidParams::with_message(
+                    format!("palette index {palette_index} out of range for {color_count} colors in frame {}", index + 1)));
+            }
+        }
+
+        for layr in &self.layers {
+            if let Some((_, palette_index)) = find_invalid_index_variant(layr.body().data(), color_count) {
+                return Err(InvalidParams::with_message(
+                    format!("palette index {palette_index} out of range for {color_count} colors in layer {:?}", layr.name())));
+            }
+        }
+
+        for (index, levl) in self.levels.iter().enumerate() {
+            if let Some((_, palette_index)) = find_invalid_index_variant(levl.body().data(), color_count) {
+                return Err(InvalidParams::with_message(
+                    format!("palette index {palette_index} out of range for {color_count} colors in level {}", index + 1)));
+            }
+        }
+
+        let mapping = reorder_palette(indx, body, self.head.width(), order)?;
+        for fram in &mut self.frames {
+            remap_indices(fram.body_mut().data_mut(), &mapping);
+        }
+
+        for layr in &mut self.layers {
+            remap_indices(layr.body_mut().data_mut(), &mapping);
+        }
+
+        for levl in &mut self.levels {
+            remap_indices(levl.body_mut().data_mut(), &mapping);
+        }
+
+        Ok(())
+    }
+
     pub fn image_data(&self) -> Option<Cow<ColorList>> {
         let Some(body) = &self.body else {
             return None;
         };
 
-        let data = body.data();
+        Some(self.apply_indx(body.data()))
+    }
 
+    /// Applies the palette if there is one and `data` are indices.
+    fn apply_indx<'a>(&self, data: &'a ColorList) -> Cow<'a, ColorList> {
         if let Some(indx) = &self.indx {
             match data {
                 ChannelVariant::U8(data) => {
                     if let ColorVariant::L(data) = data {
-                        return Some(Cow::Owned(apply_palette_variant(data, indx.colors())))
+                        return Cow::Owned(apply_palette_variant(data, indx.colors()))
                     }
                 }
                 ChannelVariant::U16(data) => {
                     if let ColorVariant::L(data) = data {
-                        return Some(Cow::Owned(apply_palette_variant(data, indx.colors())))
+                        return Cow::Owned(apply_palette_variant(data, indx.colors()))
                     }
                 }
                 ChannelVariant::U32(data) => {
                     if let ColorVariant::L(data) = data {
-                        return Some(Cow::Owned(apply_palette_variant(data, indx.colors())))
+                        return Cow::Owned(apply_palette_variant(data, indx.colors()))
                     }
                 }
                 ChannelVariant::U64(data) => {
                     if let ColorVariant::L(data) = data {
-                        return Some(Cow::Owned(apply_palette_variant(data, indx.colors())))
+                        return Cow::Owned(apply_palette_variant(data, indx.colors()))
                     }
                 }
                 ChannelVariant::U128(data) => {
                     if let ColorVariant::L(data) = data {
-                        return Some(Cow::Owned(apply_palette_variant(data, indx.colors())))
+                        return Cow::Owned(apply_palette_variant(data, indx.colors()))
                     }
                 }
                 _ => {}
             }
         }
 
-        Some(Cow::Borrowed(data))
+        Cow::Borrowed(data)
+    }
+
+    /// Colors of a BODY, FRAM, LAYR or LEVL chunk with the palette applied,
+    /// converted to RGB (or L or RGBA) and with straight alpha.
+    pub fn body_rgb_data(&self, body: &Body) -> Result<ColorList, InvalidParams> {
+        let color_model = self.head.color_model()?;
+        let data = self.apply_indx(body.data());
+
+        let mut data = if color_model == ColorModel::Rgb {
+            data.into_owned()
+        } else {
+            to_rgb_variant(&data, color_model)?.into_owned()
+        };
+
+        if self.head.is_premultiplied() {
+            data.unpremultiply_alpha();
+        }
+
+        Ok(data)
     }
 
     pub fn into_image_data(self) -> Option<ColorList> {
@@ -377,51 +842,68 @@ impl XZIB {
         Some(data)
     }
 
-    pub fn read<R>(reader: &mut R) -> Result<Self, ReadError>
-    where R: Read + Seek {
-        let head = Head::read(reader)?;
+    /// Image data with the palette applied and converted from the color model
+    /// defined in the header to RGB (or L or RGBA).
+    pub fn rgb_image_data(&self) -> Result<Option<Cow<'_, ColorList>>, InvalidParams> {
+        let color_model = self.head.color_model()?;
 
-        if head.channels() == 0 {
-            return Err(ReadError::with_message(
-                ReadErrorKind::BrokenFile,
-                format!("channels == 0")
-            ));
+        let Some(data) = self.image_data() else {
+            return Ok(None);
+        };
+
+        if color_model == ColorModel::Rgb {
+            return Ok(Some(data));
         }
 
-        if head.planes() == 0 {
-            return Err(ReadError::with_message(
-                ReadErrorKind::BrokenFile,
-                format!("planes == 0")
-            ));
+        Ok(Some(Cow::Owned(to_rgb_variant(&data, color_model)?.into_owned())))
+    }
+
+    pub fn into_rgb_image_data(self) -> Result<Option<ColorList>, InvalidParams> {
+        let color_model = self.head.color_model()?;
+
+        let Some(data) = self.into_image_data() else {
+            return Ok(None);
+        };
+
+        if color_model == ColorModel::Rgb {
+            return Ok(Some(data));
         }
 
+        Ok(Some(to_rgb_variant(&data, color_model)?.into_owned()))
+    }
+
+    #[inline]
+    pub fn read<R>(reader: &mut R) -> Result<Self, ReadError>
+    where R: Read + Seek {
+        Self::read_with_palette_index_mode(reader, PaletteIndexMode::Strict)
+    }
+
+    pub fn read_with_palette_index_mode<R>(reader: &mut R, palette_index_mode: PaletteIndexMode) -> Result<Self, ReadError>
+    where R: Read + Seek {
+        let head = Head::read(reader)?;
+
+        head.validate()?;
+
+        let mut thmb: Option<Thmb> = None;
+        let mut iccp: Option<Iccp> = None;
+        let mut colr: Option<Colr> = None;
         let mut indx: Option<Indx> = None;
         let mut meta: Option<Meta> = None;
         let mut xmet: Option<Xmet> = None;
+        let mut xpkt: Option<Xpkt> = None;
+        let mut exif: Option<Exif> = None;
+        let mut anim: Option<Anim> = None;
         let mut body: Option<Body> = None;
+        let mut frames: Vec<Fram> = Vec::new();
+        let mut layers: Vec<Layr> = Vec::new();
+        let mut levels: Vec<Levl> = Vec::new();
         let mut foot: Option<Foot> = None;
 
         let mut buf = Vec::new();
         let mut decompr = Vec::new();
 
-        loop {
-            let fourcc = match read_fourcc(reader) {
-                Ok(fourcc) => fourcc,
-                Err(err) => {
-                    if err.kind() == std::io::ErrorKind::UnexpectedEof {
-                        break;
-                    }
-                    return Err(err.into());
-                }
-            };
-
-            let chunk_size = if fourcc[0].is_ascii_uppercase() {
-                read_u64(reader)? as usize
-            } else {
-                read_u32(reader)? as usize
-            };
-
-            buf.resize(chunk_size, 0u8);
+        while let Some((fourcc, chunk_size)) = Self::read_chunk_head(reader)? {
+            buf.resize(chunk_size as usize, 0u8);
             reader.read_exact(&mut buf)?;
 
             let chunk_data = if fourcc[1].is_ascii_lowercase() {
@@ -441,6 +923,15 @@ impl XZIB {
             ];
 
             match fourcc {
+                Thmb::FOURCC => {
+                    thmb = Some(Thmb::read(chunk_data)?);
+                }
+                Iccp::FOURCC => {
+                    iccp = Some(Iccp::read(chunk_data)?);
+                }
+                Colr::FOURCC => {
+                    colr = Some(Colr::read(chunk_data)?);
+                }
                 Indx::FOURCC => {
                     indx = Some(Indx::read(chunk_data, &head)?);
                 }
@@ -450,9 +941,27 @@ impl XZIB {
                 Xmet::FOURCC => {
                     xmet = Some(Xmet::read(chunk_data)?);
                 }
+                Xpkt::FOURCC => {
+                    xpkt = Some(Xpkt::read(chunk_data)?);
+                }
+                Exif::FOURCC => {
+                    exif = Some(Exif::read(chunk_data)?);
+                }
+                Anim::FOURCC => {
+                    anim = Some(Anim::read(chunk_data)?);
+                }
                 Body::FOURCC => {
                     body = Some(Body::read(chunk_data, &head)?);
                 }
+                Fram::FOURCC => {
+                    frames.push(Fram::read(chunk_data, &head)?);
+                }
+                Layr::FOURCC => {
+                    layers.push(Layr::read(chunk_data, &head)?);
+                }
+                Levl::FOURCC => {
+                    levels.push(Levl::read(chunk_data, &head)?);
+                }
                 Foot::FOURCC => {
                     foot = Some(Foot::read(chunk_data)?);
                 }
@@ -462,23 +971,204 @@ impl XZIB {
             }
         }
 
+        if !frames.is_empty() && anim.is_none() {
+            return Err(ReadError::with_message(
+                ReadErrorKind::BrokenFile,
+                "FRAM chunks require an ANIM chunk"
+            ));
+        }
+
+        if head.is_indexed() {
+            let bodies = body.iter_mut().map(|body| (" of the BODY chunk".to_owned(), head.width(), body))
+                .chain(frames.iter_mut().enumerate().map(|(index, fram)| (format!(" of frame {}", index + 1), fram.width(), fram.body_mut())))
+                .chain(layers.iter_mut().map(|layr| (format!(" of layer {:?}", layr.name()), layr.width(), layr.body_mut())))
+                .chain(levels.iter_mut().enumerate().map(|(index, levl)| (format!(" of level {}", index + 1), levl.width(), levl.body_mut())));
+
+            Self::validate_indices(&head, &mut indx, bodies, palette_index_mode)?;
+        }
+
         Ok(Self {
             head,
+            thmb,
+            iccp,
+            colr,
             indx,
             meta,
             xmet,
+            xpkt,
+            exif,
+            anim,
             body,
+            frames,
+            layers,
+            levels,
             foot,
         })
     }
 
+    /// Reads the fourcc and size of the next chunk. `None` at the end of the
+    /// file.
+    fn read_chunk_head(reader: &mut impl Read) -> Result<Option<([u8; 4], u64)>, ReadError> {
+        let fourcc = match read_fourcc(reader) {
+            Ok(fourcc) => fourcc,
+            Err(err) => {
+                if err.kind() == std::io::ErrorKind::UnexpectedEof {
+                    return Ok(None);
+                }
+                return Err(err.into());
+            }
+        };
+
+        let chunk_size = if fourcc[0].is_ascii_uppercase() {
+            read_u64(reader)?
+        } else {
+            read_u32(reader)? as u64
+        };
+
+        Ok(Some((fourcc, chunk_size)))
+    }
+
+    /// `bodies` are all chunks with pixels, with the location used in error
+    /// messages and their width.
+    fn validate_indices<'a>(head: &Head, indx: &mut Option<Indx>, bodies: impl Iterator<Item = (String, u32, &'a mut Body)>, palette_index_mode: PaletteIndexMode) -> Result<(), ReadError> {
+        let fallback = match palette_index_mode {
+            PaletteIndexMode::Strict => {
+                if indx.is_none() {
+                    return Err(ReadError::with_message(
+                        ReadErrorKind::BrokenFile,
+                        format!("INDX chunk is required for index_planes == {}", head.index_planes())
+                    ));
+                }
+                None
+            }
+            PaletteIndexMode::Lenient(fallback) => Some(fallback),
+        };
+
+        let indx = match indx {
+            Some(indx) => indx,
+            None => {
+                let format = match (head.index_channel_value_type(), head.color_type()) {
+                    (Ok(Some(channel_value_type)), Ok(color_type)) => Format(channel_value_type, color_type),
+                    (Err(err), _) | (_, Err(err)) => return Err(ReadError::with_all(
+                        ReadErrorKind::BrokenFile,
+                        "illegal palette format",
+                        Box::new(err))),
+                    (Ok(None), _) => return Ok(()),
+                };
+                indx.insert(Indx::new(format.make_color_list()))
+            }
+        };
+
+        let color_count = indx.colors().len();
+        let mut has_fallback = false;
+
+        for (location, width, body) in bodies {
+            let Some((offset, index)) = find_invalid_index_variant(body.data(), color_count) else {
+                continue;
+            };
+
+            let Some(fallback) = fallback else {
+                let width = width.max(1) as usize;
+                return Err(ReadError::with_message(
+                    ReadErrorKind::BrokenFile,
+                    format!("palette index {index} out of range for {color_count} colors at pixel x: {}, y: {}{location}",
+                        offset % width, offset / width)
+                ));
+            };
+
+            if !has_fallback {
+                // there is an out of range index, so the palette can't already
+                // use up all the index values and there is room for one more color
+                let mut fallback = from_pixels(indx.colors(), &[fallback]);
+                if let Err(err) = indx.colors_mut().append(&mut fallback) {
+                    return Err(ReadError::with_all(
+                        ReadErrorKind::BrokenFile,
+                        "illegal palette format",
+                        Box::new(err)));
+                }
+                has_fallback = true;
+            }
+            replace_invalid_indices_variant(body.data_mut(), color_count, color_count);
+        }
+
+        Ok(())
+    }
+
+    /// Like [`XZIB::write`], but also fails if a license entry of the META
+    /// chunk isn't a valid SPDX license expression.
+    pub fn write_strict(&self, writer: &mut impl Write, compression: u32) -> Result<(), WriteError> {
+        if let Some(Err(err)) = self.meta.as_ref().map(Meta::check_licenses) {
+            return Err(WriteError::with_all(
+                WriteErrorKind::InvalidParams,
+                "illegal META chunk",
+                Box::new(err)));
+        }
+
+        self.write(writer, compression)
+    }
+
+    /// Writes the file. The output only depends on the chunks and
+    /// `compression`, writing equal images gives identical bytes.
     pub fn write(&self, writer: &mut impl Write, compression: u32) -> Result<(), WriteError> {
+        if self.head.is_indexed() {
+            let Some(indx) = &self.indx else {
+                return Err(WriteError::with_message(
+                    WriteErrorKind::InvalidParams,
+                    format!("INDX chunk is required for index_planes == {}", self.head.index_planes())));
+            };
+
+            let color_count = indx.colors().len();
+            for (location, width, body) in self.bodies() {
+                if let Some((offset, index)) = find_invalid_index_variant(body.data(), color_count) {
+                    let width = width.max(1) as usize;
+                    return Err(WriteError::with_message(
+                        WriteErrorKind::InvalidParams,
+                        format!("palette index {index} out of range for {color_count} colors at pixel x: {}, y: {}{location}",
+                            offset % width, offset / width)));
+                }
+            }
+        }
+
+        if !self.frames.is_empty() && self.anim.is_none() {
+            return Err(WriteError::with_message(
+                WriteErrorKind::InvalidParams,
+                "FRAM chunks require an ANIM chunk"));
+        }
+
+        for (location, _, body) in self.bodies() {
+            if self.indx.is_some() && body.data().color_type() != ColorType::L {
+                return Err(WriteError::with_message(
+                    WriteErrorKind::InvalidParams,
+                    format!("using an index the colors{location} must be of type L, but were: {}",
+                        body.data().color_type())));
+            }
+
+            if self.head.is_chroma_subsampled() && body.data().color_type() != ColorType::Rgb {
+                return Err(WriteError::with_message(
+                    WriteErrorKind::InvalidParams,
+                    format!("using chroma subsampling the colors{location} must be of type RGB (YCbCr), but were: {}",
+                        body.data().color_type())));
+            }
+        }
+
         self.head.write(writer)?;
 
         let mut buf = Vec::new();
 
         let compression = Compression::new(compression);
 
+        if let Some(thmb) = &self.thmb {
+            self.write_chunk(&mut buf, writer, thmb, compression)?;
+        }
+
+        if let Some(iccp) = &self.iccp {
+            self.write_chunk(&mut buf, writer, iccp, compression)?;
+        }
+
+        if let Some(colr) = &self.colr {
+            self.write_chunk(&mut buf, writer, colr, compression)?;
+        }
+
         if let Some(indx) = &self.indx {
             self.write_chunk(&mut buf, writer, indx, compression)?;
         }
@@ -491,17 +1181,34 @@ impl XZIB {
             self.write_chunk(&mut buf, writer, xmet, compression)?;
         }
 
-        if let Some(body) = &self.body {
-            if self.indx.is_some() && body.data().color_type() != ColorType::L {
-                return Err(WriteError::with_message(
-                    WriteErrorKind::InvalidParams,
-                    format!("using an index the BODY chunk must be of type L, but was: {}",
-                        body.data().color_type())));
-            }
+        if let Some(xpkt) = &self.xpkt {
+            self.write_chunk(&mut buf, writer, xpkt, compression)?;
+        }
+
+        if let Some(exif) = &self.exif {
+            self.write_chunk(&mut buf, writer, exif, compression)?;
+        }
 
+        if let Some(anim) = &self.anim {
+            self.write_chunk(&mut buf, writer, anim, compression)?;
+        }
+
+        if let Some(body) = &self.body {
             self.write_chunk(&mut buf, writer, body, compression)?;
         }
 
+        for fram in &self.frames {
+            self.write_chunk(&mut buf, writer, fram, compression)?;
+        }
+
+        for layr in &self.layers {
+            self.write_chunk(&mut buf, writer, layr, compression)?;
+        }
+
+        for levl in &self.levels {
+            self.write_chunk(&mut buf, writer, levl, compression)?;
+        }
+
         if let Some(foot) = &self.foot {
             // TODO: pipe all writes through a writer that calculates the checksum!
             self.write_chunk(&mut buf, writer, foot, compression)?;
@@ -510,6 +1217,15 @@ impl XZIB {
         Ok(())
     }
 
+    /// All chunks with pixels, with the location used in error messages and
+    /// their width.
+    fn bodies(&self) -> impl Iterator<Item = (String, u32, &Body)> {
+        self.body.iter().map(|body| (" of the BODY chunk".to_owned(), self.head.width(), body))
+            .chain(self.frames.iter().enumerate().map(|(index, fram)| (format!(" of frame {}", index + 1), fram.width(), fram.body())))
+            .chain(self.layers.iter().map(|layr| (format!(" of layer {:?}", layr.name()), layr.width(), layr.body())))
+            .chain(self.levels.iter().enumerate().map(|(index, levl)| (format!(" of level {}", index + 1), levl.width(), levl.body())))
+    }
+
     fn write_chunk<CW: ChunkWrite>(&self, mut buf: &mut Vec<u8>, writer: &mut impl Write, chunk: &CW, compression: Compression) -> Result<(), WriteError> {
         buf.clear();
         let mut fourcc = CW::FOURCC;
@@ -537,27 +1253,63 @@ impl XZIB {
     }
 }
 
+/// A calendar date, optionally only the year or the year and month, with an
+/// optional time of day. Month and day are `0` if they are unknown.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub struct Date {
     year: u16,
     month: u8,
     day:   u8,
+    time:  Option<Time>,
+}
+
+#[inline]
+fn illegal_date(value: &str) -> IllegalDate {
+    IllegalDate::with_message(format!("illegal date: {value:?}"))
+}
+
+/// Parses exactly `digits` ASCII digits.
+fn parse_digits<T: std::str::FromStr>(value: &str, digits: usize) -> Option<T> {
+    if value.len() != digits || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+#[inline]
+fn is_leap_year(year: u16) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
 }
 
 impl Date {
     #[inline]
     pub fn new(year: u16, month: u8, day: u8) -> Self {
-        Self { year, month, day }
+        Self { year, month, day, time: None }
     }
 
     #[inline]
     pub fn from_year(year: u16) -> Self {
-        Self { year, month: 0, day: 0 }
+        Self { year, month: 0, day: 0, time: None }
     }
 
     #[inline]
     pub fn from_year_and_month(year: u16, month: u8) -> Self {
-        Self { year, month, day: 0 }
+        Self { year, month, day: 0, time: None }
+    }
+
+    #[inline]
+    pub fn with_time(year: u16, month: u8, day: u8, time: Time) -> Self {
+        Self { year, month, day, time: Some(time) }
     }
 
     #[inline]
@@ -575,6 +1327,11 @@ impl Date {
         self.day
     }
 
+    #[inline]
+    pub fn time(&self) -> Option<&Time> {
+        self.time.as_ref()
+    }
+
     #[inline]
     pub fn set_year(&mut self, value: u16) {
         self.year = value;
@@ -590,45 +1347,427 @@ impl Date {
         self.day = value;
     }
 
+    #[inline]
+    pub fn set_time(&mut self, value: Option<Time>) {
+        self.time = value;
+    }
+
     #[inline]
     pub fn is_null(&self) -> bool {
-        (self.year & self.month as u16 & self.day as u16) == 0
+        (self.year | self.month as u16 | self.day as u16) == 0 && self.time.is_none()
     }
 
+    /// Checks the ranges of month, day and time. A day requires a month and
+    /// a time requires a day.
+    pub fn validate(&self) -> Result<(), IllegalDate> {
+        if self.month > 12 {
+            return Err(IllegalDate::with_message(format!("illegal month: {}", self.month)));
+        }
+
+        if self.day != 0 {
+            if self.month == 0 {
+                return Err(IllegalDate::with_message("day without month"));
+            }
+
+            let days = days_in_month(self.year, self.month);
+            if self.day > days {
+                return Err(IllegalDate::with_message(
+                    format!("illegal day: {}, {:04}-{:02} has {days} days", self.day, self.year, self.month)));
+            }
+        }
+
+        if let Some(time) = &self.time {
+            if self.day == 0 {
+                return Err(IllegalDate::with_message("time without day"));
+            }
+            time.validate()?;
+        }
+
+        Ok(())
+    }
+
+    /// Parses `YYYY`, `YYYY-MM`, `YYYY-MM-DD` and RFC 3339 timestamps like
+    /// `YYYY-MM-DDTHH:MM:SS.sssZ` or `YYYY-MM-DD HH:MM:SS+01:00`.
     pub fn parse(value: &str) -> Result<Date, IllegalDate> {
-        let mut iter = value.split('-');
+        let (date, time) = match value.find(['T', 't', ' ']) {
+            Some(index) => (&value[..index], Some(&value[index + 1..])),
+            None => (value, None),
+        };
+
+        let mut iter = date.split('-');
 
-        let Some(year) = iter.next() else {
-            return Err(IllegalDate::with_message(format!("illegal date: {value:?}")));
+        let Some(year) = iter.next().and_then(|year| parse_digits(year, 4)) else {
+            return Err(illegal_date(value));
         };
-        let Some(month) = iter.next() else {
-            return Err(IllegalDate::with_message(format!("illegal date: {value:?}")));
+
+        // 0 means unknown, so it can't be given explicitly
+        let month = match iter.next() {
+            Some(month) => parse_digits(month, 2).filter(|&month| month != 0).ok_or_else(|| illegal_date(value))?,
+            None => 0,
         };
-        let Some(day) = iter.next() else {
-            return Err(IllegalDate::with_message(format!("illegal date: {value:?}")));
+
+        let day = match iter.next() {
+            Some(day) => parse_digits(day, 2).filter(|&day| day != 0).ok_or_else(|| illegal_date(value))?,
+            None => 0,
         };
 
-        if let Some(_) = iter.next() {
-            return Err(IllegalDate::with_message(format!("illegal date: {value:?}")));
+        if iter.next().is_some() {
+            return Err(illegal_date(value));
         }
 
-        let Ok(year) = year.parse() else {
-            return Err(IllegalDate::with_message(format!("illegal date: {value:?}")));
-        };
-        let Ok(month) = month.parse() else {
-            return Err(IllegalDate::with_message(format!("illegal date: {value:?}")));
-        };
-        let Ok(day) = day.parse() else {
-            return Err(IllegalDate::with_message(format!("illegal date: {value:?}")));
+        let time = match time {
+            Some(time) => match Time::parse(time) {
+                Ok(time) => Some(time),
+                Err(err) => return Err(IllegalDate::with_all(
+                    format!("illegal date: {value:?}"),
+                    Box::new(err))),
+            },
+            None => None,
         };
 
-        Ok(Self { year, month, day })
+        let date = Self { year, month, day, time };
+        if let Err(err) = date.validate() {
+            return Err(IllegalDate::with_all(
+                format!("illegal date: {value:?}"),
+                Box::new(err)));
+        }
+
+        Ok(date)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Date {
+    /// A null date is serialized as none, other dates as string.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_null() {
+            serializer.serialize_none()
+        } else {
+            serializer.collect_str(self)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Date {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Option::<Cow<'de, str>>::deserialize(deserializer)? {
+            Some(value) => Date::parse(&value).map_err(serde::de::Error::custom),
+            None => Ok(Date::default()),
+        }
     }
 }
 
 impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}", self.year)?;
+
+        if self.month != 0 {
+            write!(f, "-{:02}", self.month)?;
+
+            if self.day != 0 {
+                write!(f, "-{:02}", self.day)?;
+
+                if let Some(time) = &self.time {
+                    write!(f, "T{time}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Time of day with a UTC offset, as used by RFC 3339.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Time {
+    hour:       u8,
+    minute:     u8,
+    second:     u8,
+    nanosecond: u32,
+    /// Minutes east of UTC.
+    offset:     i16,
+}
+
+impl Time {
+    #[inline]
+    pub fn new(hour: u8, minute: u8, second: u8) -> Self {
+        Self { hour, minute, second, nanosecond: 0, offset: 0 }
+    }
+
+    #[inline]
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    #[inline]
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    /// `60` for leap seconds.
+    #[inline]
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    #[inline]
+    pub fn nanosecond(&self) -> u32 {
+        self.nanosecond
+    }
+
+    /// Offset from UTC in minutes, positive east of UTC.
+    #[inline]
+    pub fn offset(&self) -> i16 {
+        self.offset
+    }
+
+    #[inline]
+    pub fn set_nanosecond(&mut self, value: u32) {
+        self.nanosecond = value;
+    }
+
     #[inline]
+    pub fn set_offset(&mut self, value: i16) {
+        self.offset = value;
+    }
+
+    pub fn validate(&self) -> Result<(), IllegalDate> {
+        if self.hour > 23 || self.minute > 59 || self.second > 60 || self.nanosecond > 999_999_999 {
+            return Err(IllegalDate::with_message(format!("illegal time: {self}")));
+        }
+
+        if self.offset.abs() >= 24 * 60 {
+            return Err(IllegalDate::with_message(format!("illegal UTC offset: {} minutes", self.offset)));
+        }
+
+        Ok(())
+    }
+
+    /// Parses `HH:MM:SS`, optionally with a fraction of a second, followed
+    /// by `Z` or an offset like `+01:00`. `-00:00`, which RFC 3339 uses for
+    /// an unknown local offset, is read as `Z`.
+    pub fn parse(value: &str) -> Result<Self, IllegalDate> {
+        let illegal_time = || IllegalDate::with_message(format!("illegal time: {value:?}"));
+
+        let Some(offset_index) = value.find(['Z', 'z', '+', '-']) else {
+            return Err(illegal_time());
+        };
+        let (time, offset) = value.split_at(offset_index);
+
+        let offset = match offset {
+            "Z" | "z" => 0,
+            _ => {
+                let sign = if offset.starts_with('-') { -1 } else { 1 };
+                let Some((hours, minutes)) = offset[1..].split_once(':') else {
+                    return Err(illegal_time());
+                };
+                let (Some(hours), Some(minutes)) = (parse_digits::<i16>(hours, 2), parse_digits::<i16>(minutes, 2)) else {
+                    return Err(illegal_time());
+                };
+                if minutes > 59 {
+                    return Err(illegal_time());
+                }
+                sign * (hours * 60 + minutes)
+            }
+        };
+
+        let (time, fraction) = match time.split_once('.') {
+            Some((time, fraction)) => (time, Some(fraction)),
+            None => (time, None),
+        };
+
+        let mut iter = time.split(':');
+        let (Some(hour), Some(minute), Some(second), None) = (
+            iter.next().and_then(|hour| parse_digits(hour, 2)),
+            iter.next().and_then(|minute| parse_digits(minute, 2)),
+            iter.next().and_then(|second| parse_digits(second, 2)),
+            iter.next(),
+        ) else {
+            return Err(illegal_time());
+        };
+
+        let nanosecond = match fraction {
+            Some(fraction) => {
+                if fraction.is_empty() || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+                    return Err(illegal_time());
+                }
+                // precision beyond nanoseconds is truncated
+                let digits = &fraction[..fraction.len().min(9)];
+                digits.parse::<u32>().map_err(|_| illegal_time())? * 10u32.pow(9 - digits.len() as u32)
+            }
+            None => 0,
+        };
+
+        let time = Self { hour, minute, second, nanosecond, offset };
+        time.validate()?;
+
+        Ok(time)
+    }
+}
+
+impl std::fmt::Display for Time {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+
+        if self.nanosecond != 0 {
+            let fraction = format!("{:09}", self.nanosecond);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+
+        if self.offset == 0 {
+            "Z".fmt(f)
+        } else {
+            let sign = if self.offset < 0 { '-' } else { '+' };
+            let offset = self.offset.unsigned_abs();
+            write!(f, "{sign}{:02}:{:02}", offset / 60, offset % 60)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn head_serde() {
+        let mut head = Head::new(NumberType::Integer, true, ColorType::Rgb, 12, 0, 640, 480).unwrap();
+        head.set_color_model(ColorModel::YCbCr).unwrap();
+        head.set_chroma_subsampling(ChromaSubsampling::Chroma420).unwrap();
+
+        let json = serde_json::to_value(head).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "number_type": "integer",
+            "interleaved": true,
+            "premultiplied": false,
+            "color_model": "ycbcr",
+            "chroma_subsampling": "4:2:0",
+            "channels": 3,
+            "planes": 12,
+            "index_planes": 0,
+            "width": 640,
+            "height": 480,
+        }));
+        assert_eq!(serde_json::from_value::<Head>(json.clone()).unwrap(), head);
+
+        for (key, value) in [
+            ("planes", serde_json::json!(0)),
+            ("channels", serde_json::json!(4)),
+            ("premultiplied", serde_json::json!(true)),
+            ("color_model", serde_json::json!("rgb")),
+            ("index_planes", serde_json::json!(4)),
+            ("width", serde_json::json!(0)),
+        ] {
+            let mut json = json.clone();
+            json[key] = value;
+            assert!(serde_json::from_value::<Head>(json).is_err(), "{key}");
+        }
+    }
+
+    #[test]
+    fn index_planes_need_8_bits() {
+        for index_planes in [1, 4] {
+            assert!(Head::new(NumberType::Integer, true, ColorType::Rgb, 2, index_planes, 1, 1).is_err());
+
+            let mut bytes = Vec::new();
+            Head { flags: XZIB::INTERLEAVED, channels: 3, planes: 2, index_planes, width: 1, height: 1 }.write(&mut bytes).unwrap();
+            assert!(Head::read(&mut &bytes[..]).unwrap().validate().is_err());
+        }
+
+        assert!(Head::new(NumberType::Integer, true, ColorType::Rgb, 2, 8, 1, 1).is_ok());
+        assert!(Head::new(NumberType::Float, true, ColorType::Rgb, 2, 32, 1, 1).is_ok());
+        assert!(Head::new(NumberType::Float, true, ColorType::Rgb, 2, 16, 1, 1).is_err());
+    }
+
+    #[test]
+    fn parse_partial_dates() {
+        assert_eq!(Date::parse("2024").unwrap(), Date::from_year(2024));
+        assert_eq!(Date::parse("2024-05").unwrap(), Date::from_year_and_month(2024, 5));
+        assert_eq!(Date::parse("2024-05-17").unwrap(), Date::new(2024, 5, 17));
+
+        for value in ["2024", "2024-05", "2024-05-17", "0001-01-01"] {
+            assert_eq!(Date::parse(value).unwrap().to_string(), value);
+        }
+
+        for value in ["", "24", "02024", "2024-5", "2024-05-7", "2024-05-17-01", "2024/05/17", "2024-", "-2024", "２０２４"] {
+            assert!(Date::parse(value).is_err(), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn leap_years_and_day_ranges() {
+        assert!(Date::parse("2024-02-29").is_ok());
+        assert!(Date::parse("2000-02-29").is_ok());
+        assert!(Date::parse("2023-02-29").is_err());
+        assert!(Date::parse("1900-02-29").is_err());
+        assert!(Date::parse("2023-02-28").is_ok());
+
+        assert!(Date::parse("2024-04-30").is_ok());
+        assert!(Date::parse("2024-04-31").is_err());
+        assert!(Date::parse("2024-12-31").is_ok());
+        assert!(Date::parse("2024-01-32").is_err());
+        assert!(Date::parse("2024-01-00").is_err());
+        assert!(Date::parse("2024-00").is_err());
+        assert!(Date::parse("2024-13").is_err());
+
+        assert!(Date::new(2024, 0, 1).validate().is_err());
+        assert!(Date::with_time(2024, 5, 0, Time::new(12, 0, 0)).validate().is_err());
+    }
+
+    #[test]
+    fn parse_rfc3339() {
+        let date = Date::parse("2024-05-17T13:45:30Z").unwrap();
+        assert_eq!(date, Date::with_time(2024, 5, 17, Time::new(13, 45, 30)));
+        assert_eq!(date.to_string(), "2024-05-17T13:45:30Z");
+
+        let date = Date::parse("2024-05-17t13:45:30.25+01:30").unwrap();
+        let time = date.time().unwrap();
+        assert_eq!((time.hour(), time.minute(), time.second()), (13, 45, 30));
+        assert_eq!(time.nanosecond(), 250_000_000);
+        assert_eq!(time.offset(), 90);
+        assert_eq!(date.to_string(), "2024-05-17T13:45:30.25+01:30");
+
+        let date = Date::parse("2024-05-17 23:59:60.123456789123-08:00").unwrap();
+        let time = date.time().unwrap();
+        assert_eq!(time.second(), 60);
+        assert_eq!(time.nanosecond(), 123_456_789);
+        assert_eq!(time.offset(), -480);
+        assert_eq!(date.to_string(), "2024-05-17T23:59:60.123456789-08:00");
+
+        assert_eq!(Date::parse("2024-05-17T13:45:30.000z").unwrap().to_string(), "2024-05-17T13:45:30Z");
+
+        // the unknown local offset isn't kept
+        assert_eq!(Date::parse("2024-05-17T13:45:30-00:00").unwrap().to_string(), "2024-05-17T13:45:30Z");
+
+        for value in [
+            "2024-05-17T",
+            "2024-05-17T13:45:30",
+            "2024-05-17T13:45Z",
+            "2024-05-17T24:00:00Z",
+            "2024-05-17T13:60:00Z",
+            "2024-05-17T13:45:61Z",
+            "2024-05-17T13:45:30.Z",
+            "2024-05-17T13:45:30+01",
+            "2024-05-17T13:45:30+0100",
+            "2024-05-17T13:45:30+01:60",
+            "2024-05-17T13:45:30+24:00",
+            "2024-05T13:45:30Z",
+            "2024T13:45:30Z",
+        ] {
+            assert!(Date::parse(value).is_err(), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn null_dates() {
+        assert!(Date::default().is_null());
+        assert!(Date::new(0, 0, 0).is_null());
+        assert!(!Date::from_year(2024).is_null());
+        assert!(!Date::new(0, 1, 1).is_null());
+
+        let mut date = Date::default();
+        date.set_time(Some(Time::default()));
+        assert!(!date.is_null());
     }
 }
@@ -1,8 +1,11 @@
-use std::{fs::File, io::{BufReader, BufWriter}, path::PathBuf};
+use std::{borrow::Cow, fs::File, io::{BufReader, BufWriter, Cursor, Write}, path::{Path, PathBuf}};
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use flate2::{write::ZlibEncoder, Compression, Crc};
+use image::{AnimationDecoder, ImageDecoder, ImageEncoder};
+use serde::{Deserialize, Serialize};
 
-use xzib::{chunks::Body, color::{ChannelValue, ChannelVariant, ColorList, ColorVariant, La, Rgb, Rgba}, make_error, XZIB};
+use xzib::{animation::{crop, Animation, AnimationFrame}, chunks::{colr::{Chromaticities, TransferFunction}, Body, Colr, Exif, Foot, Iccp, Meta, Xmet, Xpkt}, dither::{dither_planes, near_lossless_planes, Dithering}, color::{from_rgb_variant, ChannelValue, ChannelVariant, ColorList, ColorVariant, La, Rgb, Rgba}, format::{ChannelValueType, ChromaSubsampling, ColorModel}, make_error, palette::PaletteOrder, quantize::{planes_for_color_count, quantize, to_pixels, Pixel, QuantizeMethod}, reduce::{self, narrow}, resize::Filter, metadata::{Metadata, MetadataChunks}, raw::RawXZIB, spdx::{self, LicenseExpression}, Date, Head, PaletteIndexMode, XZIB};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -23,6 +26,88 @@ enum Command {
         #[clap(short, long, default_value_t = true, num_args = 1)]
         interleaved: bool,
 
+        /// Color model to store the image in. The input image is converted from RGB.
+        #[clap(long, value_enum, default_value_t = ColorModelArg::Rgb)]
+        color_model: ColorModelArg,
+
+        /// Chroma subsampling, only supported for the YCbCr color model.
+        #[clap(long, value_enum, default_value_t = ChromaSubsamplingArg::Chroma444)]
+        chroma_subsampling: ChromaSubsamplingArg,
+
+        /// Store colors with premultiplied alpha.
+        #[clap(long, default_value_t = false)]
+        premultiplied: bool,
+
+        /// Reduce the image to an indexed image with at most this many colors.
+        /// If given, --planes refers to the index values.
+        #[clap(long, default_value = None)]
+        colors: Option<usize>,
+
+        /// Algorithm used to find the palette for --colors.
+        #[clap(long, value_enum, default_value_t = QuantizeMethodArg::MedianCut)]
+        quantizer: QuantizeMethodArg,
+
+        /// Use Floyd–Steinberg dithering when mapping colors to the palette.
+        #[clap(long, default_value_t = false)]
+        dither: bool,
+
+        /// Reorder the colors of the palette of indexed images (--colors or
+        /// --reduce) so that the high index planes compress better.
+        #[clap(long, value_enum, default_value = None)]
+        palette_order: Option<PaletteOrderArg>,
+
+        /// Near-lossless encoding: use as few planes as possible while no
+        /// channel value changes by more than this. Given in units of the
+        /// input channel values, e.g. 2 means ±2 levels for 8 bit images.
+        #[clap(long, default_value = None, conflicts_with_all = ["planes", "colors"])]
+        max_error: Option<u128>,
+
+        /// Dithering used when reducing the number of planes via --planes or
+        /// --max-error.
+        #[clap(long, value_enum, default_value_t = DitheringArg::None)]
+        depth_dither: DitheringArg,
+
+        /// Losslessly pick the smallest color type, number of planes, and
+        /// whether to use a palette.
+        #[clap(long, default_value_t = false, conflicts_with_all = ["planes", "colors"])]
+        reduce: bool,
+
+        /// Delay in milliseconds between the frames of an animation that is
+        /// read from a directory of images.
+        #[clap(long, default_value_t = 100)]
+        frame_delay: u32,
+
+        /// How often an animation is played, 0 means forever.
+        #[clap(long, default_value_t = 0)]
+        loop_count: u32,
+
+        /// Maximum width and height of the embedded thumbnail. No thumbnail
+        /// is written if 0 or if the image isn't larger than that.
+        #[clap(long, default_value_t = 128)]
+        thumbnail_size: u32,
+
+        /// Store downscaled levels of half the size each (mipmaps), filtered
+        /// with the given filter.
+        #[clap(long, value_enum, default_value = None)]
+        levels: Option<FilterArg>,
+
+        /// Record the transfer function of the colors in a COLR chunk. Colors
+        /// aren't converted. Defaults to sRGB if only --primaries is given.
+        #[clap(long, value_enum, default_value = None)]
+        transfer_function: Option<TransferFunctionArg>,
+
+        /// Record the primaries and white point of the colors in a COLR
+        /// chunk. Colors aren't converted. Defaults to sRGB if only
+        /// --transfer-function is given.
+        #[clap(long, value_enum, default_value = None)]
+        primaries: Option<PrimariesArg>,
+
+        /// Embed the XMP packet of this sidecar file.
+        #[clap(long, default_value = None)]
+        xmp: Option<PathBuf>,
+
+        /// An image, an animated GIF or PNG, or a directory of images that are
+        /// the frames of an animation in the order of their file names.
         #[clap()]
         input: PathBuf,
 
@@ -31,6 +116,21 @@ enum Command {
     },
 
     Decode {
+        /// Don't fail on palette indices that are out of range, but map them
+        /// to this color instead. Given as RRGGBB or RRGGBBAA hex code.
+        #[clap(long, default_value = None, value_parser = parse_color)]
+        palette_fallback: Option<Pixel>,
+
+        /// Decode the smallest level that is at least this wide and high
+        /// instead of the full image.
+        #[clap(long, default_value = None)]
+        min_size: Option<u32>,
+
+        /// Write the XMP metadata to this sidecar file. Uses the embedded XMP
+        /// packet or converts the XMET chunk if there is none.
+        #[clap(long, default_value = None)]
+        xmp: Option<PathBuf>,
+
         #[clap()]
         input: PathBuf,
 
@@ -41,6 +141,335 @@ enum Command {
     Info {
         files: Vec<PathBuf>,
     },
+
+    /// Export, import or edit the metadata.
+    Meta {
+        #[command(subcommand)]
+        command: MetaCommand,
+    },
+
+    /// Check that the files can be read and that their metadata is valid,
+    /// e.g. that license entries are SPDX license expressions. Fails if any
+    /// file has errors.
+    Validate {
+        /// Also treat warnings like deprecated SPDX license IDs as errors.
+        #[clap(long, default_value_t = false)]
+        strict: bool,
+
+        files: Vec<PathBuf>,
+    },
+
+    /// Extract the embedded thumbnail without decoding the image.
+    Thumbnail {
+        #[clap()]
+        input: PathBuf,
+
+        #[clap()]
+        output: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum MetaCommand {
+    /// Write the header and the META, XMET and FOOT chunks as JSON to
+    /// standard output.
+    Export {
+        #[clap()]
+        input: PathBuf,
+    },
+
+    /// Replace the META and XMET chunks with the ones of a JSON file as
+    /// written by export. Chunks that are null are removed, missing ones are
+    /// kept as they are. The header and FOOT chunk are ignored. The other
+    /// chunks are copied without decoding them, a FOOT chunk gets a new CRC32
+    /// checksum.
+    Import {
+        #[clap(short, long, default_value_t = flate2::Compression::default().level())]
+        compression: u32,
+
+        /// Fail if a license isn't a valid SPDX license expression.
+        #[clap(long, default_value_t = false)]
+        strict: bool,
+
+        #[clap()]
+        json: PathBuf,
+
+        #[clap()]
+        file: PathBuf,
+    },
+
+    /// Change metadata fields of the files. The other chunks are copied
+    /// without decoding them, a FOOT chunk gets a new CRC32 checksum. List
+    /// options can be repeated and replace the whole list, pass an empty
+    /// value to remove a field.
+    Set(Box<MetaSetArgs>),
+}
+
+#[derive(Args)]
+struct MetaSetArgs {
+    #[clap(short, long, default_value_t = flate2::Compression::default().level())]
+    compression: u32,
+
+    /// Fail if a license isn't a valid SPDX license expression.
+    #[clap(long, default_value_t = false)]
+    strict: bool,
+
+    /// Write these chunks, removing the other one. Defaults to the chunks
+    /// the file has, or META if it has none. META is added to files with
+    /// only XMET if there is a copyright, which XMET can't store.
+    #[clap(long, default_value = None)]
+    chunks: Option<MetadataChunksArg>,
+
+    #[clap(long, default_value = None)]
+    title: Option<String>,
+
+    #[clap(long)]
+    author: Vec<String>,
+
+    /// YYYY, YYYY-MM, YYYY-MM-DD or an RFC 3339 timestamp.
+    #[clap(long, default_value = None, value_parser = parse_date)]
+    created_at: Option<Date>,
+
+    /// SPDX license expression.
+    #[clap(long)]
+    license: Vec<String>,
+
+    #[clap(long)]
+    link: Vec<String>,
+
+    #[clap(long, default_value = None)]
+    comment: Option<String>,
+
+    #[clap(long)]
+    keyword: Vec<String>,
+
+    #[clap(long, default_value = None)]
+    software: Option<String>,
+
+    #[clap(long, default_value = None)]
+    copyright: Option<String>,
+
+    /// Like --created-at.
+    #[clap(long, default_value = None, value_parser = parse_date)]
+    modified_at: Option<Date>,
+
+    #[clap(long, default_value = None)]
+    source: Option<String>,
+
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MetadataChunksArg {
+    Meta,
+    Xmet,
+    Both,
+}
+
+impl From<MetadataChunksArg> for MetadataChunks {
+    #[inline]
+    fn from(value: MetadataChunksArg) -> Self {
+        match value {
+            MetadataChunksArg::Meta => MetadataChunks::Meta,
+            MetadataChunksArg::Xmet => MetadataChunks::Xmet,
+            MetadataChunksArg::Both => MetadataChunks::Both,
+        }
+    }
+}
+
+/// The metadata of a file as exported by `xzib meta export`. `None` for a
+/// missing chunk and `Some(None)` for a chunk that is null.
+#[derive(Serialize, Deserialize)]
+struct MetadataExport {
+    head: Option<Head>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    meta: Option<Option<Meta>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    xmet: Option<Option<Xmet>>,
+    foot: Option<Foot>,
+}
+
+/// Distinguishes null from a missing field, which is `None` by `serde(default)`.
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where T: Deserialize<'de>, D: serde::Deserializer<'de> {
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorModelArg {
+    Rgb,
+    Cmyk,
+    #[value(name = "ycbcr")]
+    YCbCr,
+}
+
+impl From<ColorModelArg> for ColorModel {
+    #[inline]
+    fn from(value: ColorModelArg) -> Self {
+        match value {
+            ColorModelArg::Rgb   => ColorModel::Rgb,
+            ColorModelArg::Cmyk  => ColorModel::Cmyk,
+            ColorModelArg::YCbCr => ColorModel::YCbCr,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum QuantizeMethodArg {
+    MedianCut,
+    Octree,
+    KMeans,
+}
+
+impl From<QuantizeMethodArg> for QuantizeMethod {
+    #[inline]
+    fn from(value: QuantizeMethodArg) -> Self {
+        match value {
+            QuantizeMethodArg::MedianCut => QuantizeMethod::MedianCut,
+            QuantizeMethodArg::Octree    => QuantizeMethod::Octree,
+            QuantizeMethodArg::KMeans    => QuantizeMethod::KMeans,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PaletteOrderArg {
+    Luminance,
+    NearestNeighbor,
+    CoOccurrence,
+}
+
+impl From<PaletteOrderArg> for PaletteOrder {
+    #[inline]
+    fn from(value: PaletteOrderArg) -> Self {
+        match value {
+            PaletteOrderArg::Luminance       => PaletteOrder::Luminance,
+            PaletteOrderArg::NearestNeighbor => PaletteOrder::NearestNeighbor,
+            PaletteOrderArg::CoOccurrence    => PaletteOrder::CoOccurrence,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DitheringArg {
+    None,
+    Ordered,
+    Diffusion,
+}
+
+impl From<DitheringArg> for Dithering {
+    #[inline]
+    fn from(value: DitheringArg) -> Self {
+        match value {
+            DitheringArg::None      => Dithering::None,
+            DitheringArg::Ordered   => Dithering::Ordered,
+            DitheringArg::Diffusion => Dithering::Diffusion,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FilterArg {
+    Box,
+    Lanczos,
+}
+
+impl From<FilterArg> for Filter {
+    #[inline]
+    fn from(value: FilterArg) -> Self {
+        match value {
+            FilterArg::Box     => Filter::Box,
+            FilterArg::Lanczos => Filter::Lanczos,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TransferFunctionArg {
+    Srgb,
+    Linear,
+    Pq,
+    Hlg,
+}
+
+impl From<TransferFunctionArg> for TransferFunction {
+    #[inline]
+    fn from(value: TransferFunctionArg) -> Self {
+        match value {
+            TransferFunctionArg::Srgb   => TransferFunction::Srgb,
+            TransferFunctionArg::Linear => TransferFunction::Linear,
+            TransferFunctionArg::Pq     => TransferFunction::Pq,
+            TransferFunctionArg::Hlg    => TransferFunction::Hlg,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PrimariesArg {
+    Srgb,
+    DisplayP3,
+    Bt2020,
+    AdobeRgb,
+}
+
+impl From<PrimariesArg> for Chromaticities {
+    #[inline]
+    fn from(value: PrimariesArg) -> Self {
+        match value {
+            PrimariesArg::Srgb      => Chromaticities::SRGB,
+            PrimariesArg::DisplayP3 => Chromaticities::DISPLAY_P3,
+            PrimariesArg::Bt2020    => Chromaticities::BT2020,
+            PrimariesArg::AdobeRgb  => Chromaticities::ADOBE_RGB,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ChromaSubsamplingArg {
+    #[value(name = "444")]
+    Chroma444,
+    #[value(name = "422")]
+    Chroma422,
+    #[value(name = "420")]
+    Chroma420,
+}
+
+impl From<ChromaSubsamplingArg> for ChromaSubsampling {
+    #[inline]
+    fn from(value: ChromaSubsamplingArg) -> Self {
+        match value {
+            ChromaSubsamplingArg::Chroma444 => ChromaSubsampling::Chroma444,
+            ChromaSubsamplingArg::Chroma422 => ChromaSubsampling::Chroma422,
+            ChromaSubsamplingArg::Chroma420 => ChromaSubsampling::Chroma420,
+        }
+    }
+}
+
+/// An empty value is the null date, which removes the date.
+fn parse_date(value: &str) -> Result<Date, String> {
+    if value.is_empty() {
+        return Ok(Date::default());
+    }
+
+    Date::parse(value).map_err(|err| err.message().unwrap_or("illegal date").to_owned())
+}
+
+fn parse_color(value: &str) -> Result<Pixel, String> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return Err(format!("expected RRGGBB or RRGGBBAA, but was: {value:?}"));
+    }
+
+    let mut pixel = [1.0; 4];
+    for (channel, index) in pixel.iter_mut().zip((0..hex.len()).step_by(2)) {
+        let Ok(byte) = u8::from_str_radix(&hex[index..index + 2], 16) else {
+            return Err(format!("illegal hex code: {value:?}"));
+        };
+        *channel = byte as f64 / 255.0;
+    }
+
+    Ok(pixel)
 }
 
 make_error! {
@@ -52,11 +481,16 @@ make_error! {
         InvalidParams,
         WriteError,
         ReadError,
+        Png,
+        Json,
     }
     impl IO: std::io::Error;
     impl Image: image::ImageError;
+    impl Png: png::EncodingError;
     impl InvalidParams: xzib::error::InvalidParams;
     impl WriteError: xzib::error::WriteError;
+    impl Json: serde_json::Error;
+    impl InvalidParams: xzib::error::IllegalLicense;
     impl ReadError: xzib::error::ReadError;
 }
 
@@ -64,119 +498,188 @@ pub fn main() -> Result<(), CliError> {
     let args = Cli::parse();
 
     match args.command {
-        Command::Encode { compression, planes, interleaved, input, output } => {
-            let img = image::ImageReader::open(input)?.decode()?;
-            let width = img.width();
-            let height = img.height();
-
-            let data: ColorList = match img.color() {
-                image::ColorType::L8 => {
-                    ChannelVariant::U8(ColorVariant::L(img.into_luma8().into_vec()))
-                },
-                image::ColorType::L16 => {
-                    ChannelVariant::U16(ColorVariant::L(img.into_luma16().into_vec()))
-                },
-                image::ColorType::Rgb8 => {
-                    ChannelVariant::U8(ColorVariant::Rgb(
-                        img.into_rgb8().pixels().into_iter().map(
-                            |&image::Rgb(color)| Rgb(color)
-                        ).collect()))
-                },
-                image::ColorType::Rgb16 => {
-                    ChannelVariant::U16(ColorVariant::Rgb(
-                        img.into_rgb16().pixels().into_iter().map(
-                            |&image::Rgb(color)| Rgb(color)
-                        ).collect()))
-                },
-                image::ColorType::Rgba8 => {
-                    ChannelVariant::U8(ColorVariant::Rgba(
-                        img.into_rgba8().pixels().into_iter().map(
-                            |&image::Rgba(color)| Rgba(color)
-                        ).collect()))
-                },
-                image::ColorType::Rgba16 => {
-                    ChannelVariant::U16(ColorVariant::Rgba(
-                        img.into_rgba16().pixels().into_iter().map(
-                            |&image::Rgba(color)| Rgba(color)
-                        ).collect()))
-                },
-                image::ColorType::Rgb32F => {
-                    ChannelVariant::F32(ColorVariant::Rgba(
-                        img.into_rgba32f().pixels().into_iter().map(
-                            |&image::Rgba(color)| Rgba(color)
-                        ).collect()))
-                },
-                image::ColorType::Rgba32F => {
-                    ChannelVariant::F32(ColorVariant::Rgba(
-                        img.into_rgba32f().pixels().into_iter().map(
-                            |&image::Rgba(color)| Rgba(color)
-                        ).collect()))
-                },
-                image::ColorType::La8 => {
-                    ChannelVariant::U8(ColorVariant::La(
-                        img.into_luma_alpha8().pixels().into_iter().map(
-                            |&image::LumaA(color)| La(color)
-                        ).collect()))
-                },
-                image::ColorType::La16 => {
-                    ChannelVariant::U16(ColorVariant::La(
-                        img.into_luma_alpha16().pixels().into_iter().map(
-                            |&image::LumaA(color)| La(color)
-                        ).collect()))
-                },
-                _ => {
-                    let bits_per_channel = img.color().bits_per_pixel() / img.color().channel_count() as u16;
-                    if img.color().has_alpha() {
-                        if bits_per_channel <= 8 {
-                            ChannelVariant::U8(ColorVariant::Rgba(
-                                img.into_rgba8().pixels().into_iter().map(
-                                    |&image::Rgba(color)| Rgba(color)
-                                ).collect()))
-                        } else if bits_per_channel <= 16 {
-                            ChannelVariant::U16(ColorVariant::Rgba(
-                                img.into_rgba16().pixels().into_iter().map(
-                                    |&image::Rgba(color)| Rgba(color)
-                                ).collect()))
-                        } else {
-                            ChannelVariant::F32(ColorVariant::Rgba(
-                                img.into_rgba32f().pixels().into_iter().map(
-                                    |&image::Rgba(color)| Rgba(color)
-                                ).collect()))
-                        }
-                    } else {
-                        if bits_per_channel <= 8 {
-                            ChannelVariant::U8(ColorVariant::Rgb(
-                                img.into_rgb8().pixels().into_iter().map(
-                                    |&image::Rgb(color)| Rgb(color)
-                                ).collect()))
-                        } else if bits_per_channel <= 16 {
-                            ChannelVariant::U16(ColorVariant::Rgb(
-                                img.into_rgb16().pixels().into_iter().map(
-                                    |&image::Rgb(color)| Rgb(color)
-                                ).collect()))
-                        } else {
-                            ChannelVariant::F32(ColorVariant::Rgb(
-                                img.into_rgb32f().pixels().into_iter().map(
-                                    |&image::Rgb(color)| Rgb(color)
-                                ).collect()))
-                        }
-                    }
+        Command::Encode { compression, planes, interleaved, color_model, chroma_subsampling, premultiplied, colors, quantizer, dither, palette_order, max_error, depth_dither, reduce, frame_delay, loop_count, thumbnail_size, levels, transfer_function, primaries, xmp, input, output } => {
+            let frames = read_frames(&input, frame_delay)?;
+            let Some((_, first)) = frames.first() else {
+                return Err(CliError::with_message(
+                    CliErrorKind::InvalidParams,
+                    format!("no frames found in: {input:?}")));
+            };
+
+            let width = first.width();
+            let height = first.height();
+            let frame_count = frames.len() as u32;
+
+            // all frames are stacked into one tall image, so that they are
+            // processed the same way and share a palette
+            let mut delays = Vec::with_capacity(frames.len());
+            let mut data: Option<ColorList> = None;
+            for (index, (delay, img)) in frames.into_iter().enumerate() {
+                if img.width() != width || img.height() != height {
+                    return Err(CliError::with_message(
+                        CliErrorKind::InvalidParams,
+                        format!("frame {index} is {} x {}, but the first frame is {width} x {height}",
+                            img.width(), img.height())));
+                }
+
+                delays.push(delay);
+                let mut frame = image_to_color_list(img);
+                match &mut data {
+                    Some(data) => data.append(&mut frame)?,
+                    None => data = Some(frame),
+                }
+            }
+            let data = data.unwrap();
+
+            let color_model = ColorModel::from(color_model);
+            let data = if color_model != ColorModel::Rgb {
+                // drop alpha, neither CMYK nor YCbCr support it
+                let data = match data {
+                    ChannelVariant::U8(ColorVariant::Rgba(data)) => ChannelVariant::U8(ColorVariant::Rgb(
+                        data.into_iter().map(|Rgba([r, g, b, _])| Rgb([r, g, b])).collect())),
+                    ChannelVariant::U16(ColorVariant::Rgba(data)) => ChannelVariant::U16(ColorVariant::Rgb(
+                        data.into_iter().map(|Rgba([r, g, b, _])| Rgb([r, g, b])).collect())),
+                    ChannelVariant::F32(ColorVariant::Rgba(data)) => ChannelVariant::F32(ColorVariant::Rgb(
+                        data.into_iter().map(|Rgba([r, g, b, _])| Rgb([r, g, b])).collect())),
+                    ChannelVariant::U8(ColorVariant::L(data)) => ChannelVariant::U8(ColorVariant::Rgb(
+                        data.into_iter().map(|l| Rgb([l, l, l])).collect())),
+                    ChannelVariant::U16(ColorVariant::L(data)) => ChannelVariant::U16(ColorVariant::Rgb(
+                        data.into_iter().map(|l| Rgb([l, l, l])).collect())),
+                    ChannelVariant::U8(ColorVariant::La(data)) => ChannelVariant::U8(ColorVariant::Rgb(
+                        data.into_iter().map(|La([l, _])| Rgb([l, l, l])).collect())),
+                    ChannelVariant::U16(ColorVariant::La(data)) => ChannelVariant::U16(ColorVariant::Rgb(
+                        data.into_iter().map(|La([l, _])| Rgb([l, l, l])).collect())),
+                    data => data,
+                };
+                from_rgb_variant(&data, color_model)?.into_owned()
+            } else {
+                data
+            };
+
+            let mut data = data;
+            if premultiplied {
+                data.premultiply_alpha();
+            }
+
+            let planes = if let Some(max_error) = max_error {
+                let near_lossless_planes = near_lossless_planes(&data, interleaved, max_error)?;
+                dither_planes(&mut data, width, near_lossless_planes, depth_dither.into(), Some(max_error))?;
+                // the values are exact at this depth now, so this doesn't change them any further
+                data = narrow(&data, near_lossless_planes);
+                Some(near_lossless_planes)
+            } else {
+                if let Some(planes) = planes && colors.is_none() && depth_dither != DitheringArg::None {
+                    dither_planes(&mut data, width, planes, depth_dither.into(), None)?;
                 }
+                planes
+            };
+
+            let (indx, body) = if let Some(colors) = colors {
+                let (indx, body) = quantize(&data, width, colors, quantizer.into(), dither)?;
+                (Some(indx), body)
+            } else {
+                (None, Body::with_data(data))
+            };
+
+            let channel_value_type = body.data().channel_value_type();
+
+            let mut head = if let Some(indx) = &indx {
+                let palette_type = indx.colors().channel_value_type();
+                let color_count = indx.colors().len();
+                let index_planes = if interleaved {
+                    planes_for_color_count(color_count)
+                } else {
+                    channel_value_type.planes()
+                };
+
+                xzib::Head::new(
+                    palette_type.number_type(),
+                    interleaved,
+                    indx.colors().color_type(),
+                    planes.unwrap_or(index_planes),
+                    palette_type.planes(),
+                    width,
+                    height)?
+            } else {
+                xzib::Head::new(
+                    channel_value_type.number_type(),
+                    interleaved,
+                    body.data().color_type(),
+                    planes.unwrap_or(channel_value_type.planes()),
+                    0,
+                    width,
+                    height)?
             };
 
-            let channel_value_type = data.channel_value_type();
-            let color_type = data.color_type();
+            head.set_color_model(color_model)?;
+            head.set_chroma_subsampling(chroma_subsampling.into())?;
+            head.set_premultiplied(premultiplied)?;
 
-            let mut xzib = XZIB::new(xzib::Head::new(
-                channel_value_type.number_type(),
-                interleaved,
-                color_type,
-                planes.unwrap_or(channel_value_type.planes()),
-                0, // TODO: index support
-                width,
-                height)?);
+            let (head, indx, body) = if reduce {
+                let reduction = reduce::reduce(body.into_data(), &head.with_size(width, height * frame_count))?;
+                (reduction.head().with_size(width, height), reduction.indx().cloned(), reduction.body().clone())
+            } else {
+                (head, indx, body)
+            };
+
+            let mut xzib = if frame_count > 1 {
+                let data = body.into_data();
+                let frames = delays.iter().enumerate().map(|(index, &delay)| {
+                    AnimationFrame::new(delay, crop(&data, width, 0, index as u32 * height, width, height))
+                }).collect();
+
+                let mut xzib = XZIB::from_animation(head, Animation::new(loop_count, width, height, frames))?;
+                *xzib.indx_mut() = indx;
 
-            *xzib.body_mut() = Some(Body::with_data(data));
+                xzib
+            } else {
+                let mut xzib = XZIB::new(head);
+
+                *xzib.indx_mut() = indx;
+                *xzib.body_mut() = Some(body);
+
+                xzib
+            };
+
+            if let Some(palette_order) = palette_order {
+                xzib.reorder_palette(palette_order.into())?;
+            }
+
+            // generate_levels() uses the transfer function
+            if transfer_function.is_some() || primaries.is_some() {
+                *xzib.colr_mut() = Some(Colr::new(
+                    transfer_function.map_or(TransferFunction::Srgb, Into::into),
+                    primaries.map_or(Chromaticities::SRGB, Into::into)));
+            }
+
+            if thumbnail_size > 0 && (width > thumbnail_size || height > thumbnail_size) {
+                xzib.generate_thumbnail(thumbnail_size)?;
+            }
+
+            if let Some(filter) = levels {
+                xzib.generate_levels(filter.into())?;
+            }
+
+            if let Some(profile) = read_icc_profile(&input)? {
+                match Iccp::new(profile) {
+                    Ok(iccp) => *xzib.iccp_mut() = Some(iccp),
+                    Err(err) => eprintln!("ignored invalid ICC profile: {err}"),
+                }
+            }
+
+            if let Some(exif) = read_exif(&input)? {
+                match Exif::new(exif) {
+                    Ok(exif) => *xzib.exif_mut() = Some(exif),
+                    Err(err) => eprintln!("ignored invalid EXIF data: {err}"),
+                }
+            }
+
+            if let Some(xmp) = xmp {
+                let packet = std::fs::read_to_string(xmp)?;
+                // only store packets that can be read back
+                Xmet::from_xmp(&packet)?;
+                *xzib.xpkt_mut() = Some(Xpkt::new(packet));
+            }
 
             print_info(&xzib);
 
@@ -184,223 +687,327 @@ pub fn main() -> Result<(), CliError> {
                 &mut BufWriter::new(File::create(output)?),
                 compression)?;
         }
-        Command::Decode { input, output } => {
-            let xzib = XZIB::read(&mut BufReader::new(File::open(input)?))?;
+        Command::Decode { palette_fallback, min_size, xmp, input, output } => {
+            let palette_index_mode = match palette_fallback {
+                Some(fallback) => PaletteIndexMode::Lenient(fallback),
+                None => PaletteIndexMode::Strict,
+            };
+            let xzib = XZIB::read_with_palette_index_mode(&mut BufReader::new(File::open(input)?), palette_index_mode)?;
+            let format = image::ImageFormat::from_path(&output).unwrap_or(image::ImageFormat::Png);
+            let icc_profile = xzib.iccp().map(|iccp| iccp.profile().to_vec());
+
+            if let Some(xmp) = xmp {
+                let packet = match (xzib.xpkt(), xzib.xmet()) {
+                    (Some(xpkt), _) => xpkt.packet().to_owned(),
+                    (None, Some(xmet)) => xmet.to_xmp()?,
+                    (None, None) => return Err(CliError::with_message(
+                        CliErrorKind::ReadError,
+                        "file has no XMP metadata")),
+                };
+                std::fs::write(xmp, packet)?;
+            }
+
+            if let Some(min_size) = min_size {
+                let Some((width, height, body)) = xzib.level_for_size(min_size, min_size) else {
+                    return Err(CliError::with_message(
+                        CliErrorKind::ReadError,
+                        "file has no BODY chunk"));
+                };
+
+                let Some(img) = color_list_to_image(xzib.body_rgb_data(body)?, width, height) else {
+                    return Err(CliError::with_message(
+                        CliErrorKind::ReadError,
+                        "cannot convert image format"));
+                };
+
+                return write_image(&img, &output, format, icc_profile.as_deref());
+            }
+
+            if !xzib.frames().is_empty() {
+                if format == image::ImageFormat::Png {
+                    return write_apng(&xzib, &output);
+                }
+                eprintln!("only PNG supports animations, writing the first frame only");
+            }
+
             let width = xzib.head().width();
             let height = xzib.head().height();
+            let premultiplied = xzib.head().is_premultiplied();
 
-            let Some(input_img) = xzib.into_image_data() else {
+            let Some(mut input_img) = xzib.into_rgb_image_data()? else {
                 return Err(CliError::with_message(
                     CliErrorKind::ReadError,
                     "file has no BODY chunk"));
             };
 
-            let img: Option<image::DynamicImage> = match input_img {
-                ChannelVariant::U8(data) => {
-                    match data {
-                        ColorVariant::L(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data
-                            ).map(|img| image::DynamicImage::ImageLuma8(img))
-                        }
-                        ColorVariant::La(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|La(color)| color).collect()
-                            ).map(|img| image::DynamicImage::ImageLumaA8(img))
-                        }
-                        ColorVariant::Rgb(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgb(color)| color).collect()
-                            ).map(|img| image::DynamicImage::ImageRgb8(img))
-                        }
-                        ColorVariant::Rgba(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgba(color)| color).collect()
-                            ).map(|img| image::DynamicImage::ImageRgba8(img))
+            // PNG and most other formats use straight alpha
+            if premultiplied {
+                input_img.unpremultiply_alpha();
+            }
+
+            let img = color_list_to_image(input_img, width, height);
+
+            let Some(img) = img else {
+                return Err(CliError::with_message(
+                    CliErrorKind::ReadError,
+                    "cannot convert image format"));
+            };
+
+            write_image(&img, &output, format, icc_profile.as_deref())?;
+        }
+        Command::Thumbnail { input, output } => {
+            let Some(thmb) = XZIB::thumbnail(&mut BufReader::new(File::open(input)?))? else {
+                return Err(CliError::with_message(
+                    CliErrorKind::ReadError,
+                    "file has no THMB chunk"));
+            };
+
+            let format = image::ImageFormat::from_path(&output).unwrap_or(image::ImageFormat::Png);
+            let Some(img) = color_list_to_image(thmb.rgb_data()?, thmb.width(), thmb.height()) else {
+                return Err(CliError::with_message(
+                    CliErrorKind::ReadError,
+                    "cannot convert image format"));
+            };
+
+            img.write_to(
+                &mut BufWriter::new(File::create(&output)?),
+                format
+            )?;
+        }
+        Command::Info { files } => {
+            for file in &files {
+                println!("FILE: {:?}", file);
+
+                match File::open(file) {
+                    Ok(fp) => {
+                        match XZIB::read(&mut BufReader::new(fp)) {
+                            Ok(xzib) => {
+                                print_info(&xzib);
+                            }
+                            Err(err) => {
+                                eprintln!("Error reading file: {err}");
+                            }
                         }
                     }
-                }
-                ChannelVariant::U16(data) => {
-                    match data {
-                        ColorVariant::L(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data
-                            ).map(|img| image::DynamicImage::ImageLuma16(img))
-                        }
-                        ColorVariant::La(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|La(color)| color).collect()
-                            ).map(|img| image::DynamicImage::ImageLumaA16(img))
-                        }
-                        ColorVariant::Rgb(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgb(color)| color).collect()
-                            ).map(|img| image::DynamicImage::ImageRgb16(img))
-                        }
-                        ColorVariant::Rgba(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgba(color)| color).collect()
-                            ).map(|img| image::DynamicImage::ImageRgba16(img))
-                        }
+                    Err(err) => {
+                        eprintln!("Error opening file: {err}");
                     }
                 }
-                ChannelVariant::U32(data) => {
-                    match data {
-                        ColorVariant::L(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|l| { let l = l.as_f32(); [l, l, l] }).collect()
-                            ).map(|img| image::DynamicImage::ImageRgb32F(img))
-                        }
-                        ColorVariant::La(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|La([l, a])| { let l = l.as_f32(); [l, l, l, a.as_f32()] }).collect()
-                            ).map(|img| image::DynamicImage::ImageRgba32F(img))
-                        }
-                        ColorVariant::Rgb(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgb(color)| color.map(ChannelValue::as_f32)).collect()
-                            ).map(|img| image::DynamicImage::ImageRgb32F(img))
-                        }
-                        ColorVariant::Rgba(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgba(color)| color.map(ChannelValue::as_f32)).collect()
-                            ).map(|img| image::DynamicImage::ImageRgba32F(img))
-                        }
+            }
+        }
+        Command::Meta { command: MetaCommand::Export { input } } => {
+            let xzib = XZIB::read(&mut BufReader::new(File::open(input)?))?;
+            let export = MetadataExport {
+                head: Some(*xzib.head()),
+                meta: Some(xzib.meta().cloned()),
+                xmet: Some(xzib.xmet().cloned()),
+                foot: xzib.foot().cloned(),
+            };
+
+            let mut stdout = std::io::stdout().lock();
+            serde_json::to_writer_pretty(&mut stdout, &export)?;
+            writeln!(stdout)?;
+        }
+        Command::Meta { command: MetaCommand::Import { compression, strict, json, file } } => {
+            let export: MetadataExport = serde_json::from_reader(BufReader::new(File::open(json)?))?;
+            let mut raw = RawXZIB::read(&mut BufReader::new(File::open(&file)?))?;
+
+            match export.meta {
+                Some(Some(meta)) => {
+                    if strict {
+                        meta.check_licenses()?;
                     }
+                    raw.set(&meta, compression)?;
                 }
-                ChannelVariant::U64(data) => {
-                    match data {
-                        ColorVariant::L(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|l| { let l = l.as_f32(); [l, l, l] }).collect()
-                            ).map(|img| image::DynamicImage::ImageRgb32F(img))
-                        }
-                        ColorVariant::La(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|La([l, a])| { let l = l.as_f32(); [l, l, l, a.as_f32()] }).collect()
-                            ).map(|img| image::DynamicImage::ImageRgba32F(img))
-                        }
-                        ColorVariant::Rgb(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgb(color)| color.map(ChannelValue::as_f32)).collect()
-                            ).map(|img| image::DynamicImage::ImageRgb32F(img))
-                        }
-                        ColorVariant::Rgba(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgba(color)| color.map(ChannelValue::as_f32)).collect()
-                            ).map(|img| image::DynamicImage::ImageRgba32F(img))
-                        }
-                    }
+                Some(None) => raw.remove(Meta::FOURCC),
+                None => {}
+            }
+
+            match export.xmet {
+                Some(Some(xmet)) => raw.set(&xmet, compression)?,
+                Some(None) => raw.remove(Xmet::FOURCC),
+                None => {}
+            }
+
+            write_raw(&raw, &file)?;
+        }
+        Command::Meta { command: MetaCommand::Set(args) } => {
+            let MetaSetArgs { compression, strict, chunks, title, author, created_at, license, link, comment, keyword, software, copyright, modified_at, source, files } = *args;
+            let list = |values: &[String]| values.iter().filter(|value| !value.is_empty()).cloned().collect::<Vec<String>>();
+
+            for file in &files {
+                let mut raw = RawXZIB::read(&mut BufReader::new(File::open(file)?))?;
+                let meta = raw.meta()?;
+                let xmet = raw.xmet()?;
+
+                let mut metadata = Metadata::from_chunks(meta.as_ref(), xmet.as_ref());
+                if let Some(title) = &title {
+                    metadata.set_title(title.clone());
                 }
-                ChannelVariant::U128(data) => {
-                    match data {
-                        ColorVariant::L(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|l| { let l = l.as_f32(); [l, l, l] }).collect()
-                            ).map(|img| image::DynamicImage::ImageRgb32F(img))
-                        }
-                        ColorVariant::La(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|La([l, a])| { let l = l.as_f32(); [l, l, l, a.as_f32()] }).collect()
-                            ).map(|img| image::DynamicImage::ImageRgba32F(img))
-                        }
-                        ColorVariant::Rgb(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgb(color)| color.map(ChannelValue::as_f32)).collect()
-                            ).map(|img| image::DynamicImage::ImageRgb32F(img))
-                        }
-                        ColorVariant::Rgba(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgba(color)| color.map(ChannelValue::as_f32)).collect()
-                            ).map(|img| image::DynamicImage::ImageRgba32F(img))
-                        }
-                    }
+                if !author.is_empty() {
+                    *metadata.author_mut() = list(&author);
                 }
-                ChannelVariant::F32(data) => {
-                    match data {
-                        ColorVariant::L(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|l| [l, l, l]).collect()
-                            ).map(|img| image::DynamicImage::ImageRgb32F(img))
-                        }
-                        ColorVariant::La(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|La([l, a])| [l, l, l, a]).collect()
-                            ).map(|img| image::DynamicImage::ImageRgba32F(img))
-                        }
-                        ColorVariant::Rgb(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgb(color)| color).collect()
-                            ).map(|img| image::DynamicImage::ImageRgb32F(img))
-                        }
-                        ColorVariant::Rgba(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgba(color)| color).collect()
-                            ).map(|img| image::DynamicImage::ImageRgba32F(img))
-                        }
-                    }
+                if let Some(created_at) = &created_at {
+                    metadata.set_created_at(created_at);
                 }
-                ChannelVariant::F64(data) => {
-                    match data {
-                        ColorVariant::L(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|l| [l as f32, l as f32, l as f32]).collect()
-                            ).map(|img| image::DynamicImage::ImageRgb32F(img))
-                        }
-                        ColorVariant::La(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|La([l, a])| [l as f32, l as f32, l as f32, a as f32]).collect()
-                            ).map(|img| image::DynamicImage::ImageRgba32F(img))
-                        }
-                        ColorVariant::Rgb(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgb(color)| color.map(|v| v as f32)).collect()
-                            ).map(|img| image::DynamicImage::ImageRgb32F(img))
-                        }
-                        ColorVariant::Rgba(data) => {
-                            image::ImageBuffer::from_raw(
-                                width, height, data.into_iter().flat_map(|Rgba(color)| color.map(|v| v as f32)).collect()
-                            ).map(|img| image::DynamicImage::ImageRgba32F(img))
-                        }
+                if !license.is_empty() {
+                    *metadata.license_mut() = list(&license);
+                }
+                if !link.is_empty() {
+                    *metadata.links_mut() = list(&link);
+                }
+                if let Some(comment) = &comment {
+                    metadata.set_comment(comment.clone());
+                }
+                if !keyword.is_empty() {
+                    *metadata.keywords_mut() = list(&keyword);
+                }
+                if let Some(software) = &software {
+                    metadata.set_software(software.clone());
+                }
+                if let Some(copyright) = &copyright {
+                    metadata.set_copyright(copyright.clone());
+                }
+                if let Some(modified_at) = &modified_at {
+                    metadata.set_modified_at(modified_at);
+                }
+                if let Some(source) = &source {
+                    metadata.set_source(source.clone());
+                }
+
+                let chunks = match chunks {
+                    Some(chunks) => chunks.into(),
+                    None => match (meta.is_some(), xmet.is_some()) {
+                        (true, true) => MetadataChunks::Both,
+                        // the copyright can only be stored in META
+                        (false, true) if !metadata.copyright().is_empty() => MetadataChunks::Both,
+                        (false, true) => MetadataChunks::Xmet,
+                        _ => MetadataChunks::Meta,
+                    },
+                };
+                metadata.check_chunks(chunks)?;
+
+                if chunks.has_xmet() {
+                    let mut xmet = xmet.unwrap_or_default();
+                    metadata.update_xmet(&mut xmet);
+                    raw.set(&xmet, compression)?;
+                } else {
+                    raw.remove(Xmet::FOURCC);
+                }
+
+                if chunks.has_meta() {
+                    let mut meta = meta.unwrap_or_default();
+                    metadata.update_meta(&mut meta);
+                    if strict {
+                        meta.check_licenses()?;
                     }
+                    raw.set(&meta, compression)?;
+                } else {
+                    raw.remove(Meta::FOURCC);
                 }
-            };
 
-            let Some(img) = img else {
+                write_raw(&raw, file)?;
+            }
+        }
+        Command::Validate { strict, files } => {
+            let mut failed = 0;
+            for file in &files {
+                println!("FILE: {:?}", file);
+
+                let (errors, warnings) = match File::open(file) {
+                    Ok(fp) => match XZIB::read(&mut BufReader::new(fp)) {
+                        Ok(xzib) => validate(&xzib),
+                        Err(err) => (vec![format!("reading file: {err}")], Vec::new()),
+                    },
+                    Err(err) => (vec![format!("opening file: {err}")], Vec::new()),
+                };
+
+                for error in &errors {
+                    println!("  error: {error}");
+                }
+
+                for warning in &warnings {
+                    println!("  warning: {warning}");
+                }
+
+                if errors.is_empty() && warnings.is_empty() {
+                    println!("  ok");
+                }
+
+                if !errors.is_empty() || (strict && !warnings.is_empty()) {
+                    failed += 1;
+                }
+            }
+
+            if failed > 0 {
                 return Err(CliError::with_message(
-                    CliErrorKind::ReadError,
-                    "cannot convert image format"));
-            };
+                    CliErrorKind::InvalidParams,
+                    format!("{failed} of {} files failed validation", files.len())));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes `raw` to a temporary file next to `path` and then replaces `path`
+/// with it, so that the file isn't broken if writing fails.
+fn write_raw(raw: &RawXZIB, path: &Path) -> Result<(), CliError> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let result = (|| {
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        raw.write(&mut writer)?;
+        writer.flush()?;
+        Ok::<(), CliError>(())
+    })();
+
+    if let Err(err) = result {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(err);
+    }
 
-            img.write_to(
-                &mut BufWriter::new(File::create(&output)?),
-                image::ImageFormat::from_path(&output).unwrap_or(image::ImageFormat::Png)
-            )?;
-        }
-        Command::Info { files } => {
-            for file in &files {
-                println!("FILE: {:?}", file);
+    std::fs::rename(&temp_path, path)?;
 
-                match File::open(file) {
-                    Ok(fp) => {
-                        match XZIB::read(&mut BufReader::new(fp)) {
-                            Ok(xzib) => {
-                                print_info(&xzib);
-                            }
-                            Err(err) => {
-                                eprintln!("Error reading file: {err}");
-                            }
+    Ok(())
+}
+
+/// Errors and warnings about the metadata of a file.
+fn validate(xzib: &XZIB) -> (Vec<String>, Vec<String>) {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    if let Some(meta) = xzib.meta() {
+        for license in meta.license() {
+            let license = license.as_ref();
+            match LicenseExpression::parse(license) {
+                Ok(expression) => {
+                    for id in expression.ids() {
+                        if spdx::is_deprecated(id) {
+                            warnings.push(format!("META license {license:?} uses the deprecated SPDX ID {id:?}"));
                         }
                     }
-                    Err(err) => {
-                        eprintln!("Error opening file: {err}");
-                    }
                 }
+                Err(err) => errors.push(format!("META license: {err}")),
             }
         }
+
+        for (key, value) in meta.unknown() {
+            warnings.push(format!("META entry with unknown key {key}: {value:?}"));
+        }
     }
 
-    Ok(())
+    if let Some(Err(err)) = xzib.xmet().map(Xmet::validate) {
+        errors.push(format!("XMET: {err}"));
+    }
+
+    if let Some(Err(err)) = xzib.xpkt().map(|xpkt| Xmet::from_xmp(xpkt.packet())) {
+        errors.push(format!("XPKT: {err}"));
+    }
+
+    (errors, warnings)
 }
 
 fn print_info(xzib: &XZIB) {
@@ -412,8 +1019,28 @@ fn print_info(xzib: &XZIB) {
     println!("bit planes:       {:3}", header.planes());
     println!("index bit planes: {:3}", header.index_planes());
     println!("interleaved:      {}", header.is_interleaved());
+    println!("premultiplied:    {}", header.is_premultiplied());
+    match header.color_model() {
+        Ok(color_model) => println!("color model:      {color_model}"),
+        Err(err) => println!("color model:      {err}"),
+    }
+    if header.is_chroma_subsampled() {
+        match header.chroma_subsampling() {
+            Ok(chroma_subsampling) => println!("chroma subsampling: {chroma_subsampling}"),
+            Err(err) => println!("chroma subsampling: {err}"),
+        }
+    }
 
-    let mut chunks = Vec::with_capacity(5);
+    let mut chunks = Vec::with_capacity(14);
+    if xzib.thmb().is_some() {
+        chunks.push("THMB");
+    }
+    if xzib.iccp().is_some() {
+        chunks.push("ICCP");
+    }
+    if xzib.colr().is_some() {
+        chunks.push("COLR");
+    }
     if xzib.indx().is_some() {
         chunks.push("INDX");
     }
@@ -423,81 +1050,758 @@ fn print_info(xzib: &XZIB) {
     if xzib.xmet().is_some() {
         chunks.push("XMET");
     }
+    if xzib.xpkt().is_some() {
+        chunks.push("XPKT");
+    }
+    if xzib.exif().is_some() {
+        chunks.push("EXIF");
+    }
+    if xzib.anim().is_some() {
+        chunks.push("ANIM");
+    }
     if xzib.body().is_some() {
         chunks.push("BODY");
     }
+    if !xzib.frames().is_empty() {
+        chunks.push("FRAM");
+    }
+    if !xzib.layers().is_empty() {
+        chunks.push("LAYR");
+    }
+    if !xzib.levels().is_empty() {
+        chunks.push("LEVL");
+    }
     if xzib.foot().is_some() {
         chunks.push("FOOT");
     }
 
     println!("chunks: {}", chunks.join(", "));
 
-    if let Some(meta) = xzib.meta() {
+    if let Some(thmb) = xzib.thmb() {
+        println!();
+        println!("THMB:");
+        println!("  dimensions: {} x {}", thmb.width(), thmb.height());
+        println!("  channels:   {}", thmb.head().channels());
+        println!("  bit planes: {}", thmb.head().planes());
+    }
+
+    if let Some(iccp) = xzib.iccp() {
+        let (major, minor) = iccp.version();
+        println!();
+        println!("ICCP:");
+        println!("  size:         {} bytes", iccp.profile().len());
+        println!("  version:      {major}.{minor}");
+        println!("  device class: {}", String::from_utf8_lossy(&iccp.device_class()));
+        println!("  color space:  {}", String::from_utf8_lossy(&iccp.color_space()));
+    }
+
+    if let Some(colr) = xzib.colr() {
+        println!();
+        println!("COLR:");
+        println!("  transfer function: {}", colr.transfer_function());
+        if colr.transfer_function() == TransferFunction::Gamma {
+            println!("  gamma:             {}", colr.gamma());
+        }
+        println!("  primaries:         {}", colr.chromaticities());
+    }
+
+    if let Some(anim) = xzib.anim() {
+        println!();
+        println!("ANIM:");
+        println!("  frames:        {}", xzib.frames().len() + 1);
+        println!("  loop count:    {}", anim.loop_count());
+        println!("  default delay: {} ms", anim.default_delay());
+    }
+
+    if !xzib.layers().is_empty() {
+        println!();
+        println!("LAYR:");
+        for layr in xzib.layers() {
+            println!("- name: {:?}", layr.name());
+            println!("  offset: {}, {}", layr.x(), layr.y());
+            println!("  size: {} x {}", layr.width(), layr.height());
+            println!("  opacity: {}", layr.opacity());
+            println!("  blend mode: {}", layr.blend_mode());
+            println!("  visible: {}", layr.is_visible());
+        }
+    }
+
+    if !xzib.levels().is_empty() {
+        println!();
+        println!("LEVL:");
+        for (index, levl) in xzib.levels().iter().enumerate() {
+            println!("  {:2}: {} x {} ({})", index + 1, levl.width(), levl.height(), levl.filter());
+        }
+    }
+
+    if xzib.meta().is_some() || xzib.xmet().is_some() {
+        let metadata = xzib.metadata();
+
         println!();
-        println!("META:");
+        println!("METADATA:");
+
+        if !metadata.title().is_empty() {
+            println!("  title: {:?}", metadata.title());
+        }
 
-        if !meta.title().is_empty() {
-            println!("  title: {:?}", meta.title());
+        for (lang, title) in metadata.localized_titles() {
+            println!("  title ({lang}): {title:?}");
         }
 
-        if !meta.author().is_empty() {
+        if !metadata.author().is_empty() {
             println!("  author:");
-            for author in meta.author() {
-                println!("  - {:?}", author.as_ref());
+            for author in metadata.author() {
+                println!("  - {author:?}");
             }
         }
 
-        if !meta.created_at().is_null() {
-            println!("  created_at: {}", meta.created_at());
+        if !metadata.created_at().is_null() {
+            println!("  created_at: {}", metadata.created_at());
         }
 
-        if !meta.license().is_empty() {
+        if !metadata.license().is_empty() {
             println!("  license:");
-            for license in meta.license() {
-                println!("  - {:?}", license.as_ref());
+            for license in metadata.license() {
+                println!("  - {license:?}");
             }
         }
 
-        if !meta.links().is_empty() {
+        if !metadata.links().is_empty() {
             println!("  links:");
-            for links in meta.links() {
-                println!("  - {:?}", links.as_ref());
+            for links in metadata.links() {
+                println!("  - {links:?}");
             }
         }
 
-        if !meta.comment().is_empty() {
+        if !metadata.comment().is_empty() {
             println!("  comment: |");
-            for line in meta.comment().split("\n") {
+            for line in metadata.comment().split("\n") {
+                println!("    {line}");
+            }
+        }
+
+        for (lang, comment) in metadata.localized_comments() {
+            println!("  comment ({lang}): |");
+            for line in comment.split("\n") {
                 println!("    {line}");
             }
         }
+
+        if !metadata.keywords().is_empty() {
+            println!("  keywords:");
+            for keyword in metadata.keywords() {
+                println!("  - {keyword:?}");
+            }
+        }
+
+        if !metadata.software().is_empty() {
+            println!("  software: {:?}", metadata.software());
+        }
+
+        if !metadata.copyright().is_empty() {
+            println!("  copyright: {:?}", metadata.copyright());
+        }
+
+        if !metadata.modified_at().is_null() {
+            println!("  modified_at: {}", metadata.modified_at());
+        }
+
+        if !metadata.source().is_empty() {
+            println!("  source: {:?}", metadata.source());
+        }
+
+        for (key, value) in xzib.meta().map(|meta| meta.unknown()).unwrap_or_default() {
+            println!("  {key}: {value:?}");
+        }
     }
 
     if let Some(xmet) = xzib.xmet() {
         println!();
         println!("XMET:");
+        if let Err(err) = xmet.validate() {
+            println!("  warning: {err}");
+        }
+        print_xmet(xmet);
+    }
 
-        for (key, values) in xmet.data() {
-            match values.len() {
-                0 => {}
-                1 => {
-                    let value = &values[0];
-                    if value.contains("\n") {
-                        println!("  {key}: |");
-                        for line in value.split("\n") {
-                            println!("    {line}");
-                        }
-                    } else {
-                        println!("  {key}: {value:?}");
+    if let Some(xpkt) = xzib.xpkt() {
+        println!();
+        println!("XPKT:");
+        println!("  size: {} bytes", xpkt.packet().len());
+        match Xmet::from_xmp(xpkt.packet()) {
+            Ok(xmet) => print_xmet(&xmet),
+            Err(err) => println!("  {err}"),
+        }
+    }
+
+    if let Some(exif) = xzib.exif() {
+        println!();
+        println!("EXIF:");
+
+        if let Some(make) = exif.make() {
+            println!("  make: {make:?}");
+        }
+        if let Some(model) = exif.model() {
+            println!("  model: {model:?}");
+        }
+        if let Some(lens_model) = exif.lens_model() {
+            println!("  lens model: {lens_model:?}");
+        }
+        if let Some(software) = exif.software() {
+            println!("  software: {software:?}");
+        }
+        if let Some(artist) = exif.artist() {
+            println!("  artist: {artist:?}");
+        }
+        if let Some(copyright) = exif.copyright() {
+            println!("  copyright: {copyright:?}");
+        }
+        if let Some(date_time) = exif.date_time_original().or(exif.date_time()) {
+            println!("  date time: {date_time}");
+        }
+        if let Some(orientation) = exif.orientation() {
+            println!("  orientation: {orientation}");
+        }
+        if let Some(exposure_time) = exif.exposure_time() {
+            if exposure_time > 0.0 && exposure_time < 1.0 {
+                println!("  exposure time: 1/{} s", (1.0 / exposure_time).round());
+            } else {
+                println!("  exposure time: {exposure_time} s");
+            }
+        }
+        if let Some(f_number) = exif.f_number() {
+            println!("  f-number: f/{f_number}");
+        }
+        if let Some(iso) = exif.iso() {
+            println!("  ISO: {iso}");
+        }
+        if let Some(focal_length) = exif.focal_length() {
+            println!("  focal length: {focal_length} mm");
+        }
+        if let (Some(latitude), Some(longitude)) = (exif.gps_latitude(), exif.gps_longitude()) {
+            println!("  GPS position: {latitude:.6}, {longitude:.6}");
+        }
+        if let Some(altitude) = exif.gps_altitude() {
+            println!("  GPS altitude: {altitude} m");
+        }
+    }
+}
+
+fn print_xmet(xmet: &Xmet) {
+    for (key, values) in xmet.data() {
+        match values.len() {
+            0 => {}
+            1 => {
+                let value = &values[0];
+                if value.contains("\n") {
+                    println!("  {key}: |");
+                    for line in value.split("\n") {
+                        println!("    {line}");
                     }
+                } else {
+                    println!("  {key}: {value:?}");
                 }
-                _ => {
-                    println!("  {key}:");
-                    for value in values {
-                        println!("  - {value:?}");
-                    }
+            }
+            _ => {
+                println!("  {key}:");
+                for value in values {
+                    println!("  - {value:?}");
+                }
+            }
+        }
+    }
+}
+
+fn image_to_color_list(img: image::DynamicImage) -> ColorList {
+    match img.color() {
+        image::ColorType::L8 => {
+            ChannelVariant::U8(ColorVariant::L(img.into_luma8().into_vec()))
+        },
+        image::ColorType::L16 => {
+            ChannelVariant::U16(ColorVariant::L(img.into_luma16().into_vec()))
+        },
+        image::ColorType::Rgb8 => {
+            ChannelVariant::U8(ColorVariant::Rgb(
+                img.into_rgb8().pixels().into_iter().map(
+                    |&image::Rgb(color)| Rgb(color)
+                ).collect()))
+        },
+        image::ColorType::Rgb16 => {
+            ChannelVariant::U16(ColorVariant::Rgb(
+                img.into_rgb16().pixels().into_iter().map(
+                    |&image::Rgb(color)| Rgb(color)
+                ).collect()))
+        },
+        image::ColorType::Rgba8 => {
+            ChannelVariant::U8(ColorVariant::Rgba(
+                img.into_rgba8().pixels().into_iter().map(
+                    |&image::Rgba(color)| Rgba(color)
+                ).collect()))
+        },
+        image::ColorType::Rgba16 => {
+            ChannelVariant::U16(ColorVariant::Rgba(
+                img.into_rgba16().pixels().into_iter().map(
+                    |&image::Rgba(color)| Rgba(color)
+                ).collect()))
+        },
+        image::ColorType::Rgb32F => {
+            ChannelVariant::F32(ColorVariant::Rgba(
+                img.into_rgba32f().pixels().into_iter().map(
+                    |&image::Rgba(color)| Rgba(color)
+                ).collect()))
+        },
+        image::ColorType::Rgba32F => {
+            ChannelVariant::F32(ColorVariant::Rgba(
+                img.into_rgba32f().pixels().into_iter().map(
+                    |&image::Rgba(color)| Rgba(color)
+                ).collect()))
+        },
+        image::ColorType::La8 => {
+            ChannelVariant::U8(ColorVariant::La(
+                img.into_luma_alpha8().pixels().into_iter().map(
+                    |&image::LumaA(color)| La(color)
+                ).collect()))
+        },
+        image::ColorType::La16 => {
+            ChannelVariant::U16(ColorVariant::La(
+                img.into_luma_alpha16().pixels().into_iter().map(
+                    |&image::LumaA(color)| La(color)
+                ).collect()))
+        },
+        _ => {
+            let bits_per_channel = img.color().bits_per_pixel() / img.color().channel_count() as u16;
+            if img.color().has_alpha() {
+                if bits_per_channel <= 8 {
+                    ChannelVariant::U8(ColorVariant::Rgba(
+                        img.into_rgba8().pixels().into_iter().map(
+                            |&image::Rgba(color)| Rgba(color)
+                        ).collect()))
+                } else if bits_per_channel <= 16 {
+                    ChannelVariant::U16(ColorVariant::Rgba(
+                        img.into_rgba16().pixels().into_iter().map(
+                            |&image::Rgba(color)| Rgba(color)
+                        ).collect()))
+                } else {
+                    ChannelVariant::F32(ColorVariant::Rgba(
+                        img.into_rgba32f().pixels().into_iter().map(
+                            |&image::Rgba(color)| Rgba(color)
+                        ).collect()))
+                }
+            } else {
+                if bits_per_channel <= 8 {
+                    ChannelVariant::U8(ColorVariant::Rgb(
+                        img.into_rgb8().pixels().into_iter().map(
+                            |&image::Rgb(color)| Rgb(color)
+                        ).collect()))
+                } else if bits_per_channel <= 16 {
+                    ChannelVariant::U16(ColorVariant::Rgb(
+                        img.into_rgb16().pixels().into_iter().map(
+                            |&image::Rgb(color)| Rgb(color)
+                        ).collect()))
+                } else {
+                    ChannelVariant::F32(ColorVariant::Rgb(
+                        img.into_rgb32f().pixels().into_iter().map(
+                            |&image::Rgb(color)| Rgb(color)
+                        ).collect()))
+                }
+            }
+        }
+    }
+}
+
+/// Converts decoded colors to an image, `None` if the size doesn't match.
+fn color_list_to_image(data: ColorList, width: u32, height: u32) -> Option<image::DynamicImage> {
+    match data {
+        ChannelVariant::U8(data) => {
+            match data {
+                ColorVariant::L(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data
+                    ).map(|img| image::DynamicImage::ImageLuma8(img))
+                }
+                ColorVariant::La(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|La(color)| color).collect()
+                    ).map(|img| image::DynamicImage::ImageLumaA8(img))
+                }
+                ColorVariant::Rgb(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgb(color)| color).collect()
+                    ).map(|img| image::DynamicImage::ImageRgb8(img))
+                }
+                ColorVariant::Rgba(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgba(color)| color).collect()
+                    ).map(|img| image::DynamicImage::ImageRgba8(img))
+                }
+            }
+        }
+        ChannelVariant::U16(data) => {
+            match data {
+                ColorVariant::L(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data
+                    ).map(|img| image::DynamicImage::ImageLuma16(img))
+                }
+                ColorVariant::La(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|La(color)| color).collect()
+                    ).map(|img| image::DynamicImage::ImageLumaA16(img))
+                }
+                ColorVariant::Rgb(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgb(color)| color).collect()
+                    ).map(|img| image::DynamicImage::ImageRgb16(img))
+                }
+                ColorVariant::Rgba(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgba(color)| color).collect()
+                    ).map(|img| image::DynamicImage::ImageRgba16(img))
+                }
+            }
+        }
+        ChannelVariant::U32(data) => {
+            match data {
+                ColorVariant::L(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|l| { let l = l.as_f32(); [l, l, l] }).collect()
+                    ).map(|img| image::DynamicImage::ImageRgb32F(img))
+                }
+                ColorVariant::La(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|La([l, a])| { let l = l.as_f32(); [l, l, l, a.as_f32()] }).collect()
+                    ).map(|img| image::DynamicImage::ImageRgba32F(img))
+                }
+                ColorVariant::Rgb(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgb(color)| color.map(ChannelValue::as_f32)).collect()
+                    ).map(|img| image::DynamicImage::ImageRgb32F(img))
+                }
+                ColorVariant::Rgba(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgba(color)| color.map(ChannelValue::as_f32)).collect()
+                    ).map(|img| image::DynamicImage::ImageRgba32F(img))
+                }
+            }
+        }
+        ChannelVariant::U64(data) => {
+            match data {
+                ColorVariant::L(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|l| { let l = l.as_f32(); [l, l, l] }).collect()
+                    ).map(|img| image::DynamicImage::ImageRgb32F(img))
+                }
+                ColorVariant::La(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|La([l, a])| { let l = l.as_f32(); [l, l, l, a.as_f32()] }).collect()
+                    ).map(|img| image::DynamicImage::ImageRgba32F(img))
+                }
+                ColorVariant::Rgb(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgb(color)| color.map(ChannelValue::as_f32)).collect()
+                    ).map(|img| image::DynamicImage::ImageRgb32F(img))
+                }
+                ColorVariant::Rgba(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgba(color)| color.map(ChannelValue::as_f32)).collect()
+                    ).map(|img| image::DynamicImage::ImageRgba32F(img))
+                }
+            }
+        }
+        ChannelVariant::U128(data) => {
+            match data {
+                ColorVariant::L(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|l| { let l = l.as_f32(); [l, l, l] }).collect()
+                    ).map(|img| image::DynamicImage::ImageRgb32F(img))
+                }
+                ColorVariant::La(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|La([l, a])| { let l = l.as_f32(); [l, l, l, a.as_f32()] }).collect()
+                    ).map(|img| image::DynamicImage::ImageRgba32F(img))
+                }
+                ColorVariant::Rgb(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgb(color)| color.map(ChannelValue::as_f32)).collect()
+                    ).map(|img| image::DynamicImage::ImageRgb32F(img))
                 }
+                ColorVariant::Rgba(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgba(color)| color.map(ChannelValue::as_f32)).collect()
+                    ).map(|img| image::DynamicImage::ImageRgba32F(img))
+                }
+            }
+        }
+        ChannelVariant::F32(data) => {
+            match data {
+                ColorVariant::L(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|l| [l, l, l]).collect()
+                    ).map(|img| image::DynamicImage::ImageRgb32F(img))
+                }
+                ColorVariant::La(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|La([l, a])| [l, l, l, a]).collect()
+                    ).map(|img| image::DynamicImage::ImageRgba32F(img))
+                }
+                ColorVariant::Rgb(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgb(color)| color).collect()
+                    ).map(|img| image::DynamicImage::ImageRgb32F(img))
+                }
+                ColorVariant::Rgba(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgba(color)| color).collect()
+                    ).map(|img| image::DynamicImage::ImageRgba32F(img))
+                }
+            }
+        }
+        ChannelVariant::F64(data) => {
+            match data {
+                ColorVariant::L(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|l| [l as f32, l as f32, l as f32]).collect()
+                    ).map(|img| image::DynamicImage::ImageRgb32F(img))
+                }
+                ColorVariant::La(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|La([l, a])| [l as f32, l as f32, l as f32, a as f32]).collect()
+                    ).map(|img| image::DynamicImage::ImageRgba32F(img))
+                }
+                ColorVariant::Rgb(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgb(color)| color.map(|v| v as f32)).collect()
+                    ).map(|img| image::DynamicImage::ImageRgb32F(img))
+                }
+                ColorVariant::Rgba(data) => {
+                    image::ImageBuffer::from_raw(
+                        width, height, data.into_iter().flat_map(|Rgba(color)| color.map(|v| v as f32)).collect()
+                    ).map(|img| image::DynamicImage::ImageRgba32F(img))
+                }
+            }
+        }
+    }
+}
+
+/// Single images, animated GIFs and PNGs, or all images in a directory, which
+/// are sorted by file name. Returns the frames with their delay in milliseconds.
+fn read_frames(input: &Path, frame_delay: u32) -> Result<Vec<(u32, image::DynamicImage)>, CliError> {
+    if input.is_dir() {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(input)? {
+            let path = entry?.path();
+            if path.is_file() && image::ImageFormat::from_path(&path).is_ok() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut frames = Vec::with_capacity(paths.len());
+        for path in paths {
+            frames.push((frame_delay, image::ImageReader::open(path)?.decode()?));
+        }
+
+        // frames have to share one color type to be stacked into one image
+        let color = frames.first().map(|(_, img)| img.color());
+        if frames.iter().any(|(_, img)| Some(img.color()) != color) {
+            let is_16bit = frames.iter().any(|(_, img)| img.color().bytes_per_pixel() > img.color().channel_count());
+            for (_, img) in &mut frames {
+                *img = if is_16bit {
+                    image::DynamicImage::ImageRgba16(img.to_rgba16())
+                } else {
+                    image::DynamicImage::ImageRgba8(img.to_rgba8())
+                };
+            }
+        }
+
+        return Ok(frames);
+    }
+
+    let animation = match image::ImageFormat::from_path(input) {
+        Ok(image::ImageFormat::Gif) => {
+            let decoder = image::codecs::gif::GifDecoder::new(BufReader::new(File::open(input)?))?;
+            Some(decoder.into_frames().collect_frames()?)
+        }
+        Ok(image::ImageFormat::Png) => {
+            let decoder = image::codecs::png::PngDecoder::new(BufReader::new(File::open(input)?))?;
+            if decoder.is_apng()? {
+                Some(decoder.apng()?.into_frames().collect_frames()?)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+
+    if let Some(frames) = animation {
+        return Ok(frames.into_iter().map(|frame| {
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            (numer / denom.max(1), image::DynamicImage::ImageRgba8(frame.into_buffer()))
+        }).collect());
+    }
+
+    Ok(vec![(0, image::ImageReader::open(input)?.decode()?)])
+}
+
+/// The ICC profile of an image file, if its format supports one.
+fn read_icc_profile(input: &Path) -> Result<Option<Vec<u8>>, CliError> {
+    if input.is_dir() {
+        return Ok(None);
+    }
+
+    let mut decoder = image::ImageReader::open(input)?.with_guessed_format()?.into_decoder()?;
+
+    Ok(decoder.icc_profile()?)
+}
+
+/// The raw EXIF data of an image file, if its format supports it.
+fn read_exif(input: &Path) -> Result<Option<Vec<u8>>, CliError> {
+    if input.is_dir() {
+        return Ok(None);
+    }
+
+    let mut decoder = image::ImageReader::open(input)?.with_guessed_format()?.into_decoder()?;
+
+    Ok(decoder.exif_metadata()?)
+}
+
+/// Writes the image, embedding the ICC profile for PNG, JPEG, and WebP.
+fn write_image(img: &image::DynamicImage, output: &Path, format: image::ImageFormat, icc_profile: Option<&[u8]>) -> Result<(), CliError> {
+    let Some(icc_profile) = icc_profile else {
+        img.write_to(&mut BufWriter::new(File::create(output)?), format)?;
+        return Ok(());
+    };
+
+    match format {
+        image::ImageFormat::Png => {
+            let mut buf = Vec::new();
+            img.write_to(&mut Cursor::new(&mut buf), format)?;
+            std::fs::write(output, insert_png_icc_profile(&buf, icc_profile)?)?;
+        }
+        image::ImageFormat::Jpeg => {
+            let mut buf = Vec::new();
+            img.write_to(&mut Cursor::new(&mut buf), format)?;
+            std::fs::write(output, insert_jpeg_icc_profile(&buf, icc_profile))?;
+        }
+        image::ImageFormat::WebP => {
+            let mut encoder = image::codecs::webp::WebPEncoder::new_lossless(BufWriter::new(File::create(output)?));
+            if let Err(err) = encoder.set_icc_profile(icc_profile.to_vec()) {
+                return Err(CliError::with_all(
+                    CliErrorKind::Image,
+                    "cannot embed ICC profile",
+                    Box::new(err)));
             }
+            img.write_with_encoder(encoder)?;
+        }
+        _ => {
+            eprintln!("ICC profiles aren't supported for {format:?}, writing the image without it");
+            img.write_to(&mut BufWriter::new(File::create(output)?), format)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Inserts an iCCP chunk after the IHDR chunk of a PNG file.
+fn insert_png_icc_profile(png: &[u8], icc_profile: &[u8]) -> Result<Vec<u8>, CliError> {
+    // signature and IHDR chunk
+    const IHDR_END: usize = 8 + 8 + 13 + 4;
+
+    let mut data = b"ICC profile\0\0".to_vec();
+    let mut encoder = ZlibEncoder::new(&mut data, Compression::default());
+    encoder.write_all(icc_profile)?;
+    encoder.finish()?;
+
+    let mut crc = Crc::new();
+    crc.update(b"iCCP");
+    crc.update(&data);
+
+    let mut result = Vec::with_capacity(png.len() + data.len() + 12);
+    result.extend_from_slice(&png[..IHDR_END]);
+    result.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    result.extend_from_slice(b"iCCP");
+    result.extend_from_slice(&data);
+    result.extend_from_slice(&crc.sum().to_be_bytes());
+    result.extend_from_slice(&png[IHDR_END..]);
+
+    Ok(result)
+}
+
+/// Inserts APP2 segments with the ICC profile after the SOI marker and the
+/// JFIF segment of a JPEG file.
+fn insert_jpeg_icc_profile(jpeg: &[u8], icc_profile: &[u8]) -> Vec<u8> {
+    const MAX_CHUNK_SIZE: usize = 65533 - 14;
+
+    let mut offset = 2;
+    if jpeg.len() >= 6 && jpeg[2..4] == [0xFF, 0xE0] {
+        offset += 2 + u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize;
+    }
+
+    let chunks: Vec<&[u8]> = icc_profile.chunks(MAX_CHUNK_SIZE).collect();
+    let mut result = Vec::with_capacity(jpeg.len() + icc_profile.len() + chunks.len() * 18);
+    result.extend_from_slice(&jpeg[..offset]);
+    for (index, chunk) in chunks.iter().enumerate() {
+        result.extend_from_slice(&[0xFF, 0xE2]);
+        result.extend_from_slice(&((chunk.len() + 16) as u16).to_be_bytes());
+        result.extend_from_slice(b"ICC_PROFILE\0");
+        result.extend_from_slice(&[(index + 1) as u8, chunks.len() as u8]);
+        result.extend_from_slice(chunk);
+    }
+    result.extend_from_slice(&jpeg[offset..]);
+
+    result
+}
+
+/// RGBA colors as 8 bit values for `u8` colors, otherwise as big endian 16 bit
+/// values.
+fn rgba_bytes(data: &ColorList) -> Vec<u8> {
+    match data {
+        ChannelVariant::U8(ColorVariant::Rgba(data)) => {
+            data.iter().flat_map(|&Rgba(color)| color).collect()
+        }
+        ChannelVariant::U16(ColorVariant::Rgba(data)) => {
+            data.iter().flat_map(|&Rgba(color)| color).flat_map(u16::to_be_bytes).collect()
         }
+        data => {
+            to_pixels(data).into_iter()
+                .flat_map(|pixel| pixel.map(|value| (value.clamp(0.0, 1.0) * 65535.0).round() as u16))
+                .flat_map(u16::to_be_bytes).collect()
+        }
+    }
+}
+
+fn write_apng(xzib: &XZIB, output: &Path) -> Result<(), CliError> {
+    let Some(animation) = xzib.animation()? else {
+        return Err(CliError::with_message(
+            CliErrorKind::ReadError,
+            "file has no BODY chunk"));
+    };
+
+    let bit_depth = match animation.frames().first().map(|frame| frame.data().channel_value_type()) {
+        Some(ChannelValueType::U8) => png::BitDepth::Eight,
+        _ => png::BitDepth::Sixteen,
+    };
+
+    let mut info = png::Info::with_size(animation.width(), animation.height());
+    info.icc_profile = xzib.iccp().map(|iccp| Cow::Borrowed(iccp.profile()));
+
+    let mut encoder = png::Encoder::with_info(BufWriter::new(File::create(output)?), info)?;
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(bit_depth);
+    encoder.set_animated(animation.frames().len() as u32, animation.loop_count())?;
+
+    let mut writer = encoder.write_header()?;
+    for frame in animation.frames() {
+        // the delay is a fraction of u16 values
+        let (numer, denom) = if frame.delay() <= u16::MAX as u32 {
+            (frame.delay() as u16, 1000)
+        } else {
+            ((frame.delay() / 10).min(u16::MAX as u32) as u16, 100)
+        };
+        writer.set_frame_delay(numer, denom)?;
+        writer.write_image_data(&rgba_bytes(frame.data()))?;
     }
+    writer.finish()?;
+
+    Ok(())
 }
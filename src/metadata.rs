@@ -0,0 +1,476 @@
+use crate::{chunks::{Meta, Xmet}, error::InvalidParams, lang::best_match_text, Date, XZIB};
+
+const XMP_NS: &str = "http://ns.adobe.com/xap/1.0/";
+
+/// Which chunks [`XZIB::set_metadata`] writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetadataChunks {
+    /// Only META, readable by every reader.
+    Meta,
+    /// Only XMET, which maps to XMP.
+    Xmet,
+    /// META and XMET with the same values.
+    #[default]
+    Both,
+}
+
+impl MetadataChunks {
+    #[inline]
+    pub fn has_meta(self) -> bool {
+        self != Self::Xmet
+    }
+
+    #[inline]
+    pub fn has_xmet(self) -> bool {
+        self != Self::Meta
+    }
+}
+
+impl std::fmt::Display for MetadataChunks {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Meta => "META".fmt(f),
+            Self::Xmet => "XMET".fmt(f),
+            Self::Both => "META and XMET".fmt(f),
+        }
+    }
+}
+
+/// The fields of the META chunk, merged with their equivalents of the XMET
+/// chunk. Values of the META chunk take precedence, fields that are empty
+/// in META are taken from XMET:
+///
+/// | META          | XMET              |
+/// | :------------ | :---------------- |
+/// | `title`       | `title`           |
+/// | `author`      | `creator`         |
+/// | `created_at`  | `date`            |
+/// | `license`     | `rights`          |
+/// | `links`       | `relation`        |
+/// | `comment`     | `description`     |
+/// | `keywords`    | `subject`         |
+/// | `software`    | `xmp:CreatorTool` |
+/// | `modified_at` | `xmp:ModifyDate`  |
+/// | `source`      | `source`          |
+///
+/// Localized titles and comments map to `title@<tag>` and
+/// `description@<tag>`. `copyright` has no XMET equivalent and is only
+/// written to META.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Metadata {
+    title: String,
+    localized_titles: Vec<(String, String)>,
+    author: Vec<String>,
+    created_at: Date,
+    license: Vec<String>,
+    links: Vec<String>,
+    comment: String,
+    localized_comments: Vec<(String, String)>,
+    keywords: Vec<String>,
+    software: String,
+    copyright: String,
+    modified_at: Date,
+    source: String,
+}
+
+impl Metadata {
+    /// Merges the chunks, see [`Metadata`].
+    pub fn from_chunks(meta: Option<&Meta>, xmet: Option<&Xmet>) -> Self {
+        let mut metadata = match meta {
+            Some(meta) => Self {
+                title: meta.title().to_owned(),
+                localized_titles: meta.localized_titles().to_vec(),
+                author: to_strings(meta.author()),
+                created_at: *meta.created_at(),
+                license: to_strings(meta.license()),
+                links: to_strings(meta.links()),
+                comment: meta.comment().to_owned(),
+                localized_comments: meta.localized_comments().to_vec(),
+                keywords: to_strings(meta.keywords()),
+                software: meta.software().to_owned(),
+                copyright: meta.copyright().to_owned(),
+                modified_at: *meta.modified_at(),
+                source: meta.source().to_owned(),
+            },
+            None => Self::default(),
+        };
+
+        let Some(xmet) = xmet else {
+            return metadata;
+        };
+
+        let text = |value: Option<&str>| value.map(str::to_owned).unwrap_or_default();
+        let date = |value: Option<&String>| value
+            .and_then(|value| Date::parse(value).ok())
+            .unwrap_or_default();
+        let xmp = |local| xmet.get_by_namespace(XMP_NS, local).and_then(<[String]>::first);
+
+        merge_text(&mut metadata.title, text(xmet.title()));
+        merge_list(&mut metadata.author, xmet.creator());
+        merge_date(&mut metadata.created_at, date(xmet.date().first()));
+        // rights() is only the first value, but there can be several licenses
+        merge_list(&mut metadata.license, xmet.get_by_namespace(Xmet::DUBLIN_CORE, "rights").unwrap_or_default());
+        merge_list(&mut metadata.links, xmet.relation());
+        merge_text(&mut metadata.comment, text(xmet.description()));
+        merge_list(&mut metadata.keywords, xmet.subject());
+        merge_text(&mut metadata.software, text(xmp("CreatorTool").map(String::as_str)));
+        merge_date(&mut metadata.modified_at, date(xmp("ModifyDate")));
+        merge_text(&mut metadata.source, text(xmet.source()));
+
+        for (key, localized) in [("title", &mut metadata.localized_titles), ("description", &mut metadata.localized_comments)] {
+            for lang in xmet.languages(key) {
+                if localized.iter().any(|(other, _)| other.eq_ignore_ascii_case(lang)) {
+                    continue;
+                }
+
+                let value = xmet.get(&format!("{key}@{lang}")).and_then(<[String]>::first).cloned().unwrap_or_default();
+                localized.push((lang.to_owned(), value));
+            }
+        }
+
+        metadata
+    }
+
+    #[inline]
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Titles in other languages as BCP 47 language tag and title.
+    #[inline]
+    pub fn localized_titles(&self) -> &[(String, String)] {
+        &self.localized_titles
+    }
+
+    /// The title best matching `locale`, see [`crate::lang::best_match`].
+    #[inline]
+    pub fn title_for(&self, locale: &str) -> &str {
+        best_match_text(locale, &self.title, &self.localized_titles)
+    }
+
+    #[inline]
+    pub fn author(&self) -> &[String] {
+        &self.author
+    }
+
+    #[inline]
+    pub fn created_at(&self) -> &Date {
+        &self.created_at
+    }
+
+    /// SPDX license expressions.
+    #[inline]
+    pub fn license(&self) -> &[String] {
+        &self.license
+    }
+
+    #[inline]
+    pub fn links(&self) -> &[String] {
+        &self.links
+    }
+
+    #[inline]
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+
+    /// Comments in other languages as BCP 47 language tag and comment.
+    #[inline]
+    pub fn localized_comments(&self) -> &[(String, String)] {
+        &self.localized_comments
+    }
+
+    /// The comment best matching `locale`, see [`crate::lang::best_match`].
+    #[inline]
+    pub fn comment_for(&self, locale: &str) -> &str {
+        best_match_text(locale, &self.comment, &self.localized_comments)
+    }
+
+    #[inline]
+    pub fn keywords(&self) -> &[String] {
+        &self.keywords
+    }
+
+    #[inline]
+    pub fn software(&self) -> &str {
+        &self.software
+    }
+
+    #[inline]
+    pub fn copyright(&self) -> &str {
+        &self.copyright
+    }
+
+    #[inline]
+    pub fn modified_at(&self) -> &Date {
+        &self.modified_at
+    }
+
+    #[inline]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    #[inline]
+    pub fn set_title(&mut self, value: impl Into<String>) {
+        self.title = value.into();
+    }
+
+    #[inline]
+    pub fn set_created_at(&mut self, value: &Date) {
+        self.created_at = *value;
+    }
+
+    #[inline]
+    pub fn set_comment(&mut self, value: impl Into<String>) {
+        self.comment = value.into();
+    }
+
+    #[inline]
+    pub fn set_software(&mut self, value: impl Into<String>) {
+        self.software = value.into();
+    }
+
+    #[inline]
+    pub fn set_copyright(&mut self, value: impl Into<String>) {
+        self.copyright = value.into();
+    }
+
+    #[inline]
+    pub fn set_modified_at(&mut self, value: &Date) {
+        self.modified_at = *value;
+    }
+
+    #[inline]
+    pub fn set_source(&mut self, value: impl Into<String>) {
+        self.source = value.into();
+    }
+
+    #[inline]
+    pub fn localized_titles_mut(&mut self) -> &mut Vec<(String, String)> {
+        &mut self.localized_titles
+    }
+
+    #[inline]
+    pub fn author_mut(&mut self) -> &mut Vec<String> {
+        &mut self.author
+    }
+
+    #[inline]
+    pub fn license_mut(&mut self) -> &mut Vec<String> {
+        &mut self.license
+    }
+
+    #[inline]
+    pub fn links_mut(&mut self) -> &mut Vec<String> {
+        &mut self.links
+    }
+
+    #[inline]
+    pub fn localized_comments_mut(&mut self) -> &mut Vec<(String, String)> {
+        &mut self.localized_comments
+    }
+
+    #[inline]
+    pub fn keywords_mut(&mut self) -> &mut Vec<String> {
+        &mut self.keywords
+    }
+
+    /// Whether all fields are empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Writes the fields into `meta`, keeping its entries with unknown keys.
+    pub fn update_meta(&self, meta: &mut Meta) {
+        meta.set_title(self.title.clone());
+        *meta.localized_titles_mut() = self.localized_titles.clone();
+        *meta.author_mut() = self.author.clone();
+        meta.set_created_at(&self.created_at);
+        *meta.license_mut() = self.license.clone();
+        *meta.links_mut() = self.links.clone();
+        *meta.comment_mut() = self.comment.clone();
+        *meta.localized_comments_mut() = self.localized_comments.clone();
+        *meta.keywords_mut() = self.keywords.clone();
+        meta.set_software(self.software.clone());
+        meta.set_copyright(self.copyright.clone());
+        meta.set_modified_at(&self.modified_at);
+        meta.set_source(self.source.clone());
+    }
+
+    /// Fails if a field would be lost when only writing `chunks`, i.e. the
+    /// copyright without META.
+    pub fn check_chunks(&self, chunks: MetadataChunks) -> Result<(), InvalidParams> {
+        if !chunks.has_meta() && !self.copyright.is_empty() {
+            return Err(InvalidParams::with_message(
+                format!("copyright has no XMET equivalent: {:?}", self.copyright)));
+        }
+
+        Ok(())
+    }
+
+    /// Writes the fields into `xmet`, keeping its other entries. The
+    /// copyright is skipped, since it has no XMET equivalent.
+    pub fn update_xmet(&self, xmet: &mut Xmet) {
+        let text = |value: &str| if value.is_empty() { Vec::new() } else { vec![value.to_owned()] };
+        let date = |value: &Date| if value.is_null() { Vec::new() } else { vec![value.to_string()] };
+
+        // the namespace is declared as xmp instead of a generated prefix
+        if !self.software.is_empty() || !self.modified_at.is_null() {
+            let declared = xmet.namespaces().any(|(_, namespace)| namespace == XMP_NS);
+            if !declared && xmet.namespace("xmp").is_none() {
+                xmet.data_mut().insert("xmlns:xmp".to_owned(), vec![XMP_NS.to_owned()]);
+            }
+        }
+
+        xmet.set_by_namespace(Xmet::DUBLIN_CORE, "title", text(&self.title));
+        xmet.set_by_namespace(Xmet::DUBLIN_CORE, "creator", self.author.clone());
+        xmet.set_by_namespace(Xmet::DUBLIN_CORE, "date", date(&self.created_at));
+        xmet.set_by_namespace(Xmet::DUBLIN_CORE, "rights", self.license.clone());
+        xmet.set_by_namespace(Xmet::DUBLIN_CORE, "relation", self.links.clone());
+        xmet.set_by_namespace(Xmet::DUBLIN_CORE, "description", text(&self.comment));
+        xmet.set_by_namespace(Xmet::DUBLIN_CORE, "subject", self.keywords.clone());
+        xmet.set_by_namespace(XMP_NS, "CreatorTool", text(&self.software));
+        xmet.set_by_namespace(XMP_NS, "ModifyDate", date(&self.modified_at));
+        xmet.set_by_namespace(Xmet::DUBLIN_CORE, "source", text(&self.source));
+
+        for (key, localized) in [("title", &self.localized_titles), ("description", &self.localized_comments)] {
+            let languages: Vec<String> = xmet.languages(key).map(str::to_owned).collect();
+            for lang in languages {
+                xmet.set_localized(key, Some(&lang), Vec::<String>::new());
+            }
+
+            for (lang, value) in localized {
+                xmet.set_localized(key, Some(lang), [value]);
+            }
+        }
+    }
+}
+
+impl XZIB {
+    /// The merged META and XMET chunks.
+    #[inline]
+    pub fn metadata(&self) -> Metadata {
+        Metadata::from_chunks(self.meta(), self.xmet())
+    }
+
+    /// Writes `metadata` into the META chunk, the XMET chunk or both,
+    /// keeping entries that aren't part of [`Metadata`]. The chunk that isn't
+    /// chosen is removed. See [`Metadata::check_chunks`] for when this fails.
+    pub fn set_metadata(&mut self, metadata: &Metadata, chunks: MetadataChunks) -> Result<(), InvalidParams> {
+        metadata.check_chunks(chunks)?;
+
+        if chunks.has_xmet() {
+            let mut xmet = self.xmet().cloned().unwrap_or_default();
+            metadata.update_xmet(&mut xmet);
+            *self.xmet_mut() = Some(xmet);
+        } else {
+            *self.xmet_mut() = None;
+        }
+
+        if chunks.has_meta() {
+            let mut meta = self.meta().cloned().unwrap_or_default();
+            metadata.update_meta(&mut meta);
+            *self.meta_mut() = Some(meta);
+        } else {
+            *self.meta_mut() = None;
+        }
+
+        Ok(())
+    }
+}
+
+#[inline]
+fn to_strings(values: &[impl AsRef<str>]) -> Vec<String> {
+    values.iter().map(|value| value.as_ref().to_owned()).collect()
+}
+
+#[inline]
+fn merge_text(value: &mut String, other: String) {
+    if value.is_empty() {
+        *value = other;
+    }
+}
+
+#[inline]
+fn merge_list(values: &mut Vec<String>, other: &[String]) {
+    if values.is_empty() {
+        values.extend_from_slice(other);
+    }
+}
+
+#[inline]
+fn merge_date(value: &mut Date, other: Date) {
+    if value.is_null() {
+        *value = other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format::{ColorType, NumberType}, Head};
+
+    use super::*;
+
+    fn xzib() -> XZIB {
+        XZIB::new(Head::new(NumberType::Integer, false, ColorType::Rgb, 8, 0, 1, 1).unwrap())
+    }
+
+    #[test]
+    fn copyright_is_only_written_to_meta() {
+        let mut metadata = Metadata::default();
+        metadata.set_title("Title");
+        metadata.set_copyright("(c) me");
+
+        let mut xzib = xzib();
+        xzib.set_metadata(&metadata, MetadataChunks::Both).unwrap();
+
+        assert_eq!(xzib.meta().unwrap().copyright(), "(c) me");
+        assert_eq!(xzib.xmet().unwrap().get_by_namespace(Xmet::DUBLIN_CORE, "title"), Some(&["Title".to_owned()][..]));
+        assert_eq!(xzib.metadata(), metadata);
+
+        assert!(xzib.set_metadata(&metadata, MetadataChunks::Xmet).is_err());
+        assert!(xzib.meta().is_some());
+
+        metadata.set_copyright("");
+        xzib.set_metadata(&metadata, MetadataChunks::Xmet).unwrap();
+        assert!(xzib.meta().is_none());
+        assert_eq!(xzib.metadata(), metadata);
+    }
+
+    #[test]
+    fn xmet_with_declared_dublin_core_prefix() {
+        let entries = [
+            ("xmlns:dc", vec![Xmet::DUBLIN_CORE]),
+            ("xmlns:x", vec![XMP_NS]),
+            ("dc:title", vec!["Title"]),
+            ("dc:description", vec!["Comment"]),
+            ("dc:rights", vec!["MIT", "Apache-2.0"]),
+            ("dc:date", vec!["2024-05-06"]),
+            ("dc:source", vec!["Source"]),
+            ("dc:creator", vec!["Author"]),
+            ("x:CreatorTool", vec!["Software"]),
+        ];
+        let xmet = Xmet::new(entries.into_iter()
+            .map(|(key, values)| (key.to_owned(), values.into_iter().map(str::to_owned).collect()))
+            .collect());
+
+        let metadata = Metadata::from_chunks(None, Some(&xmet));
+        assert_eq!(metadata.title(), "Title");
+        assert_eq!(metadata.comment(), "Comment");
+        assert_eq!(metadata.license(), ["MIT", "Apache-2.0"]);
+        assert_eq!(metadata.created_at(), &Date::new(2024, 5, 6));
+        assert_eq!(metadata.source(), "Source");
+        assert_eq!(metadata.author(), ["Author"]);
+        assert_eq!(metadata.software(), "Software");
+
+        // Dublin Core is written without prefix, replacing the prefixed entries
+        let mut updated = xmet.clone();
+        metadata.update_xmet(&mut updated);
+        assert_eq!(updated.data().len(), xmet.data().len());
+        assert_eq!(updated.get("title"), Some(&["Title".to_owned()][..]));
+        assert_eq!(Metadata::from_chunks(None, Some(&updated)), metadata);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{chunks::{Body, Levl}, colorimetry::{from_linear, to_linear}, color::{from_rgb_variant, ChannelVariant, ColorList, ColorVariant}, error::InvalidParams, format::ColorModel, quantize::{from_pixels, nearest_indices, to_pixels, Pixel}, resize::{alpha_channel, resize_pixels, Filter}, XZIB};
+
+impl XZIB {
+    /// Replaces the LEVL chunks with levels of half the width and height of
+    /// the previous level each, down to 1 x 1. Colors are filtered in linear
+    /// light, decoded with the transfer function of the COLR chunk, or as sRGB
+    /// without one. Colors are premultiplied by alpha while filtering. Indexed images map the filtered colors to the nearest
+    /// colors of the palette.
+    pub fn generate_levels(&mut self, filter: Filter) -> Result<(), InvalidParams> {
+        let Some(body) = &self.body else {
+            return Err(InvalidParams::with_message("levels require a BODY chunk"));
+        };
+
+        let data = self.body_rgb_data(body)?;
+        let mut width  = self.head.width();
+        let mut height = self.head.height();
+
+        if data.len() as u64 != width as u64 * height as u64 {
+            return Err(InvalidParams::with_message(
+                format!("image of {width} x {height} has {} colors", data.len())));
+        }
+
+        let color_type = data.color_type();
+        let alpha = alpha_channel(color_type);
+        let color_channels = if color_type.channels() < 3 { 1 } else { 3 };
+        let colr = self.colr.unwrap_or_default();
+        let (transfer_function, gamma) = (colr.transfer_function(), colr.gamma());
+
+        let mut pixels = to_pixels(&data);
+        for pixel in &mut pixels {
+            let alpha = alpha.map_or(1.0, |alpha| pixel[alpha]);
+            for value in &mut pixel[..color_channels] {
+                *value = to_linear(*value, transfer_function, gamma) * alpha;
+            }
+        }
+
+        let mut levels = Vec::new();
+        while width > 1 || height > 1 {
+            let new_width  = (width  / 2).max(1);
+            let new_height = (height / 2).max(1);
+            pixels = resize_pixels(&pixels, width, height, new_width, new_height, filter);
+            width  = new_width;
+            height = new_height;
+
+            let level_pixels: Vec<Pixel> = pixels.iter().map(|pixel| {
+                let mut pixel = *pixel;
+                let alpha = match alpha {
+                    Some(alpha) => {
+                        pixel[alpha] = pixel[alpha].clamp(0.0, 1.0);
+                        pixel[alpha]
+                    }
+                    None => 1.0,
+                };
+                for value in &mut pixel[..color_channels] {
+                    let linear = if alpha > 0.0 { (*value / alpha).max(0.0) } else { 0.0 };
+                    *value = from_linear(linear, transfer_function, gamma);
+                }
+                pixel
+            }).collect();
+
+            levels.push(Levl::new(width, height, filter, Body::with_data(self.level_data(body, &data, &level_pixels)?)));
+        }
+
+        self.levels = levels;
+
+        Ok(())
+    }
+
+    /// Converts straight alpha RGB pixels like `rgb_data` back into the
+    /// format of `body`.
+    fn level_data(&self, body: &Body, rgb_data: &ColorList, pixels: &[Pixel]) -> Result<ColorList, InvalidParams> {
+        let mut data = from_pixels(rgb_data, pixels);
+
+        if self.head.is_premultiplied() {
+            data.premultiply_alpha();
+        }
+
+        let color_model = self.head.color_model()?;
+        if color_model != ColorModel::Rgb {
+            data = from_rgb_variant(&data, color_model)?.into_owned();
+        }
+
+        let Some(indx) = &self.indx else {
+            return Ok(data);
+        };
+
+        let indices = nearest_indices(&data, indx.colors())?;
+        let like = body.data();
+
+        let indices = match like {
+            ChannelVariant::U8  (_) => ChannelVariant::U8  (ColorVariant::L(indices.iter().map(|&index| index as u8  ).collect())),
+            ChannelVariant::U16 (_) => ChannelVariant::U16 (ColorVariant::L(indices.iter().map(|&index| index as u16 ).collect())),
+            ChannelVariant::U32 (_) => ChannelVariant::U32 (ColorVariant::L(indices.iter().map(|&index| index as u32 ).collect())),
+            ChannelVariant::U64 (_) => ChannelVariant::U64 (ColorVariant::L(indices.iter().map(|&index| index as u64 ).collect())),
+            ChannelVariant::U128(_) => ChannelVariant::U128(ColorVariant::L(indices.iter().map(|&index| index as u128).collect())),
+            ChannelVariant::F32 (_) | ChannelVariant::F64(_) => return Err(InvalidParams::with_message(
+                format!("palette indices must be integers, but were: {}", like.channel_value_type()))),
+        };
+
+        Ok(indices)
+    }
+
+    /// The smallest level that is at least `min_width` x `min_height`, as
+    /// width, height and pixels. The BODY chunk is used if no smaller level
+    /// is large enough. `None` if there is no BODY chunk.
+    pub fn level_for_size(&self, min_width: u32, min_height: u32) -> Option<(u32, u32, &Body)> {
+        let body = self.body.as_ref()?;
+
+        let level = self.levels.iter()
+            .filter(|levl| levl.width() >= min_width && levl.height() >= min_height)
+            .min_by_key(|levl| levl.width() as u64 * levl.height() as u64);
+
+        Some(match level {
+            Some(levl) => (levl.width(), levl.height(), levl.body()),
+            None => (self.head.width(), self.head.height(), body),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chunks::{colr::{Chromaticities, TransferFunction}, Colr}, colorimetry::linear_to_srgb, format::{ColorType, NumberType}, Head};
+
+    use super::*;
+
+    fn level_value(number_type: NumberType, data: ColorList, transfer_function: Option<TransferFunction>) -> f64 {
+        let planes = data.channel_value_type().planes();
+        let mut xzib = XZIB::new(Head::new(number_type, false, ColorType::L, planes, 0, 2, 1).unwrap());
+        *xzib.body_mut() = Some(Body::with_data(data));
+        *xzib.colr_mut() = transfer_function.map(|transfer_function| Colr::new(transfer_function, Chromaticities::SRGB));
+
+        xzib.generate_levels(Filter::Box).unwrap();
+
+        assert_eq!(xzib.levels().len(), 1);
+        match xzib.levels()[0].body().data() {
+            ChannelVariant::U8 (ColorVariant::L(values)) => values[0] as f64 / 255.0,
+            ChannelVariant::F32(ColorVariant::L(values)) => values[0] as f64,
+            data => panic!("unexpected data: {}", data.channel_value_type()),
+        }
+    }
+
+    #[test]
+    fn levels_are_filtered_in_linear_light() {
+        let srgb_half = linear_to_srgb(0.5);
+
+        let value = level_value(NumberType::Integer, ChannelVariant::U8(ColorVariant::L(vec![0, 255])), None);
+        assert!((value - srgb_half).abs() < 1.0 / 255.0, "{value}");
+
+        let value = level_value(NumberType::Float, ChannelVariant::F32(ColorVariant::L(vec![0.0, 1.0])), None);
+        assert!((value - srgb_half).abs() < 1e-6, "{value}");
+
+        let value = level_value(NumberType::Float, ChannelVariant::F32(ColorVariant::L(vec![0.0, 1.0])), Some(TransferFunction::Linear));
+        assert!((value - 0.5).abs() < 1e-6, "{value}");
+
+        let value = level_value(NumberType::Integer, ChannelVariant::U8(ColorVariant::L(vec![0, 255])), Some(TransferFunction::Linear));
+        assert!((value - 0.5).abs() < 1.0 / 255.0, "{value}");
+
+        let value = level_value(NumberType::Float, ChannelVariant::F32(ColorVariant::L(vec![0.0, 1.0])), Some(TransferFunction::Pq));
+        assert!((value - crate::colorimetry::linear_to_pq(0.5)).abs() < 1e-6, "{value}");
+    }
+}
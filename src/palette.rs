@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use crate::{chunks::{Body, Indx}, color::{ChannelValue, ChannelVariant, Color, ColorList, ColorVariant, ColorVecDataInner, IntChannelValue}, error::InvalidParams, quantize::{to_pixels, Pixel}};
+
+/// The order of the colors of a palette. Similar colors, or colors that are
+/// often next to each other, get similar indices. This makes the high index
+/// planes of interleaved images more uniform and thus compress better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaletteOrder {
+    /// Darkest to brightest.
+    #[default]
+    Luminance,
+    /// Start with the darkest color and always continue with the most similar
+    /// of the remaining colors.
+    NearestNeighbor,
+    /// Start with the most frequent color and always continue with the color
+    /// that is most often next to the previous color in the image.
+    CoOccurrence,
+}
+
+impl std::fmt::Display for PaletteOrder {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Luminance       => "luminance".fmt(f),
+            Self::NearestNeighbor => "nearest-neighbor".fmt(f),
+            Self::CoOccurrence    => "co-occurrence".fmt(f),
+        }
+    }
+}
+
+/// Reorders the colors of the palette and rewrites the indices accordingly.
+/// `width` is only needed for [`PaletteOrder::CoOccurrence`]. Returns the
+/// mapping from old to new indices, see [`remap_indices()`].
+pub fn reorder_palette(indx: &mut Indx, body: &mut Body, width: u32, order: PaletteOrder) -> Result<Vec<usize>, InvalidParams> {
+    let Some(indices) = indices(body.data()) else {
+        return Err(InvalidParams::with_message(
+            format!("palette indices have to be integer L colors, but were: {} {}",
+                body.data().color_type(), body.data().channel_value_type())));
+    };
+
+    let color_count = indx.colors().len();
+    if let Some(&index) = indices.iter().find(|&&index| index >= color_count) {
+        return Err(InvalidParams::with_message(
+            format!("palette index {index} out of range for {color_count} colors")));
+    }
+
+    let pixels = to_pixels(indx.colors());
+    let channels = indx.colors().color_type().channels() as usize;
+    let order = match order {
+        PaletteOrder::Luminance       => luminance_order(&pixels, channels),
+        PaletteOrder::NearestNeighbor => nearest_neighbor_order(&pixels, channels),
+        PaletteOrder::CoOccurrence    => {
+            if width == 0 {
+                return Err(InvalidParams::with_message("width must be non-zero for co-occurrence ordering"));
+            }
+            co_occurrence_order(&indices, color_count, width as usize)
+        }
+    };
+
+    // order maps new indices to old indices, mapping maps old indices to new ones
+    let mut mapping = vec![0; color_count];
+    for (new_index, &old_index) in order.iter().enumerate() {
+        mapping[old_index] = new_index;
+    }
+
+    *indx.colors_mut() = permute(indx.colors(), &order);
+    remap_indices(body.data_mut(), &mapping);
+
+    Ok(mapping)
+}
+
+#[inline]
+fn luminance(pixel: &Pixel, channels: usize) -> f64 {
+    if channels < 3 {
+        pixel[0]
+    } else {
+        0.299 * pixel[0] + 0.587 * pixel[1] + 0.114 * pixel[2]
+    }
+}
+
+fn luminance_order(pixels: &[Pixel], channels: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..pixels.len()).collect();
+    order.sort_by(|&lhs, &rhs| {
+        let lhs_pixel = &pixels[lhs];
+        let rhs_pixel = &pixels[rhs];
+        luminance(lhs_pixel, channels).total_cmp(&luminance(rhs_pixel, channels))
+            .then_with(|| lhs_pixel.iter().zip(rhs_pixel)
+                .map(|(lhs, rhs)| lhs.total_cmp(rhs))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal))
+    });
+    order
+}
+
+#[inline]
+fn distance(lhs: &Pixel, rhs: &Pixel) -> f64 {
+    lhs.iter().zip(rhs).map(|(lhs, rhs)| (lhs - rhs) * (lhs - rhs)).sum()
+}
+
+fn nearest_neighbor_order(pixels: &[Pixel], channels: usize) -> Vec<usize> {
+    let mut remaining = luminance_order(pixels, channels);
+    let mut order = Vec::with_capacity(pixels.len());
+
+    if remaining.is_empty() {
+        return order;
+    }
+
+    let mut current = remaining.remove(0);
+    order.push(current);
+
+    while !remaining.is_empty() {
+        let (position, _) = remaining.iter().enumerate()
+            .min_by(|&(_, &lhs), &(_, &rhs)| {
+                distance(&pixels[current], &pixels[lhs]).total_cmp(&distance(&pixels[current], &pixels[rhs]))
+            })
+            .unwrap();
+        current = remaining.remove(position);
+        order.push(current);
+    }
+
+    order
+}
+
+fn co_occurrence_order(indices: &[usize], color_count: usize, width: usize) -> Vec<usize> {
+    let mut counts = vec![0u64; color_count];
+    let mut pairs: HashMap<(usize, usize), u64> = HashMap::new();
+
+    for (offset, &index) in indices.iter().enumerate() {
+        counts[index] += 1;
+
+        let x = offset % width;
+        let mut neighbors = [None, None];
+        if x + 1 < width {
+            neighbors[0] = indices.get(offset + 1);
+        }
+        neighbors[1] = indices.get(offset + width);
+
+        for &neighbor in neighbors.into_iter().flatten() {
+            if neighbor != index {
+                *pairs.entry((index.min(neighbor), index.max(neighbor))).or_default() += 1;
+            }
+        }
+    }
+
+    let mut neighbors: Vec<Vec<(usize, u64)>> = vec![Vec::new(); color_count];
+    for (&(lhs, rhs), &count) in &pairs {
+        neighbors[lhs].push((rhs, count));
+        neighbors[rhs].push((lhs, count));
+    }
+
+    // most frequent neighbors first, ties are resolved by index so the result
+    // doesn't depend on the iteration order of the hash map
+    for neighbors in &mut neighbors {
+        neighbors.sort_by(|(lhs_index, lhs_count), (rhs_index, rhs_count)| {
+            rhs_count.cmp(lhs_count).then(lhs_index.cmp(rhs_index))
+        });
+    }
+
+    // fallback if the last color has no unvisited neighbors
+    let mut by_count: Vec<usize> = (0..color_count).collect();
+    by_count.sort_by(|&lhs, &rhs| counts[rhs].cmp(&counts[lhs]).then(lhs.cmp(&rhs)));
+    let mut by_count = by_count.into_iter();
+
+    let mut visited = vec![false; color_count];
+    let mut order = Vec::with_capacity(color_count);
+    let mut current = None;
+
+    while order.len() < color_count {
+        let next = current
+            .and_then(|current: usize| neighbors[current].iter()
+                .map(|&(neighbor, _)| neighbor)
+                .find(|&neighbor| !visited[neighbor]))
+            .or_else(|| by_count.find(|&index| !visited[index]));
+
+        let Some(next) = next else {
+            break;
+        };
+
+        visited[next] = true;
+        order.push(next);
+        current = Some(next);
+    }
+
+    order
+}
+
+#[inline]
+fn indices_inner<I: IntChannelValue>(indices: &[I]) -> Vec<usize> {
+    indices.iter().map(|index| index.as_u128() as usize).collect()
+}
+
+/// Only integer L colors are considered to be indices.
+fn indices(data: &ColorList) -> Option<Vec<usize>> {
+    match data {
+        ChannelVariant::U8  (ColorVariant::L(indices)) => Some(indices_inner(indices)),
+        ChannelVariant::U16 (ColorVariant::L(indices)) => Some(indices_inner(indices)),
+        ChannelVariant::U32 (ColorVariant::L(indices)) => Some(indices_inner(indices)),
+        ChannelVariant::U64 (ColorVariant::L(indices)) => Some(indices_inner(indices)),
+        ChannelVariant::U128(ColorVariant::L(indices)) => Some(indices_inner(indices)),
+        _ => None,
+    }
+}
+
+#[inline]
+fn remap_indices_inner<I: IntChannelValue>(indices: &mut [I], mapping: &[usize]) {
+    for index in indices {
+        *index = I::from_u128(mapping[index.as_u128() as usize] as u128);
+    }
+}
+
+/// Replaces every index with `mapping[index]`. Only integer L colors are
+/// considered to be indices and all of them have to be in range of `mapping`.
+pub fn remap_indices(data: &mut ColorList, mapping: &[usize]) {
+    match data {
+        ChannelVariant::U8  (ColorVariant::L(indices)) => remap_indices_inner(indices, mapping),
+        ChannelVariant::U16 (ColorVariant::L(indices)) => remap_indices_inner(indices, mapping),
+        ChannelVariant::U32 (ColorVariant::L(indices)) => remap_indices_inner(indices, mapping),
+        ChannelVariant::U64 (ColorVariant::L(indices)) => remap_indices_inner(indices, mapping),
+        ChannelVariant::U128(ColorVariant::L(indices)) => remap_indices_inner(indices, mapping),
+        _ => {}
+    }
+}
+
+#[inline]
+fn permute_colors<C: ChannelValue, Col: Color<C>>(colors: &[Col], order: &[usize]) -> Vec<Col> {
+    order.iter().map(|&index| colors[index].clone()).collect()
+}
+
+#[inline]
+fn permute_inner<C: ChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>, order: &[usize]) -> ColorVariant<C, ColorVecDataInner> {
+    match colors {
+        ColorVariant::L   (colors) => ColorVariant::L   (permute_colors(colors, order)),
+        ColorVariant::La  (colors) => ColorVariant::La  (permute_colors(colors, order)),
+        ColorVariant::Rgb (colors) => ColorVariant::Rgb (permute_colors(colors, order)),
+        ColorVariant::Rgba(colors) => ColorVariant::Rgba(permute_colors(colors, order)),
+    }
+}
+
+/// Colors in the given order, `order` contains the indices into `colors`.
+pub fn permute(colors: &ColorList, order: &[usize]) -> ColorList {
+    match colors {
+        ChannelVariant::U8  (colors) => ChannelVariant::U8  (permute_inner(colors, order)),
+        ChannelVariant::U16 (colors) => ChannelVariant::U16 (permute_inner(colors, order)),
+        ChannelVariant::U32 (colors) => ChannelVariant::U32 (permute_inner(colors, order)),
+        ChannelVariant::U64 (colors) => ChannelVariant::U64 (permute_inner(colors, order)),
+        ChannelVariant::U128(colors) => ChannelVariant::U128(permute_inner(colors, order)),
+        ChannelVariant::F32 (colors) => ChannelVariant::F32 (permute_inner(colors, order)),
+        ChannelVariant::F64 (colors) => ChannelVariant::F64 (permute_inner(colors, order)),
+    }
+}
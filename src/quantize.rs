@@ -0,0 +1,571 @@
+use std::collections::HashMap;
+
+use crate::{chunks::{Body, Indx}, color::{ChannelValue, ChannelVariant, Color, ColorList, ColorVariant, ColorVecDataInner}, error::InvalidParams, format::{ColorType, NumberType}};
+
+pub const MAX_COLORS: usize = 65536;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuantizeMethod {
+    #[default]
+    MedianCut,
+    Octree,
+    KMeans,
+}
+
+impl std::fmt::Display for QuantizeMethod {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MedianCut => "median-cut".fmt(f),
+            Self::Octree    => "octree".fmt(f),
+            Self::KMeans    => "k-means".fmt(f),
+        }
+    }
+}
+
+/// Colors are handled as up to 4 normalized channels, unused channels are 0.
+pub type Pixel = [f64; 4];
+
+/// Minimum number of planes needed to store indices into a palette of the given size.
+#[inline]
+pub fn planes_for_color_count(color_count: usize) -> u8 {
+    let mut planes = 1;
+    while planes < 128 && (1u128 << planes) < color_count as u128 {
+        planes += 1;
+    }
+    planes
+}
+
+/// Reduces the colors of an image to a palette of at most `colors` entries.
+///
+/// Returns the palette as an INDX chunk with the same channel value type and
+/// color type as the input data and the indices as an L BODY chunk. Indices
+/// are `u8` for up to 256 colors and `u16` otherwise. `width` is only needed
+/// for dithering.
+pub fn quantize(data: &ColorList, width: u32, colors: usize, method: QuantizeMethod, dither: bool) -> Result<(Indx, Body), InvalidParams> {
+    if colors == 0 || colors > MAX_COLORS {
+        return Err(InvalidParams::with_message(
+            format!("number of colors must be in the range 1 to {MAX_COLORS}, but was: {colors}")));
+    }
+
+    if dither && width == 0 {
+        return Err(InvalidParams::with_message("width must be non-zero for dithering"));
+    }
+
+    let channels = data.color_type().channels() as usize;
+    let pixels = to_pixels(data);
+    let histogram = histogram(&pixels);
+
+    let palette = match method {
+        QuantizeMethod::MedianCut => median_cut(&histogram, channels, colors),
+        QuantizeMethod::Octree    => octree(&histogram, channels, colors),
+        QuantizeMethod::KMeans    => k_means(&histogram, channels, colors),
+    };
+
+    let clamp = data.channel_value_type().number_type() == NumberType::Integer;
+    let indices = if dither {
+        map_dithered(&pixels, &palette, channels, width as usize, clamp)
+    } else {
+        map_nearest(&pixels, &palette, channels)
+    };
+
+    Ok((Indx::new(from_pixels(data, &palette)), Body::with_data(make_indices(&indices, palette.len()))))
+}
+
+#[inline]
+fn to_pixels_inner<C: ChannelValue, Col: Color<C>>(colors: &[Col]) -> Vec<Pixel> {
+    colors.iter().map(|color| {
+        let mut pixel = [0.0; 4];
+        for (value, &channel) in pixel.iter_mut().zip(color.channels()) {
+            *value = channel.as_f64();
+        }
+        pixel
+    }).collect()
+}
+
+#[inline]
+fn to_pixels_variant_inner<C: ChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>) -> Vec<Pixel> {
+    match colors {
+        ColorVariant::L   (colors) => to_pixels_inner(colors),
+        ColorVariant::La  (colors) => to_pixels_inner(colors),
+        ColorVariant::Rgb (colors) => to_pixels_inner(colors),
+        ColorVariant::Rgba(colors) => to_pixels_inner(colors),
+    }
+}
+
+pub fn to_pixels(data: &ColorList) -> Vec<Pixel> {
+    match data {
+        ChannelVariant::U8  (colors) => to_pixels_variant_inner(colors),
+        ChannelVariant::U16 (colors) => to_pixels_variant_inner(colors),
+        ChannelVariant::U32 (colors) => to_pixels_variant_inner(colors),
+        ChannelVariant::U64 (colors) => to_pixels_variant_inner(colors),
+        ChannelVariant::U128(colors) => to_pixels_variant_inner(colors),
+        ChannelVariant::F32 (colors) => to_pixels_variant_inner(colors),
+        ChannelVariant::F64 (colors) => to_pixels_variant_inner(colors),
+    }
+}
+
+#[inline]
+fn from_pixels_inner<C: ChannelValue, Col: Color<C>>(pixels: &[Pixel]) -> Vec<Col> {
+    pixels.iter().map(|pixel| {
+        let mut color = Col::default();
+        for (channel, &value) in color.channels_mut().iter_mut().zip(pixel) {
+            *channel = C::from_f64(value);
+        }
+        color
+    }).collect()
+}
+
+#[inline]
+fn from_pixels_variant_inner<C: ChannelValue>(color_type: ColorType, pixels: &[Pixel]) -> ColorVariant<C, ColorVecDataInner> {
+    match color_type {
+        ColorType::L    => ColorVariant::L   (from_pixels_inner(pixels)),
+        ColorType::La   => ColorVariant::La  (from_pixels_inner(pixels)),
+        ColorType::Rgb  => ColorVariant::Rgb (from_pixels_inner(pixels)),
+        ColorType::Rgba => ColorVariant::Rgba(from_pixels_inner(pixels)),
+    }
+}
+
+/// Converts pixels back to the channel value type and color type of `like`.
+pub fn from_pixels(like: &ColorList, pixels: &[Pixel]) -> ColorList {
+    let color_type = like.color_type();
+    match like {
+        ChannelVariant::U8  (_) => ChannelVariant::U8  (from_pixels_variant_inner(color_type, pixels)),
+        ChannelVariant::U16 (_) => ChannelVariant::U16 (from_pixels_variant_inner(color_type, pixels)),
+        ChannelVariant::U32 (_) => ChannelVariant::U32 (from_pixels_variant_inner(color_type, pixels)),
+        ChannelVariant::U64 (_) => ChannelVariant::U64 (from_pixels_variant_inner(color_type, pixels)),
+        ChannelVariant::U128(_) => ChannelVariant::U128(from_pixels_variant_inner(color_type, pixels)),
+        ChannelVariant::F32 (_) => ChannelVariant::F32 (from_pixels_variant_inner(color_type, pixels)),
+        ChannelVariant::F64 (_) => ChannelVariant::F64 (from_pixels_variant_inner(color_type, pixels)),
+    }
+}
+
+/// Index of the nearest color of `palette` for each color of `data`. Both
+/// have to be of the same color type.
+pub fn nearest_indices(data: &ColorList, palette: &ColorList) -> Result<Vec<usize>, InvalidParams> {
+    if data.color_type() != palette.color_type() {
+        return Err(InvalidParams::with_message(
+            format!("color type of the colors and the palette differ: {} != {}", data.color_type(), palette.color_type())));
+    }
+
+    if palette.is_empty() && !data.is_empty() {
+        return Err(InvalidParams::with_message("palette is empty"));
+    }
+
+    let channels = data.color_type().channels() as usize;
+
+    Ok(map_nearest(&to_pixels(data), &to_pixels(palette), channels))
+}
+
+fn make_indices(indices: &[usize], color_count: usize) -> ColorList {
+    if color_count <= 256 {
+        ChannelVariant::U8(ColorVariant::L(indices.iter().map(|&index| index as u8).collect()))
+    } else {
+        ChannelVariant::U16(ColorVariant::L(indices.iter().map(|&index| index as u16).collect()))
+    }
+}
+
+#[inline]
+fn pixel_key(pixel: &Pixel) -> [u64; 4] {
+    pixel.map(f64::to_bits)
+}
+
+/// Unique colors and how often they occur, sorted by color so that the result
+/// doesn't depend on the order of the pixels.
+fn histogram(pixels: &[Pixel]) -> Vec<(Pixel, u64)> {
+    let mut sorted = pixels.to_vec();
+    sorted.sort_unstable_by_key(pixel_key);
+
+    let mut histogram: Vec<(Pixel, u64)> = Vec::new();
+    for pixel in sorted {
+        match histogram.last_mut() {
+            Some((last, count)) if pixel_key(last) == pixel_key(&pixel) => *count += 1,
+            _ => histogram.push((pixel, 1)),
+        }
+    }
+
+    histogram
+}
+
+#[inline]
+fn distance(lhs: &Pixel, rhs: &Pixel, channels: usize) -> f64 {
+    let mut sum = 0.0;
+    for channel in 0..channels {
+        let diff = lhs[channel] - rhs[channel];
+        sum += diff * diff;
+    }
+    sum
+}
+
+#[inline]
+fn nearest(pixel: &Pixel, palette: &[Pixel], channels: usize) -> usize {
+    let mut best_index = 0;
+    let mut best_distance = f64::INFINITY;
+    for (index, color) in palette.iter().enumerate() {
+        let distance = distance(pixel, color, channels);
+        if distance < best_distance {
+            best_distance = distance;
+            best_index = index;
+        }
+    }
+    best_index
+}
+
+fn mean(entries: &[(Pixel, u64)]) -> Pixel {
+    let mut sum = [0.0; 4];
+    let mut count = 0.0;
+    for (pixel, weight) in entries {
+        let weight = *weight as f64;
+        for (sum, value) in sum.iter_mut().zip(pixel) {
+            *sum += value * weight;
+        }
+        count += weight;
+    }
+    if count > 0.0 {
+        for sum in &mut sum {
+            *sum /= count;
+        }
+    }
+    sum
+}
+
+fn median_cut(histogram: &[(Pixel, u64)], channels: usize, colors: usize) -> Vec<Pixel> {
+    if histogram.len() <= colors {
+        return histogram.iter().map(|(pixel, _)| *pixel).collect();
+    }
+
+    let mut entries = histogram.to_vec();
+    let mut boxes = Vec::with_capacity(colors);
+    boxes.push(0..entries.len());
+
+    while boxes.len() < colors {
+        // split the box with the widest channel range, weighted by its population
+        let mut best: Option<(usize, usize, f64)> = None;
+        for (box_index, range) in boxes.iter().enumerate() {
+            if range.len() < 2 {
+                continue;
+            }
+            let entries = &entries[range.clone()];
+            let weight: u64 = entries.iter().map(|(_, weight)| weight).sum();
+            for channel in 0..channels {
+                let mut min = f64::INFINITY;
+                let mut max = f64::NEG_INFINITY;
+                for (pixel, _) in entries {
+                    min = min.min(pixel[channel]);
+                    max = max.max(pixel[channel]);
+                }
+                let score = (max - min) * (weight as f64).sqrt();
+                if score > 0.0 && best.is_none_or(|(_, _, best_score)| score > best_score) {
+                    best = Some((box_index, channel, score));
+                }
+            }
+        }
+
+        let Some((box_index, channel, _)) = best else {
+            break;
+        };
+
+        let range = boxes[box_index].clone();
+        let slice = &mut entries[range.clone()];
+        slice.sort_by(|(lhs, _), (rhs, _)| lhs[channel].total_cmp(&rhs[channel]));
+
+        let total: u64 = slice.iter().map(|(_, weight)| weight).sum();
+        let mut sum = 0;
+        let mut split = 1;
+        for (index, (_, weight)) in slice.iter().enumerate() {
+            sum += weight;
+            if sum * 2 >= total {
+                split = index + 1;
+                break;
+            }
+        }
+        let split = split.clamp(1, slice.len() - 1);
+
+        boxes[box_index] = range.start..range.start + split;
+        boxes.push(range.start + split..range.end);
+    }
+
+    boxes.into_iter().map(|range| mean(&entries[range])).collect()
+}
+
+#[derive(Debug, Default)]
+struct OctreeNode {
+    children: [Option<usize>; 16],
+    sum: Pixel,
+    count: u64,
+    leaf: bool,
+}
+
+fn octree(histogram: &[(Pixel, u64)], channels: usize, colors: usize) -> Vec<Pixel> {
+    const DEPTH: usize = 8;
+
+    if histogram.len() <= colors {
+        return histogram.iter().map(|(pixel, _)| *pixel).collect();
+    }
+
+    let mut nodes = vec![OctreeNode::default()];
+    let mut levels: Vec<Vec<usize>> = vec![Vec::new(); DEPTH];
+    let mut leaf_count = 0;
+
+    for (pixel, weight) in histogram {
+        let quantized = pixel.map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8);
+        let mut node_index = 0;
+        for level in 0..DEPTH {
+            let shift = 7 - level;
+            let mut child = 0;
+            for (channel, value) in quantized.iter().enumerate().take(channels) {
+                child |= (((value >> shift) & 1) as usize) << channel;
+            }
+
+            node_index = match nodes[node_index].children[child] {
+                Some(child_index) => child_index,
+                None => {
+                    let child_index = nodes.len();
+                    nodes.push(OctreeNode::default());
+                    nodes[node_index].children[child] = Some(child_index);
+                    if level + 1 < DEPTH {
+                        levels[level + 1].push(child_index);
+                    } else {
+                        nodes[child_index].leaf = true;
+                        leaf_count += 1;
+                    }
+                    child_index
+                }
+            };
+        }
+
+        let node = &mut nodes[node_index];
+        for (sum, value) in node.sum.iter_mut().zip(pixel) {
+            *sum += value * *weight as f64;
+        }
+        node.count += weight;
+    }
+    levels[0].push(0);
+
+    // merge the least populated nodes of the deepest level first
+    let mut level = DEPTH - 1;
+    while leaf_count > colors {
+        while levels[level].is_empty() {
+            level -= 1;
+        }
+
+        let (position, _) = levels[level].iter().enumerate()
+            .min_by_key(|&(_, &node_index)| subtree_count(&nodes, node_index))
+            .unwrap();
+        let node_index = levels[level].remove(position);
+
+        let mut sum = [0.0; 4];
+        let mut count = 0;
+        let mut merged = 0;
+        for child in std::mem::take(&mut nodes[node_index].children).into_iter().flatten() {
+            let child = &nodes[child];
+            for (sum, value) in sum.iter_mut().zip(&child.sum) {
+                *sum += value;
+            }
+            count += child.count;
+            merged += 1;
+        }
+
+        let node = &mut nodes[node_index];
+        node.sum = sum;
+        node.count = count;
+        node.leaf = true;
+        leaf_count = leaf_count + 1 - merged;
+    }
+
+    let mut palette = Vec::with_capacity(leaf_count);
+    collect_leaves(&nodes, 0, &mut palette);
+    palette
+}
+
+fn subtree_count(nodes: &[OctreeNode], node_index: usize) -> u64 {
+    let node = &nodes[node_index];
+    if node.leaf {
+        return node.count;
+    }
+    node.children.iter().flatten().map(|&child| subtree_count(nodes, child)).sum()
+}
+
+fn collect_leaves(nodes: &[OctreeNode], node_index: usize, palette: &mut Vec<Pixel>) {
+    let node = &nodes[node_index];
+    if node.leaf {
+        if node.count > 0 {
+            palette.push(node.sum.map(|sum| sum / node.count as f64));
+        }
+        return;
+    }
+    for &child in node.children.iter().flatten() {
+        collect_leaves(nodes, child, palette);
+    }
+}
+
+fn k_means(histogram: &[(Pixel, u64)], channels: usize, colors: usize) -> Vec<Pixel> {
+    const MAX_ITERATIONS: usize = 16;
+
+    let mut centers = median_cut(histogram, channels, colors);
+    if histogram.len() <= colors {
+        return centers;
+    }
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut sums = vec![([0.0; 4], 0u64); centers.len()];
+        for (pixel, weight) in histogram {
+            let (sum, count) = &mut sums[nearest(pixel, &centers, channels)];
+            for (sum, value) in sum.iter_mut().zip(pixel) {
+                *sum += value * *weight as f64;
+            }
+            *count += weight;
+        }
+
+        let mut max_shift: f64 = 0.0;
+        for (center, (sum, count)) in centers.iter_mut().zip(sums) {
+            if count == 0 {
+                continue;
+            }
+            let new_center = sum.map(|sum| sum / count as f64);
+            max_shift = max_shift.max(distance(center, &new_center, channels));
+            *center = new_center;
+        }
+
+        if max_shift < 1e-12 {
+            break;
+        }
+    }
+
+    centers
+}
+
+fn map_nearest(pixels: &[Pixel], palette: &[Pixel], channels: usize) -> Vec<usize> {
+    let mut cache = HashMap::new();
+    pixels.iter().map(|pixel| {
+        *cache.entry(pixel_key(pixel)).or_insert_with(|| nearest(pixel, palette, channels))
+    }).collect()
+}
+
+/// Floyd–Steinberg dithering
+fn map_dithered(pixels: &[Pixel], palette: &[Pixel], channels: usize, width: usize, clamp: bool) -> Vec<usize> {
+    let mut indices = Vec::with_capacity(pixels.len());
+    let mut errors      = vec![[0.0; 4]; width + 2];
+    let mut next_errors = vec![[0.0; 4]; width + 2];
+
+    for row in pixels.chunks(width) {
+        for (x, pixel) in row.iter().enumerate() {
+            let mut value = *pixel;
+            for channel in 0..channels {
+                value[channel] += errors[x + 1][channel];
+                if clamp {
+                    value[channel] = value[channel].clamp(0.0, 1.0);
+                }
+            }
+
+            let index = nearest(&value, palette, channels);
+            indices.push(index);
+
+            let color = &palette[index];
+            for channel in 0..channels {
+                let error = value[channel] - color[channel];
+                errors[x + 2][channel]      += error * 7.0 / 16.0;
+                next_errors[x][channel]     += error * 3.0 / 16.0;
+                next_errors[x + 1][channel] += error * 5.0 / 16.0;
+                next_errors[x + 2][channel] += error * 1.0 / 16.0;
+            }
+        }
+
+        std::mem::swap(&mut errors, &mut next_errors);
+        next_errors.fill([0.0; 4]);
+    }
+
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::{Rgb, Rgba};
+
+    use super::*;
+
+    const WIDTH: u32 = 32;
+    const METHODS: [QuantizeMethod; 3] = [QuantizeMethod::MedianCut, QuantizeMethod::Octree, QuantizeMethod::KMeans];
+
+    fn indices(body: &Body) -> Vec<usize> {
+        match body.data() {
+            ChannelVariant::U8 (ColorVariant::L(indices)) => indices.iter().map(|&index| index as usize).collect(),
+            ChannelVariant::U16(ColorVariant::L(indices)) => indices.iter().map(|&index| index as usize).collect(),
+            data => panic!("unexpected indices: {} {}", data.channel_value_type(), data.color_type()),
+        }
+    }
+
+    fn test_data() -> Vec<ColorList> {
+        let count = WIDTH * 16;
+        vec![
+            ChannelVariant::U8(ColorVariant::Rgb((0..count).map(|index| Rgb([index as u8, ((index * 3) >> 2) as u8, (index >> 2) as u8 ^ 0x55])).collect())),
+            ChannelVariant::U16(ColorVariant::Rgba((0..count).map(|index| Rgba([(index * 64) as u16, u16::MAX - index as u16, (index * 977) as u16, (index % 3 * 30000) as u16])).collect())),
+            ChannelVariant::U8(ColorVariant::L((0..count).map(|index| (index * 5) as u8).collect())),
+            ChannelVariant::F32(ColorVariant::Rgb((0..count).map(|index| Rgb([index as f32 / count as f32, 0.5, 1.0 - index as f32 / count as f32])).collect())),
+        ]
+    }
+
+    #[test]
+    fn palette_size_and_indices() {
+        for data in test_data() {
+            for method in METHODS {
+                for dither in [false, true] {
+                    for colors in [1, 2, 16, 300] {
+                        let (indx, body) = quantize(&data, WIDTH, colors, method, dither).unwrap();
+                        let palette = indx.colors();
+
+                        assert!(!palette.is_empty() && palette.len() <= colors, "{method}: {} colors for {colors}", palette.len());
+                        assert_eq!(palette.channel_value_type(), data.channel_value_type());
+                        assert_eq!(palette.color_type(), data.color_type());
+
+                        let indices = indices(&body);
+                        assert_eq!(indices.len(), data.len());
+                        assert!(indices.iter().all(|&index| index < palette.len()), "{method}: index out of range");
+                        if palette.len() <= 256 {
+                            assert_eq!(body.data().channel_value_type(), crate::format::ChannelValueType::U8);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn few_colors_are_kept() {
+        let palette = [Rgb([0u8, 0, 0]), Rgb([255, 0, 0]), Rgb([0, 128, 255]), Rgb([10, 200, 30])];
+        let data = ChannelVariant::U8(ColorVariant::Rgb((0..WIDTH * 8).map(|index| palette[(index * 7 % 4) as usize]).collect()));
+
+        for method in METHODS {
+            for dither in [false, true] {
+                let (indx, body) = quantize(&data, WIDTH, 4, method, dither).unwrap();
+                let ChannelVariant::U8(ColorVariant::Rgb(colors)) = indx.colors() else {
+                    panic!("unexpected palette type");
+                };
+
+                let mapped: Vec<Rgb<u8>> = indices(&body).into_iter().map(|index| colors[index]).collect();
+                let ChannelVariant::U8(ColorVariant::Rgb(expected)) = &data else {
+                    unreachable!();
+                };
+                assert_eq!(&mapped, expected, "{method}, dither: {dither}");
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_params() {
+        let data = &test_data()[0];
+        assert!(quantize(data, WIDTH, 0, QuantizeMethod::MedianCut, false).is_err());
+        assert!(quantize(data, WIDTH, MAX_COLORS + 1, QuantizeMethod::MedianCut, false).is_err());
+        assert!(quantize(data, 0, 16, QuantizeMethod::MedianCut, true).is_err());
+    }
+
+    #[test]
+    fn index_planes() {
+        assert_eq!(planes_for_color_count(1), 1);
+        assert_eq!(planes_for_color_count(2), 1);
+        assert_eq!(planes_for_color_count(3), 2);
+        assert_eq!(planes_for_color_count(256), 8);
+        assert_eq!(planes_for_color_count(257), 9);
+        assert_eq!(planes_for_color_count(MAX_COLORS), 16);
+    }
+}
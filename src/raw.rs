@@ -0,0 +1,320 @@
+use std::{borrow::Cow, io::{Read, Write}};
+
+use flate2::{bufread::ZlibDecoder, write::ZlibEncoder, Compression, Crc};
+
+use crate::{chunks::{foot::Checksum, Anim, Body, ChunkWrite, Colr, Exif, Foot, Fram, Iccp, Indx, Layr, Levl, Meta, Thmb, Xmet, Xpkt}, error::{ReadError, ReadErrorKind, WriteError}, Head, XZIB};
+
+/// The order in which [`XZIB::write`] writes the chunks.
+const CHUNK_ORDER: [[u8; 4]; 14] = [
+    Thmb::FOURCC,
+    Iccp::FOURCC,
+    Colr::FOURCC,
+    Indx::FOURCC,
+    Meta::FOURCC,
+    Xmet::FOURCC,
+    Xpkt::FOURCC,
+    Exif::FOURCC,
+    Anim::FOURCC,
+    Body::FOURCC,
+    Fram::FOURCC,
+    Layr::FOURCC,
+    Levl::FOURCC,
+    Foot::FOURCC,
+];
+
+/// A chunk as stored in the file, with its payload still compressed if it
+/// is compressed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawChunk {
+    fourcc: [u8; 4],
+    compressed: bool,
+    data: Vec<u8>,
+}
+
+impl RawChunk {
+    /// `fourcc` in uppercase like [`Meta::FOURCC`], `data` is zlib
+    /// compressed if `compressed` is set.
+    #[inline]
+    pub fn new(fourcc: [u8; 4], compressed: bool, data: Vec<u8>) -> Self {
+        Self { fourcc, compressed, data }
+    }
+
+    /// Encodes `chunk` like [`XZIB::write`] does.
+    pub fn encode<CW: ChunkWrite>(chunk: &CW, head: &Head, compression: u32) -> Result<Self, WriteError> {
+        let compression = Compression::new(compression);
+        let mut data = Vec::new();
+
+        if compression.level() > 0 {
+            let mut encoder = ZlibEncoder::new(&mut data, compression);
+            chunk.write(head, &mut encoder)?;
+            encoder.finish()?;
+        } else {
+            chunk.write(head, &mut data)?;
+        }
+
+        Ok(Self::new(CW::FOURCC, compression.level() > 0, data))
+    }
+
+    /// The chunk type in uppercase, independent of how the size and
+    /// compression are encoded in the file.
+    #[inline]
+    pub fn fourcc(&self) -> [u8; 4] {
+        self.fourcc
+    }
+
+    #[inline]
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// The payload as stored in the file.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The payload, decompressed if needed.
+    pub fn decompressed_data(&self) -> Result<Cow<'_, [u8]>, ReadError> {
+        if !self.compressed {
+            return Ok(Cow::Borrowed(&self.data));
+        }
+
+        let mut data = Vec::new();
+        ZlibDecoder::new(&self.data[..]).read_to_end(&mut data)?;
+
+        Ok(Cow::Owned(data))
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), WriteError> {
+        let mut fourcc = self.fourcc;
+        if self.compressed {
+            fourcc[1] = fourcc[1].to_ascii_lowercase();
+        }
+
+        if self.data.len() <= u32::MAX as usize {
+            fourcc[0] = fourcc[0].to_ascii_lowercase();
+            writer.write_all(&fourcc)?;
+            writer.write_all(&(self.data.len() as u32).to_le_bytes())?;
+        } else {
+            writer.write_all(&fourcc)?;
+            writer.write_all(&(self.data.len() as u64).to_le_bytes())?;
+        }
+
+        writer.write_all(&self.data)?;
+
+        Ok(())
+    }
+}
+
+/// A file as header and raw chunks, to replace some chunks, e.g. the
+/// metadata, without decoding and encoding the others. Unknown chunks are
+/// kept as they are.
+#[derive(Debug, Clone)]
+pub struct RawXZIB {
+    head: Head,
+    chunks: Vec<RawChunk>,
+}
+
+impl RawXZIB {
+    pub fn read(reader: &mut impl Read) -> Result<Self, ReadError> {
+        let head = Head::read(reader)?;
+
+        head.validate()?;
+
+        let mut chunks = Vec::new();
+        while let Some((fourcc, chunk_size)) = XZIB::read_chunk_head(reader)? {
+            let Ok(chunk_size) = usize::try_from(chunk_size) else {
+                return Err(ReadError::with_message(
+                    ReadErrorKind::BrokenFile,
+                    format!("chunk size too big: {chunk_size}")));
+            };
+
+            let mut data = Vec::new();
+            reader.take(chunk_size as u64).read_to_end(&mut data)?;
+            if data.len() != chunk_size {
+                return Err(ReadError::with_message(
+                    ReadErrorKind::BrokenFile,
+                    format!("truncated {} chunk: {} < {chunk_size}", String::from_utf8_lossy(&fourcc), data.len())));
+            }
+
+            let compressed = fourcc[1].is_ascii_lowercase();
+            let fourcc = [
+                fourcc[0].to_ascii_uppercase(),
+                fourcc[1].to_ascii_uppercase(),
+                fourcc[2],
+                fourcc[3],
+            ];
+
+            chunks.push(RawChunk::new(fourcc, compressed, data));
+        }
+
+        Ok(Self { head, chunks })
+    }
+
+    #[inline]
+    pub fn head(&self) -> &Head {
+        &self.head
+    }
+
+    #[inline]
+    pub fn chunks(&self) -> &[RawChunk] {
+        &self.chunks
+    }
+
+    #[inline]
+    pub fn chunks_mut(&mut self) -> &mut Vec<RawChunk> {
+        &mut self.chunks
+    }
+
+    /// The first chunk of the type `fourcc`.
+    #[inline]
+    pub fn get(&self, fourcc: [u8; 4]) -> Option<&RawChunk> {
+        self.chunks.iter().find(|chunk| chunk.fourcc == fourcc)
+    }
+
+    /// Decodes the META chunk.
+    pub fn meta(&self) -> Result<Option<Meta>, ReadError> {
+        let Some(chunk) = self.get(Meta::FOURCC) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Meta::read(&chunk.decompressed_data()?)?))
+    }
+
+    /// Decodes the XMET chunk.
+    pub fn xmet(&self) -> Result<Option<Xmet>, ReadError> {
+        let Some(chunk) = self.get(Xmet::FOURCC) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Xmet::read(&chunk.decompressed_data()?)?))
+    }
+
+    /// Removes all chunks of the type `fourcc`.
+    #[inline]
+    pub fn remove(&mut self, fourcc: [u8; 4]) {
+        self.chunks.retain(|chunk| chunk.fourcc != fourcc);
+    }
+
+    /// Replaces the chunks of the same type with `chunk`. If there are none
+    /// `chunk` is inserted where [`XZIB::write`] would write it.
+    pub fn set_raw(&mut self, chunk: RawChunk) {
+        if let Some(index) = self.chunks.iter().position(|other| other.fourcc == chunk.fourcc) {
+            self.remove(chunk.fourcc);
+            self.chunks.insert(index, chunk);
+            return;
+        }
+
+        let order = |fourcc: [u8; 4]| CHUNK_ORDER.iter().position(|other| *other == fourcc);
+        let index = match order(chunk.fourcc) {
+            Some(chunk_order) => self.chunks.iter()
+                .position(|other| order(other.fourcc).is_some_and(|other_order| other_order > chunk_order))
+                .unwrap_or(self.chunks.len()),
+            None => self.chunks.iter()
+                .position(|other| other.fourcc == Foot::FOURCC)
+                .unwrap_or(self.chunks.len()),
+        };
+
+        self.chunks.insert(index, chunk);
+    }
+
+    /// Encodes `chunk` and replaces the chunks of the same type with it, see
+    /// [`RawXZIB::set_raw`].
+    #[inline]
+    pub fn set<CW: ChunkWrite>(&mut self, chunk: &CW, compression: u32) -> Result<(), WriteError> {
+        let chunk = RawChunk::encode(chunk, &self.head, compression)?;
+        self.set_raw(chunk);
+        Ok(())
+    }
+
+    /// Writes the header and the chunks. If there is a FOOT chunk it is
+    /// written last with a recomputed CRC32 checksum. This also replaces
+    /// other checksum types, because only CRC32 checksums can be computed.
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), WriteError> {
+        let mut writer = CrcWriter { writer, crc: Crc::new() };
+
+        self.head.write(&mut writer)?;
+
+        for chunk in &self.chunks {
+            if chunk.fourcc != Foot::FOURCC {
+                chunk.write(&mut writer)?;
+            }
+        }
+
+        if self.get(Foot::FOURCC).is_some() {
+            let foot = Foot::new(Checksum::Crc32(writer.crc.sum()));
+            RawChunk::encode(&foot, &self.head, 0)?.write(writer.writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Passes writes on and computes the CRC32 of the written bytes.
+struct CrcWriter<'a, W: Write> {
+    writer: &'a mut W,
+    crc: Crc,
+}
+
+impl<W: Write> Write for CrcWriter<'_, W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let len = self.writer.write(buf)?;
+        self.crc.update(&buf[..len]);
+        Ok(len)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{color::{ChannelVariant, ColorVariant, Rgb}, format::{ColorType, NumberType}};
+
+    use super::*;
+
+    #[test]
+    fn set_meta_keeps_body_and_updates_crc() {
+        let head = Head::new(NumberType::Integer, false, ColorType::Rgb, 8, 0, 16, 16).unwrap();
+        let mut xzib = XZIB::new(head);
+        *xzib.body_mut() = Some(Body::with_data(ChannelVariant::U8(ColorVariant::Rgb(
+            (0..256u32).map(|index| Rgb([index as u8, (index * 3) as u8, 7])).collect()))));
+
+        for checksum in [Checksum::Crc32(0), Checksum::Sha1(Box::new([0xab; 20]))] {
+            *xzib.foot_mut() = Some(Foot::new(checksum));
+
+            let mut bytes = Vec::new();
+            xzib.write(&mut bytes, 6).unwrap();
+
+            let mut raw = RawXZIB::read(&mut Cursor::new(&bytes)).unwrap();
+            let body = raw.get(Body::FOURCC).unwrap().clone();
+            assert!(body.is_compressed());
+
+            let mut meta = Meta::default();
+            meta.set_title("Edited");
+            raw.set(&meta, 6).unwrap();
+
+            let mut bytes = Vec::new();
+            raw.write(&mut bytes).unwrap();
+
+            let raw = RawXZIB::read(&mut Cursor::new(&bytes)).unwrap();
+            assert_eq!(raw.get(Body::FOURCC), Some(&body));
+            assert_eq!(raw.meta().unwrap().unwrap().title(), "Edited");
+            assert_eq!(raw.chunks().last().unwrap().fourcc(), Foot::FOURCC);
+
+            // fourcc, u32 size, checksum type and CRC32
+            let foot_size = 4 + 4 + 1 + 4;
+            let mut crc = Crc::new();
+            crc.update(&bytes[..bytes.len() - foot_size]);
+
+            let xzib = XZIB::read(&mut Cursor::new(&bytes)).unwrap();
+            assert_eq!(xzib.foot().unwrap().checksum(), &Checksum::Crc32(crc.sum()));
+            assert_eq!(xzib.meta().unwrap().title(), "Edited");
+        }
+    }
+}
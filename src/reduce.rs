@@ -0,0 +1,549 @@
+use std::collections::BTreeMap;
+
+use crate::{chunks::{Body, Indx}, color::{ChannelValue, ChannelVariant, Color, ColorList, ColorVariant, ColorVecDataInner, IntChannelValue, La, Rgb, Rgba}, error::InvalidParams, format::{ChannelValueType, ColorModel, ColorType, NumberType}, quantize::planes_for_color_count, Head, XZIB};
+
+/// Maximum number of colors for which an exact palette is considered.
+pub const MAX_PALETTE_COLORS: usize = 256;
+
+/// The smallest lossless representation of an image as found by [`reduce()`].
+#[derive(Debug)]
+pub struct Reduction {
+    head: Head,
+    indx: Option<Indx>,
+    body: Body,
+}
+
+impl Reduction {
+    #[inline]
+    pub fn head(&self) -> &Head {
+        &self.head
+    }
+
+    #[inline]
+    pub fn indx(&self) -> Option<&Indx> {
+        self.indx.as_ref()
+    }
+
+    #[inline]
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    #[inline]
+    pub fn into_xzib(self) -> XZIB {
+        let mut xzib = XZIB::new(self.head);
+        *xzib.indx_mut() = self.indx;
+        *xzib.body_mut() = Some(self.body);
+        xzib
+    }
+}
+
+/// Losslessly reduces the image to the smallest representation, similar to
+/// the reductions of oxipng:
+///
+/// * drop the alpha channel if every pixel is fully opaque
+/// * turn RGB into L if every pixel is gray
+/// * use an exact palette if there are at most [`MAX_PALETTE_COLORS`] colors
+/// * lower the number of planes if the low bits are unused
+///
+/// `head` describes how the image is meant to be stored (interleaved, color
+/// model, chroma subsampling, premultiplied alpha) and must not be indexed.
+/// The first two reductions are only done for the RGB color model. Chroma
+/// subsampling is lossy anyway and thus disables the last two.
+///
+/// Which one of palette and direct colors is used is decided by the estimated
+/// size of the uncompressed data.
+pub fn reduce(data: ColorList, head: &Head) -> Result<Reduction, InvalidParams> {
+    if head.is_indexed() {
+        return Err(InvalidParams::with_message("cannot reduce already indexed images"));
+    }
+
+    if data.len() != head.width() as usize * head.height() as usize {
+        return Err(InvalidParams::with_message(
+            format!("number of colors doesn't match image size: {} != {} x {}",
+                data.len(), head.width(), head.height())));
+    }
+
+    let color_model = head.color_model()?;
+    let chroma_subsampling = head.chroma_subsampling()?;
+    let interleaved = head.is_interleaved();
+
+    let mut data = data;
+    if color_model == ColorModel::Rgb {
+        if is_opaque(&data) {
+            data = drop_alpha(data);
+        }
+
+        if is_gray(&data) {
+            data = to_gray(data);
+        }
+    }
+
+    let channel_value_type = data.channel_value_type();
+    let color_type = data.color_type();
+    let number_type = channel_value_type.number_type();
+    let premultiplied = head.is_premultiplied() && matches!(color_type, ColorType::La | ColorType::Rgba);
+
+    if chroma_subsampling.is_subsampled() {
+        let planes = channel_value_type.planes();
+        let head = make_head(head, number_type, color_type, planes, 0, premultiplied)?;
+        return Ok(Reduction { head, indx: None, body: Body::with_data(data) });
+    }
+
+    let bits = channel_value_type.planes() as u32;
+    let planes = min_planes(&data, &planes_candidates(bits, interleaved));
+    let pixel_count = data.len() as u128;
+    let channels = color_type.channels() as u128;
+    let direct_size = pixel_count * channels * planes as u128;
+
+    if let Some((palette, indices)) = exact_palette(&data) {
+        let color_count = palette.len();
+        let index_bits = planes_for_color_count(color_count);
+        let index_bits = if interleaved {
+            index_bits
+        } else {
+            *planes_candidates(8, false).iter().find(|&&planes| planes >= index_bits).unwrap()
+        };
+        // the INDX chunk can't store palettes with padding bits
+        let index_candidates: Vec<u8> = planes_candidates(bits, false).into_iter().filter(|&planes| planes >= 8).collect();
+        let index_planes = min_planes(&palette, &index_candidates);
+        let palette_size = pixel_count * index_bits as u128 + color_count as u128 * channels * index_planes as u128;
+
+        if palette_size < direct_size {
+            let palette_type = palette.color_type();
+            let head = make_head(head, number_type, palette_type, index_bits, index_planes, premultiplied)?;
+            let indx = Indx::new(narrow(&palette, index_planes));
+            let body = Body::with_data(ChannelVariant::U8(ColorVariant::L(indices)));
+            return Ok(Reduction { head, indx: Some(indx), body });
+        }
+    }
+
+    let head = make_head(head, number_type, color_type, planes, 0, premultiplied)?;
+    let body = Body::with_data(narrow(&data, planes));
+
+    Ok(Reduction { head, indx: None, body })
+}
+
+fn make_head(like: &Head, number_type: NumberType, color_type: ColorType, planes: u8, index_planes: u8, premultiplied: bool) -> Result<Head, InvalidParams> {
+    let mut head = Head::new(number_type, like.is_interleaved(), color_type, planes, index_planes, like.width(), like.height())?;
+    head.set_color_model(like.color_model()?)?;
+    head.set_chroma_subsampling(like.chroma_subsampling()?)?;
+    head.set_premultiplied(premultiplied)?;
+    Ok(head)
+}
+
+/// Planes that can be used to store integer values of the given number of bits,
+/// sorted ascending.
+pub fn planes_candidates(bits: u32, interleaved: bool) -> Vec<u8> {
+    if interleaved {
+        (1..=bits as u8).collect()
+    } else {
+        [1, 4, 8, 16, 32, 64, 128].into_iter().filter(|&planes| planes as u32 <= bits).collect()
+    }
+}
+
+/// Repeats the bit pattern of a `bits` bit value until `to_bits` bits are
+/// filled, like [`IntChannelValue::extend()`].
+#[inline]
+fn repeat_bits(value: u128, bits: u32, to_bits: u32) -> u128 {
+    if bits >= to_bits {
+        return value;
+    }
+
+    let mut value = value << (to_bits - bits);
+    let mut shift = bits;
+    while shift < to_bits {
+        value |= value >> shift;
+        shift *= 2;
+    }
+    value
+}
+
+/// The value that is read back from a file for `level` written with `planes`
+/// planes, in units of `C`. A file with `planes` planes is read into the
+/// smallest channel value type with at least `planes` bits (see [`narrow()`]),
+/// which is scaled back to `C` here.
+#[inline]
+pub fn read_back<C: IntChannelValue>(level: u128, planes: u8) -> u128 {
+    let planes = planes as u32;
+    let read_bits = planes.next_power_of_two().max(8).min(C::BITS);
+    repeat_bits(repeat_bits(level, planes, read_bits), read_bits, C::BITS)
+}
+
+/// If the value survives being stored with fewer planes and then being
+/// scaled back to the full range when read.
+#[inline]
+fn is_exact<C: IntChannelValue>(value: C, planes: u8) -> bool {
+    let value = value.as_u128();
+    read_back::<C>(value >> (C::BITS - planes as u32), planes) == value
+}
+
+#[inline]
+fn is_opaque_inner<C: ChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>) -> bool {
+    match colors {
+        ColorVariant::La  (colors) => colors.iter().all(|&La([_, a])| a == C::MAX_VALUE),
+        ColorVariant::Rgba(colors) => colors.iter().all(|&Rgba([_, _, _, a])| a == C::MAX_VALUE),
+        ColorVariant::L(_) | ColorVariant::Rgb(_) => false,
+    }
+}
+
+/// If the colors have an alpha channel and every pixel is fully opaque.
+pub fn is_opaque(data: &ColorList) -> bool {
+    match data {
+        ChannelVariant::U8  (colors) => is_opaque_inner(colors),
+        ChannelVariant::U16 (colors) => is_opaque_inner(colors),
+        ChannelVariant::U32 (colors) => is_opaque_inner(colors),
+        ChannelVariant::U64 (colors) => is_opaque_inner(colors),
+        ChannelVariant::U128(colors) => is_opaque_inner(colors),
+        ChannelVariant::F32 (colors) => is_opaque_inner(colors),
+        ChannelVariant::F64 (colors) => is_opaque_inner(colors),
+    }
+}
+
+#[inline]
+fn drop_alpha_inner<C: ChannelValue>(colors: ColorVariant<C, ColorVecDataInner>) -> ColorVariant<C, ColorVecDataInner> {
+    match colors {
+        ColorVariant::La  (colors) => ColorVariant::L(colors.into_iter().map(|La([l, _])| l).collect()),
+        ColorVariant::Rgba(colors) => ColorVariant::Rgb(colors.into_iter().map(|Rgba([r, g, b, _])| Rgb([r, g, b])).collect()),
+        colors => colors,
+    }
+}
+
+/// Removes the alpha channel, if any.
+pub fn drop_alpha(data: ColorList) -> ColorList {
+    match data {
+        ChannelVariant::U8  (colors) => ChannelVariant::U8  (drop_alpha_inner(colors)),
+        ChannelVariant::U16 (colors) => ChannelVariant::U16 (drop_alpha_inner(colors)),
+        ChannelVariant::U32 (colors) => ChannelVariant::U32 (drop_alpha_inner(colors)),
+        ChannelVariant::U64 (colors) => ChannelVariant::U64 (drop_alpha_inner(colors)),
+        ChannelVariant::U128(colors) => ChannelVariant::U128(drop_alpha_inner(colors)),
+        ChannelVariant::F32 (colors) => ChannelVariant::F32 (drop_alpha_inner(colors)),
+        ChannelVariant::F64 (colors) => ChannelVariant::F64 (drop_alpha_inner(colors)),
+    }
+}
+
+#[inline]
+fn is_gray_inner<C: ChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>) -> bool {
+    match colors {
+        ColorVariant::Rgb(colors) => colors.iter().all(|&Rgb([r, g, b])| r == g && g == b),
+        _ => false,
+    }
+}
+
+/// If the colors are RGB (without alpha) and every pixel is gray.
+///
+/// Gray RGBA colors are not considered, since there is no support for LA
+/// images in the file format.
+pub fn is_gray(data: &ColorList) -> bool {
+    match data {
+        ChannelVariant::U8  (colors) => is_gray_inner(colors),
+        ChannelVariant::U16 (colors) => is_gray_inner(colors),
+        ChannelVariant::U32 (colors) => is_gray_inner(colors),
+        ChannelVariant::U64 (colors) => is_gray_inner(colors),
+        ChannelVariant::U128(colors) => is_gray_inner(colors),
+        ChannelVariant::F32 (colors) => is_gray_inner(colors),
+        ChannelVariant::F64 (colors) => is_gray_inner(colors),
+    }
+}
+
+#[inline]
+fn to_gray_inner<C: ChannelValue>(colors: ColorVariant<C, ColorVecDataInner>) -> ColorVariant<C, ColorVecDataInner> {
+    match colors {
+        ColorVariant::Rgb(colors) => ColorVariant::L(colors.into_iter().map(|Rgb([r, _, _])| r).collect()),
+        colors => colors,
+    }
+}
+
+/// Turns RGB colors into L by only keeping the red channel. Only use this if
+/// [`is_gray()`] is true.
+pub fn to_gray(data: ColorList) -> ColorList {
+    match data {
+        ChannelVariant::U8  (colors) => ChannelVariant::U8  (to_gray_inner(colors)),
+        ChannelVariant::U16 (colors) => ChannelVariant::U16 (to_gray_inner(colors)),
+        ChannelVariant::U32 (colors) => ChannelVariant::U32 (to_gray_inner(colors)),
+        ChannelVariant::U64 (colors) => ChannelVariant::U64 (to_gray_inner(colors)),
+        ChannelVariant::U128(colors) => ChannelVariant::U128(to_gray_inner(colors)),
+        ChannelVariant::F32 (colors) => ChannelVariant::F32 (to_gray_inner(colors)),
+        ChannelVariant::F64 (colors) => ChannelVariant::F64 (to_gray_inner(colors)),
+    }
+}
+
+fn min_planes_colors<C: IntChannelValue, Col: Color<C>>(colors: &[Col], candidates: &[u8]) -> u8 {
+    let mut index = 0;
+    for color in colors {
+        for &value in color.channels() {
+            while index + 1 < candidates.len() && !is_exact(value, candidates[index]) {
+                index += 1;
+            }
+        }
+        if index + 1 == candidates.len() {
+            break;
+        }
+    }
+    candidates[index]
+}
+
+#[inline]
+fn min_planes_inner<C: IntChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>, candidates: &[u8]) -> u8 {
+    match colors {
+        ColorVariant::L   (colors) => min_planes_colors(colors, candidates),
+        ColorVariant::La  (colors) => min_planes_colors(colors, candidates),
+        ColorVariant::Rgb (colors) => min_planes_colors(colors, candidates),
+        ColorVariant::Rgba(colors) => min_planes_colors(colors, candidates),
+    }
+}
+
+/// The smallest of the candidate planes (sorted ascending) that can store the
+/// integer colors without loss. The last candidate has to be the number of
+/// bits of the channel value type. Float colors always need all their bits.
+fn min_planes(data: &ColorList, candidates: &[u8]) -> u8 {
+    match data {
+        ChannelVariant::U8  (colors) => min_planes_inner(colors, candidates),
+        ChannelVariant::U16 (colors) => min_planes_inner(colors, candidates),
+        ChannelVariant::U32 (colors) => min_planes_inner(colors, candidates),
+        ChannelVariant::U64 (colors) => min_planes_inner(colors, candidates),
+        ChannelVariant::U128(colors) => min_planes_inner(colors, candidates),
+        ChannelVariant::F32 (_) => 32,
+        ChannelVariant::F64 (_) => 64,
+    }
+}
+
+#[inline]
+fn narrow_colors<C: IntChannelValue, D: IntChannelValue, ColC: Color<C>, ColD: Color<D>>(colors: &[ColC], planes: u8) -> Vec<ColD> {
+    let shift = C::BITS - planes as u32;
+    colors.iter().map(|color| {
+        let mut narrowed = ColD::default();
+        for (narrowed, &value) in narrowed.channels_mut().iter_mut().zip(color.channels()) {
+            *narrowed = D::from_u128((value >> shift).as_u128()).extend(planes);
+        }
+        narrowed
+    }).collect()
+}
+
+#[inline]
+fn narrow_variant<C: IntChannelValue, D: IntChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>, planes: u8) -> ColorVariant<D, ColorVecDataInner> {
+    match colors {
+        ColorVariant::L   (colors) => ColorVariant::L   (narrow_colors(colors, planes)),
+        ColorVariant::La  (colors) => ColorVariant::La  (narrow_colors(colors, planes)),
+        ColorVariant::Rgb (colors) => ColorVariant::Rgb (narrow_colors(colors, planes)),
+        ColorVariant::Rgba(colors) => ColorVariant::Rgba(narrow_colors(colors, planes)),
+    }
+}
+
+fn narrow_inner<C: IntChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>, planes: u8) -> ColorList {
+    match ChannelValueType::from_planes(NumberType::Integer, planes) {
+        Ok(ChannelValueType::U8)   => ChannelVariant::U8  (narrow_variant(colors, planes)),
+        Ok(ChannelValueType::U16)  => ChannelVariant::U16 (narrow_variant(colors, planes)),
+        Ok(ChannelValueType::U32)  => ChannelVariant::U32 (narrow_variant(colors, planes)),
+        Ok(ChannelValueType::U64)  => ChannelVariant::U64 (narrow_variant(colors, planes)),
+        _                          => ChannelVariant::U128(narrow_variant(colors, planes)),
+    }
+}
+
+/// Converts integer colors to the smallest channel value type that has at
+/// least `planes` bits, keeping the `planes` most significant bits and scaling
+/// them to the full range of the new type. Float colors are returned as they
+/// are.
+pub fn narrow(data: &ColorList, planes: u8) -> ColorList {
+    match data {
+        ChannelVariant::U8  (colors) => narrow_inner(colors, planes),
+        ChannelVariant::U16 (colors) => narrow_inner(colors, planes),
+        ChannelVariant::U32 (colors) => narrow_inner(colors, planes),
+        ChannelVariant::U64 (colors) => narrow_inner(colors, planes),
+        ChannelVariant::U128(colors) => narrow_inner(colors, planes),
+        data => data.clone(),
+    }
+}
+
+#[inline]
+fn color_key<C: ChannelValue, Col: Color<C>>(color: &Col, channel_key: fn(C) -> u128) -> [u128; 4] {
+    let mut key = [0; 4];
+    for (key, &value) in key.iter_mut().zip(color.channels()) {
+        *key = channel_key(value);
+    }
+    key
+}
+
+fn exact_palette_colors<C: ChannelValue, Col: Color<C>>(colors: &[Col], channel_key: fn(C) -> u128) -> Option<(Vec<Col>, Vec<u8>)> {
+    let mut palette = BTreeMap::new();
+    for color in colors {
+        let key = color_key(color, channel_key);
+        if !palette.contains_key(&key) {
+            if palette.len() == MAX_PALETTE_COLORS {
+                return None;
+            }
+            palette.insert(key, color.clone());
+        }
+    }
+
+    // sorted by color, so the result doesn't depend on the order of the pixels
+    let mut indices = BTreeMap::new();
+    for (index, key) in palette.keys().enumerate() {
+        indices.insert(*key, index as u8);
+    }
+
+    let indices = colors.iter().map(|color| indices[&color_key(color, channel_key)]).collect();
+    Some((palette.into_values().collect(), indices))
+}
+
+#[inline]
+fn exact_palette_inner<C: ChannelValue>(colors: &ColorVariant<C, ColorVecDataInner>, channel_key: fn(C) -> u128) -> Option<(ColorVariant<C, ColorVecDataInner>, Vec<u8>)> {
+    match colors {
+        ColorVariant::L   (colors) => exact_palette_colors(colors, channel_key).map(|(palette, indices)| (ColorVariant::L   (palette), indices)),
+        ColorVariant::La  (colors) => exact_palette_colors(colors, channel_key).map(|(palette, indices)| (ColorVariant::La  (palette), indices)),
+        ColorVariant::Rgb (colors) => exact_palette_colors(colors, channel_key).map(|(palette, indices)| (ColorVariant::Rgb (palette), indices)),
+        ColorVariant::Rgba(colors) => exact_palette_colors(colors, channel_key).map(|(palette, indices)| (ColorVariant::Rgba(palette), indices)),
+    }
+}
+
+/// Palette and indices if there are at most [`MAX_PALETTE_COLORS`] unique
+/// colors.
+pub fn exact_palette(data: &ColorList) -> Option<(ColorList, Vec<u8>)> {
+    match data {
+        ChannelVariant::U8  (colors) => exact_palette_inner(colors, u8::as_u128).map(|(palette, indices)| (ChannelVariant::U8  (palette), indices)),
+        ChannelVariant::U16 (colors) => exact_palette_inner(colors, u16::as_u128).map(|(palette, indices)| (ChannelVariant::U16 (palette), indices)),
+        ChannelVariant::U32 (colors) => exact_palette_inner(colors, u32::as_u128).map(|(palette, indices)| (ChannelVariant::U32 (palette), indices)),
+        ChannelVariant::U64 (colors) => exact_palette_inner(colors, u64::as_u128).map(|(palette, indices)| (ChannelVariant::U64 (palette), indices)),
+        ChannelVariant::U128(colors) => exact_palette_inner(colors, u128::as_u128).map(|(palette, indices)| (ChannelVariant::U128(palette), indices)),
+        ChannelVariant::F32 (colors) => exact_palette_inner(colors, |value| value.to_bits() as u128).map(|(palette, indices)| (ChannelVariant::F32 (palette), indices)),
+        ChannelVariant::F64 (colors) => exact_palette_inner(colors, |value| value.to_bits() as u128).map(|(palette, indices)| (ChannelVariant::F64 (palette), indices)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{color::IntChannelValue, format::ColorType, quantize::to_pixels};
+
+    use super::*;
+
+    const WIDTH: u32 = 32;
+
+    /// The colors as normalized RGBA, so that different color types compare.
+    fn rgba(data: &ColorList) -> Vec<[f64; 4]> {
+        let color_type = data.color_type();
+        to_pixels(data).into_iter().map(|pixel| match color_type {
+            ColorType::L    => [pixel[0], pixel[0], pixel[0], 1.0],
+            ColorType::La   => [pixel[0], pixel[0], pixel[0], pixel[1]],
+            ColorType::Rgb  => [pixel[0], pixel[1], pixel[2], 1.0],
+            ColorType::Rgba => pixel,
+        }).collect()
+    }
+
+    /// Reduces, writes and reads the image and checks that the colors are
+    /// unchanged, including the size of the palette. Returns the header of the
+    /// reduced image.
+    fn assert_exact(data: ColorList, interleaved: bool) -> Head {
+        let height = data.len() as u32 / WIDTH;
+        let channel_value_type = data.channel_value_type();
+        let head = Head::new(channel_value_type.number_type(), interleaved, data.color_type(), channel_value_type.planes(), 0, WIDTH, height).unwrap();
+
+        let expected = rgba(&data);
+        let reduction = reduce(data, &head).unwrap();
+        let reduced_head = *reduction.head();
+        let palette_len = reduction.indx().map(|indx| indx.colors().len());
+
+        let mut bytes = Vec::new();
+        reduction.into_xzib().write(&mut bytes, 0).unwrap();
+        let xzib = XZIB::read(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(xzib.indx().map(|indx| indx.colors().len()), palette_len);
+        let actual = rgba(&xzib.image_data().unwrap());
+
+        assert_eq!(actual.len(), expected.len());
+        for (index, (actual, expected)) in actual.iter().zip(&expected).enumerate() {
+            for (actual, expected) in actual.iter().zip(expected) {
+                assert!((actual - expected).abs() < 1e-12,
+                    "pixel {index}: {actual} != {expected}, reduced to {} {} planes, interleaved: {interleaved}",
+                    reduced_head.planes(), reduced_head.color_type().unwrap_or(ColorType::L));
+            }
+        }
+
+        reduced_head
+    }
+
+    fn gradient(count: u32) -> impl Iterator<Item = (u8, u8, u8)> {
+        (0..count).map(|index| (index as u8, (index >> 8) as u8 ^ (index as u8 >> 1), (index * 7) as u8))
+    }
+
+    #[test]
+    fn opaque_gray_palette() {
+        for interleaved in [false, true] {
+            let data = ChannelVariant::U8(ColorVariant::Rgba(
+                (0..WIDTH * 8).map(|index| {
+                    let l = (index % 5) as u8 * 60;
+                    Rgba([l, l, l, u8::MAX])
+                }).collect()));
+
+            let head = assert_exact(data, interleaved);
+            assert!(head.is_indexed());
+            assert_eq!(head.channels(), 1);
+        }
+    }
+
+    #[test]
+    fn palette_with_one_bit_values() {
+        // exact with 1 plane, but the palette must still use 8
+        let colors = [Rgba([0, 0, 0, 0]), Rgba([255, 0, 255, 255]), Rgba([255, 255, 255, 0])];
+        let data = ChannelVariant::U8(ColorVariant::Rgba(
+            (0..WIDTH * 8).map(|index| colors[index as usize % colors.len()]).collect()));
+
+        let head = assert_exact(data, true);
+        assert!(head.is_indexed());
+        assert_eq!(head.index_planes(), 8);
+    }
+
+    #[test]
+    fn direct_colors() {
+        for interleaved in [false, true] {
+            let data = ChannelVariant::U8(ColorVariant::Rgb(
+                gradient(WIDTH * 32).map(|(r, g, b)| Rgb([r, g, b])).collect()));
+
+            let head = assert_exact(data, interleaved);
+            assert!(!head.is_indexed());
+            assert_eq!(head.channels(), 3);
+            assert_eq!(head.planes(), 8);
+        }
+    }
+
+    #[test]
+    fn unused_low_bits() {
+        for interleaved in [false, true] {
+            // 8 significant bits in 16 bit values
+            let data = ChannelVariant::U16(ColorVariant::Rgba(
+                gradient(WIDTH * 32).map(|(r, g, b)| Rgba([r as u16 * 257, g as u16 * 257, b as u16 * 257, u16::MAX / 3])).collect()));
+            let head = assert_exact(data, interleaved);
+            assert_eq!(head.planes(), 8);
+            assert_eq!(head.channels(), 4);
+
+            // 4 significant bits
+            let data = ChannelVariant::U16(ColorVariant::Rgb(
+                (0..4096).map(|index| Rgb([index & 15, (index >> 4) & 15, index >> 8].map(|value| value * 0x1111))).collect()));
+            let head = assert_exact(data, interleaved);
+            assert_eq!(head.planes(), 4);
+        }
+    }
+
+    #[test]
+    fn planes_not_dividing_the_bits() {
+        // values that are exact with 7 and 12 planes in 16 bit
+        for planes in [3, 7, 12] {
+            let data = ChannelVariant::U16(ColorVariant::Rgb(
+                gradient(WIDTH * 32).map(|(r, g, b)| {
+                    let value = |value: u8| (((value as u16) << 8 | (value ^ 0x5a) as u16) >> (16 - planes)).extend(planes);
+                    Rgb([value(r), value(g), value(b)])
+                }).collect()));
+            assert_exact(data, true);
+        }
+    }
+
+    #[test]
+    fn float_colors() {
+        for interleaved in [false, true] {
+            let data = ChannelVariant::F32(ColorVariant::Rgb(
+                gradient(WIDTH * 32).map(|(r, g, b)| Rgb([r as f32 / 100.0, g as f32 / 255.0, -(b as f32)])).collect()));
+            let head = assert_exact(data, interleaved);
+            assert_eq!(head.planes(), 32);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{color::ColorList, error::InvalidParams, format::ColorType, quantize::{from_pixels, to_pixels, Pixel}};
+
+/// Filter used to resample images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    /// Average of the covered area.
+    #[default]
+    Box     = 0,
+    /// Lanczos with 3 lobes. Sharper than box filtering, but may ring at
+    /// hard edges.
+    Lanczos = 1,
+}
+
+impl Filter {
+    const BOX:     u8 = Filter::Box     as u8;
+    const LANCZOS: u8 = Filter::Lanczos as u8;
+
+    #[inline]
+    pub fn new(filter: u8) -> Option<Self> {
+        match filter {
+            Self::BOX     => Some(Self::Box),
+            Self::LANCZOS => Some(Self::Lanczos),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Filter {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Box     => "box".fmt(f),
+            Self::Lanczos => "lanczos".fmt(f),
+        }
+    }
+}
+
+/// The largest size with the aspect ratio of `width` x `height` that fits
+/// into `max_size` x `max_size`, but never larger than the image itself.
+/// Each dimension is at least 1.
+pub fn fit_size(width: u32, height: u32, max_size: u32) -> (u32, u32) {
+    if width <= max_size && height <= max_size {
+        return (width, height);
+    }
+
+    let max_size = max_size.max(1) as u64;
+    if width >= height {
+        let new_height = (height as u64 * max_size + width as u64 / 2) / width as u64;
+        (max_size as u32, new_height.max(1) as u32)
+    } else {
+        let new_width = (width as u64 * max_size + height as u64 / 2) / height as u64;
+        (new_width.max(1) as u32, max_size as u32)
+    }
+}
+
+/// Index of the alpha channel in a [`Pixel`] of the given color type.
+#[inline]
+pub fn alpha_channel(color_type: ColorType) -> Option<usize> {
+    match color_type {
+        ColorType::L    => None,
+        ColorType::La   => Some(1),
+        ColorType::Rgb  => None,
+        ColorType::Rgba => Some(3),
+    }
+}
+
+/// Shrinks the image by averaging the area of the source pixels that each
+/// destination pixel covers. Colors are weighted by their alpha so that fully
+/// transparent pixels don't bleed into their neighbours.
+pub fn downscale_pixels(pixels: &[Pixel], width: u32, height: u32, new_width: u32, new_height: u32, alpha: Option<usize>) -> Vec<Pixel> {
+    let width  = width  as usize;
+    let height = height as usize;
+    let new_width  = new_width  as usize;
+    let new_height = new_height as usize;
+
+    let scale_x = width  as f64 / new_width  as f64;
+    let scale_y = height as f64 / new_height as f64;
+
+    let mut result = Vec::with_capacity(new_width * new_height);
+
+    for new_y in 0..new_height {
+        let min_y = new_y as f64 * scale_y;
+        let max_y = min_y + scale_y;
+
+        for new_x in 0..new_width {
+            let min_x = new_x as f64 * scale_x;
+            let max_x = min_x + scale_x;
+
+            let mut sum = [0.0; 4];
+            let mut weight_sum = 0.0;
+            let mut alpha_sum = 0.0;
+
+            for y in (min_y as usize)..(max_y.ceil() as usize).min(height) {
+                let weight_y = (max_y.min(y as f64 + 1.0) - min_y.max(y as f64)).max(0.0);
+
+                for x in (min_x as usize)..(max_x.ceil() as usize).min(width) {
+                    let weight = weight_y * (max_x.min(x as f64 + 1.0) - min_x.max(x as f64)).max(0.0);
+                    let pixel = &pixels[y * width + x];
+                    let color_weight = match alpha {
+                        Some(alpha) => weight * pixel[alpha],
+                        None => weight,
+                    };
+
+                    for (channel, value) in pixel.iter().enumerate() {
+                        sum[channel] += value * if Some(channel) == alpha { weight } else { color_weight };
+                    }
+                    weight_sum += weight;
+                    alpha_sum += color_weight;
+                }
+            }
+
+            let mut pixel = [0.0; 4];
+            if weight_sum > 0.0 {
+                for (channel, value) in pixel.iter_mut().enumerate() {
+                    let divisor = if Some(channel) == alpha || alpha.is_none() { weight_sum } else { alpha_sum };
+                    if divisor > 0.0 {
+                        *value = sum[channel] / divisor;
+                    }
+                }
+            }
+            result.push(pixel);
+        }
+    }
+
+    result
+}
+
+/// Shrinks `data` of `width` x `height` to `new_width` x `new_height`, keeping
+/// its channel value type and color type. See [`downscale_pixels()`].
+pub fn downscale(data: &ColorList, width: u32, height: u32, new_width: u32, new_height: u32) -> Result<ColorList, InvalidParams> {
+    if data.len() as u64 != width as u64 * height as u64 {
+        return Err(InvalidParams::with_message(
+            format!("image of {width} x {height} has {} colors", data.len())));
+    }
+
+    if new_width == 0 || new_height == 0 || new_width > width || new_height > height {
+        return Err(InvalidParams::with_message(
+            format!("can't downscale {width} x {height} to {new_width} x {new_height}")));
+    }
+
+    let pixels = to_pixels(data);
+    let pixels = downscale_pixels(&pixels, width, height, new_width, new_height, alpha_channel(data.color_type()));
+
+    Ok(from_pixels(data, &pixels))
+}
+
+#[inline]
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = x * std::f64::consts::PI;
+        x.sin() / x
+    }
+}
+
+#[inline]
+fn lanczos3(x: f64) -> f64 {
+    if x.abs() < 3.0 {
+        sinc(x) * sinc(x / 3.0)
+    } else {
+        0.0
+    }
+}
+
+/// For each destination coordinate the source coordinates and their
+/// normalized weights. Coordinates beyond the edges are clamped.
+fn filter_weights(size: usize, new_size: usize, filter: Filter) -> Vec<Vec<(usize, f64)>> {
+    let scale = size as f64 / new_size as f64;
+
+    (0..new_size).map(|index| {
+        let mut weights: Vec<(usize, f64)> = Vec::new();
+
+        match filter {
+            Filter::Box => {
+                let min = index as f64 * scale;
+                let max = min + scale;
+                for source in (min as usize)..(max.ceil() as usize).min(size) {
+                    let weight = max.min(source as f64 + 1.0) - min.max(source as f64);
+                    if weight > 0.0 {
+                        weights.push((source, weight));
+                    }
+                }
+            }
+            Filter::Lanczos => {
+                let center = (index as f64 + 0.5) * scale;
+                let filter_scale = scale.max(1.0);
+                let support = 3.0 * filter_scale;
+                let first = (center - support).floor() as i64;
+                let last  = (center + support).ceil() as i64;
+                for source in first..=last {
+                    let weight = lanczos3((source as f64 + 0.5 - center) / filter_scale);
+                    if weight != 0.0 {
+                        let source = source.clamp(0, size as i64 - 1) as usize;
+                        match weights.last_mut() {
+                            Some((last, sum)) if *last == source => *sum += weight,
+                            _ => weights.push((source, weight)),
+                        }
+                    }
+                }
+            }
+        }
+
+        let sum: f64 = weights.iter().map(|&(_, weight)| weight).sum();
+        if sum != 0.0 {
+            for (_, weight) in &mut weights {
+                *weight /= sum;
+            }
+        }
+
+        weights
+    }).collect()
+}
+
+/// Resamples the image to `new_width` x `new_height`, first horizontally and
+/// then vertically. All channels are treated the same, so colors should be
+/// premultiplied by alpha. Lanczos filtering may produce values outside of
+/// the range of the input.
+pub fn resize_pixels(pixels: &[Pixel], width: u32, height: u32, new_width: u32, new_height: u32, filter: Filter) -> Vec<Pixel> {
+    let width  = width  as usize;
+    let height = height as usize;
+    let new_width  = new_width  as usize;
+    let new_height = new_height as usize;
+
+    let weights_x = filter_weights(width, new_width, filter);
+    let mut horizontal = Vec::with_capacity(new_width * height);
+    for y in 0..height {
+        let row = &pixels[y * width..(y + 1) * width];
+        for weights in &weights_x {
+            let mut pixel = [0.0; 4];
+            for &(x, weight) in weights {
+                for (channel, value) in pixel.iter_mut().enumerate() {
+                    *value += row[x][channel] * weight;
+                }
+            }
+            horizontal.push(pixel);
+        }
+    }
+
+    let weights_y = filter_weights(height, new_height, filter);
+    let mut result = Vec::with_capacity(new_width * new_height);
+    for weights in &weights_y {
+        for x in 0..new_width {
+            let mut pixel = [0.0; 4];
+            for &(y, weight) in weights {
+                for (channel, value) in pixel.iter_mut().enumerate() {
+                    *value += horizontal[y * new_width + x][channel] * weight;
+                }
+            }
+            result.push(pixel);
+        }
+    }
+
+    result
+}
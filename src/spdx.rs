@@ -0,0 +1,471 @@
+use crate::error::IllegalLicense;
+
+mod list;
+
+pub use list::VERSION;
+
+const MAX_DEPTH: usize = 256;
+
+/// An [SPDX license expression](https://spdx.github.io/spdx-spec/v3.0.1/annexes/spdx-license-expressions/)
+/// like `MIT OR Apache-2.0` or `GPL-2.0-or-later WITH Classpath-exception-2.0`.
+/// `AND` binds stronger than `OR`. Chains of the same operator are stored as
+/// one list of operands, so only parentheses nest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LicenseExpression {
+    /// A license ID of the SPDX license list in its canonical case, or a
+    /// custom `LicenseRef-` ID. `or_later` is set for IDs like `GPL-2.0+`.
+    License {
+        id: String,
+        or_later: bool,
+        exception: Option<String>,
+    },
+    /// At least two operands.
+    And(Vec<LicenseExpression>),
+    /// At least two operands.
+    Or(Vec<LicenseExpression>),
+}
+
+impl LicenseExpression {
+    /// Parses an expression. Operators may be all uppercase or all lowercase,
+    /// IDs are matched case-insensitively against the SPDX license list.
+    pub fn parse(expression: &str) -> Result<Self, IllegalLicense> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser { expression, tokens: &tokens, index: 0 };
+
+        let result = parser.or(0)?;
+        if let Some(token) = parser.peek() {
+            return Err(parser.error(format!("unexpected \"{token}\"")));
+        }
+
+        Ok(result)
+    }
+
+    /// All license and exception IDs used in the expression, in order.
+    pub fn ids(&self) -> Vec<&str> {
+        let mut ids = Vec::new();
+        self.collect_ids(&mut ids);
+        ids
+    }
+
+    fn collect_ids<'a>(&'a self, ids: &mut Vec<&'a str>) {
+        match self {
+            Self::License { id, exception, .. } => {
+                ids.push(id);
+                if let Some(exception) = exception {
+                    ids.push(exception);
+                }
+            }
+            Self::And(operands) | Self::Or(operands) => {
+                for operand in operands {
+                    operand.collect_ids(ids);
+                }
+            }
+        }
+    }
+
+    /// Whether any license or exception ID is deprecated by the SPDX license
+    /// list.
+    pub fn is_deprecated(&self) -> bool {
+        self.ids().into_iter().any(is_deprecated)
+    }
+}
+
+impl std::fmt::Display for LicenseExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::License { id, or_later, exception } => {
+                id.fmt(f)?;
+                if *or_later {
+                    "+".fmt(f)?;
+                }
+                if let Some(exception) = exception {
+                    write!(f, " WITH {exception}")?;
+                }
+                Ok(())
+            }
+            Self::And(operands) => {
+                for (index, operand) in operands.iter().enumerate() {
+                    if index > 0 {
+                        " AND ".fmt(f)?;
+                    }
+                    if matches!(operand, Self::Or(..)) {
+                        write!(f, "({operand})")?;
+                    } else {
+                        operand.fmt(f)?;
+                    }
+                }
+                Ok(())
+            }
+            Self::Or(operands) => {
+                for (index, operand) in operands.iter().enumerate() {
+                    if index > 0 {
+                        " OR ".fmt(f)?;
+                    }
+                    operand.fmt(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The canonical form of a license ID of the SPDX license list, e.g. `MIT`
+/// for `mit`.
+pub fn find_license(id: &str) -> Option<&'static str> {
+    find(&list::LICENSES, id)
+}
+
+/// The canonical form of an exception ID of the SPDX license list.
+pub fn find_exception(id: &str) -> Option<&'static str> {
+    find(&list::EXCEPTIONS, id)
+}
+
+/// Whether `id` is a deprecated license or exception ID, like `GPL-2.0`.
+pub fn is_deprecated(id: &str) -> bool {
+    [&list::LICENSES[..], &list::EXCEPTIONS[..]].into_iter()
+        .any(|entries| lookup(entries, id).is_some_and(|(_, deprecated)| deprecated))
+}
+
+fn lookup(entries: &[(&'static str, bool)], id: &str) -> Option<(&'static str, bool)> {
+    let lowercase = |value: &str| value.bytes().map(|byte| byte.to_ascii_lowercase()).collect::<Vec<u8>>();
+    let id = lowercase(id);
+
+    entries.binary_search_by(|(entry, _)| entry.bytes().map(|byte| byte.to_ascii_lowercase()).cmp(id.iter().copied()))
+        .ok()
+        .map(|index| entries[index])
+}
+
+#[inline]
+fn find(entries: &[(&'static str, bool)], id: &str) -> Option<&'static str> {
+    lookup(entries, id).map(|(id, _)| id)
+}
+
+#[inline]
+fn is_idstring(value: &str) -> bool {
+    !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'.')
+}
+
+/// Whether `id` is a custom ID like `LicenseRef-foo` or
+/// `DocumentRef-bar:LicenseRef-foo` with the given `prefix`.
+fn is_user_defined(id: &str, prefix: &str) -> bool {
+    let id = match id.split_once(':') {
+        Some((document, id)) => {
+            if !document.strip_prefix("DocumentRef-").is_some_and(is_idstring) {
+                return false;
+            }
+            id
+        }
+        None => id,
+    };
+
+    id.strip_prefix(prefix).is_some_and(is_idstring)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Open,
+    Close,
+    Word(&'a str),
+}
+
+impl std::fmt::Display for Token<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Open  => "(".fmt(f),
+            Self::Close => ")".fmt(f),
+            Self::Word(word) => word.fmt(f),
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token<'_>>, IllegalLicense> {
+    let mut tokens = Vec::new();
+    let mut rest = expression;
+
+    loop {
+        rest = rest.trim_start();
+        let Some(ch) = rest.chars().next() else {
+            break;
+        };
+
+        match ch {
+            '(' => {
+                tokens.push(Token::Open);
+                rest = &rest[1..];
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                rest = &rest[1..];
+            }
+            _ => {
+                let end = rest.find(|ch: char| ch.is_whitespace() || ch == '(' || ch == ')').unwrap_or(rest.len());
+                tokens.push(Token::Word(&rest[..end]));
+                rest = &rest[end..];
+            }
+        }
+    }
+
+    if tokens.is_empty() {
+        return Err(IllegalLicense::with_message("empty license expression"));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    expression: &'a str,
+    tokens: &'a [Token<'a>],
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    #[inline]
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.index).copied()
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> IllegalLicense {
+        IllegalLicense::with_message(format!("illegal license expression {:?}: {message}", self.expression))
+    }
+
+    /// Consumes the operator `name` in uppercase or lowercase.
+    fn operator(&mut self, name: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if word == name || word == name.to_ascii_lowercase() => {
+                self.index += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self, depth: usize) -> Result<LicenseExpression, IllegalLicense> {
+        let mut operands = vec![self.and(depth)?];
+        while self.operator("OR") {
+            operands.push(self.and(depth)?);
+        }
+
+        if operands.len() == 1 {
+            return Ok(operands.swap_remove(0));
+        }
+        Ok(LicenseExpression::Or(operands))
+    }
+
+    fn and(&mut self, depth: usize) -> Result<LicenseExpression, IllegalLicense> {
+        let mut operands = vec![self.primary(depth)?];
+        while self.operator("AND") {
+            operands.push(self.primary(depth)?);
+        }
+
+        if operands.len() == 1 {
+            return Ok(operands.swap_remove(0));
+        }
+        Ok(LicenseExpression::And(operands))
+    }
+
+    fn primary(&mut self, depth: usize) -> Result<LicenseExpression, IllegalLicense> {
+        let token = self.peek();
+        self.index += 1;
+
+        let word = match token {
+            Some(Token::Open) => {
+                if depth >= MAX_DEPTH {
+                    return Err(self.error("nested too deeply"));
+                }
+
+                let result = self.or(depth + 1)?;
+                if self.peek() != Some(Token::Close) {
+                    return Err(self.error("missing \")\""));
+                }
+                self.index += 1;
+
+                return Ok(result);
+            }
+            Some(Token::Word(word)) if !matches!(word, "AND" | "OR" | "WITH" | "and" | "or" | "with") => word,
+            Some(token) => return Err(self.error(format!("expected a license ID, but got \"{token}\""))),
+            None => return Err(self.error("expected a license ID at the end")),
+        };
+
+        let (id, or_later) = match word.strip_suffix('+') {
+            Some(id) => (id, true),
+            None => (word, false),
+        };
+
+        let id = if is_user_defined(id, "LicenseRef-") {
+            id.to_owned()
+        } else if let Some(id) = find_license(id) {
+            id.to_owned()
+        } else {
+            return Err(self.error(format!("unknown license ID {word:?}")));
+        };
+
+        let exception = if self.operator("WITH") {
+            let exception = match self.peek() {
+                Some(Token::Word(word)) => word,
+                _ => return Err(self.error("expected an exception ID after WITH")),
+            };
+            self.index += 1;
+
+            if is_user_defined(exception, "AdditionRef-") {
+                Some(exception.to_owned())
+            } else if let Some(exception) = find_exception(exception) {
+                Some(exception.to_owned())
+            } else {
+                return Err(self.error(format!("unknown exception ID {exception:?}")));
+            }
+        } else {
+            None
+        };
+
+        Ok(LicenseExpression::License { id, or_later, exception })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn license(id: &str, or_later: bool, exception: Option<&str>) -> LicenseExpression {
+        LicenseExpression::License {
+            id: id.to_owned(),
+            or_later,
+            exception: exception.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn lists_are_sorted() {
+        for entries in [&list::LICENSES[..], &list::EXCEPTIONS[..]] {
+            for pair in entries.windows(2) {
+                assert!(pair[0].0.to_ascii_lowercase() < pair[1].0.to_ascii_lowercase(), "{} >= {}", pair[0].0, pair[1].0);
+            }
+            for &(id, _) in entries {
+                assert_eq!(lookup(entries, id).map(|(found, _)| found), Some(id));
+            }
+        }
+    }
+
+    #[test]
+    fn lookup_ignores_case() {
+        assert_eq!(find_license("mit"), Some("MIT"));
+        assert_eq!(find_license("apache-2.0"), Some("Apache-2.0"));
+        assert_eq!(find_license("GPL-2.0-ONLY"), Some("GPL-2.0-only"));
+        assert_eq!(find_license("no-such-license"), None);
+        assert_eq!(find_exception("classpath-exception-2.0"), Some("Classpath-exception-2.0"));
+        assert_eq!(find_exception("MIT"), None);
+
+        assert!(is_deprecated("GPL-2.0"));
+        assert!(is_deprecated("gpl-2.0"));
+        assert!(!is_deprecated("GPL-2.0-only"));
+        assert!(!is_deprecated("no-such-license"));
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            LicenseExpression::parse("MIT OR Apache-2.0 AND BSD-3-Clause").unwrap(),
+            LicenseExpression::Or(vec![
+                license("MIT", false, None),
+                LicenseExpression::And(vec![
+                    license("Apache-2.0", false, None),
+                    license("BSD-3-Clause", false, None),
+                ]),
+            ]));
+
+        assert_eq!(
+            LicenseExpression::parse("(MIT OR Apache-2.0) AND BSD-3-Clause").unwrap(),
+            LicenseExpression::And(vec![
+                LicenseExpression::Or(vec![
+                    license("MIT", false, None),
+                    license("Apache-2.0", false, None),
+                ]),
+                license("BSD-3-Clause", false, None),
+            ]));
+
+        assert_eq!(
+            LicenseExpression::parse("((MIT))").unwrap(),
+            license("MIT", false, None));
+
+        assert_eq!(
+            LicenseExpression::parse("(MIT OR Apache-2.0) AND BSD-3-Clause").unwrap().to_string(),
+            "(MIT OR Apache-2.0) AND BSD-3-Clause");
+        assert_eq!(
+            LicenseExpression::parse("mit or (apache-2.0 and bsd-3-clause)").unwrap().to_string(),
+            "MIT OR Apache-2.0 AND BSD-3-Clause");
+    }
+
+    #[test]
+    fn with_and_or_later() {
+        assert_eq!(
+            LicenseExpression::parse("GPL-2.0-or-later WITH Classpath-exception-2.0").unwrap(),
+            license("GPL-2.0-or-later", false, Some("Classpath-exception-2.0")));
+
+        assert_eq!(
+            LicenseExpression::parse("gpl-2.0+ with classpath-exception-2.0").unwrap(),
+            license("GPL-2.0", true, Some("Classpath-exception-2.0")));
+
+        let parsed = LicenseExpression::parse("LGPL-2.1+ OR MIT").unwrap();
+        assert_eq!(parsed.to_string(), "LGPL-2.1+ OR MIT");
+        assert_eq!(parsed.ids(), ["LGPL-2.1", "MIT"]);
+        assert!(parsed.is_deprecated());
+
+        assert!(LicenseExpression::parse("MIT WITH").is_err());
+        assert!(LicenseExpression::parse("MIT WITH Apache-2.0").is_err());
+        assert!(LicenseExpression::parse("WITH Classpath-exception-2.0").is_err());
+    }
+
+    #[test]
+    fn user_defined_ids() {
+        assert_eq!(
+            LicenseExpression::parse("LicenseRef-my-license.1").unwrap(),
+            license("LicenseRef-my-license.1", false, None));
+
+        assert_eq!(
+            LicenseExpression::parse("DocumentRef-spdx-tool-1.2:LicenseRef-MIT-Style-2").unwrap(),
+            license("DocumentRef-spdx-tool-1.2:LicenseRef-MIT-Style-2", false, None));
+
+        assert_eq!(
+            LicenseExpression::parse("MIT WITH AdditionRef-my-exception").unwrap(),
+            license("MIT", false, Some("AdditionRef-my-exception")));
+
+        assert!(LicenseExpression::parse("LicenseRef-").is_err());
+        assert!(LicenseExpression::parse("LicenseRef-a_b").is_err());
+        assert!(LicenseExpression::parse("DocumentRef-:LicenseRef-foo").is_err());
+        assert!(LicenseExpression::parse("DocumentRef-doc:MIT").is_err());
+        assert!(LicenseExpression::parse("MIT WITH LicenseRef-foo").is_err());
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for expression in ["", "   ", "MIT AND", "AND MIT", "MIT Apache-2.0", "(MIT", "MIT)", "()", "MIT And Apache-2.0", "no-such-license"] {
+            assert!(LicenseExpression::parse(expression).is_err(), "{expression:?}");
+        }
+    }
+
+    #[test]
+    fn long_chain_does_not_nest() {
+        let expression = vec!["MIT"; 300_000].join(" AND ");
+        let parsed = LicenseExpression::parse(&expression).unwrap();
+
+        let LicenseExpression::And(operands) = &parsed else {
+            panic!("expected AND: {parsed:?}");
+        };
+        assert_eq!(operands.len(), 300_000);
+        assert_eq!(parsed.to_string(), expression);
+
+        let expression = vec!["MIT AND Apache-2.0"; 100_000].join(" OR ");
+        let parsed = LicenseExpression::parse(&expression).unwrap();
+        assert_eq!(parsed.ids().len(), 200_000);
+        assert_eq!(parsed.to_string(), expression);
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let expression = format!("{}MIT{}", "(".repeat(MAX_DEPTH + 1), ")".repeat(MAX_DEPTH + 1));
+        assert!(LicenseExpression::parse(&expression).is_err());
+
+        let expression = format!("{}MIT{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH));
+        assert!(LicenseExpression::parse(&expression).is_ok());
+    }
+}
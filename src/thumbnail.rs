@@ -0,0 +1,78 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use flate2::bufread::ZlibDecoder;
+
+use crate::{chunks::{Body, Thmb}, error::{InvalidParams, ReadError, ReadErrorKind}, format::{ChannelValueType, Format, NumberType}, quantize::{from_pixels, to_pixels}, resize::{alpha_channel, downscale_pixels, fit_size}, Head, XZIB};
+
+impl XZIB {
+    /// Reads only the THMB chunk of a file, skipping over all other chunks
+    /// without decoding them. `None` if the file has no thumbnail.
+    pub fn thumbnail<R>(reader: &mut R) -> Result<Option<Thmb>, ReadError>
+    where R: Read + Seek {
+        let head = Head::read(reader)?;
+
+        head.validate()?;
+
+        while let Some((fourcc, chunk_size)) = Self::read_chunk_head(reader)? {
+            let is_thmb =
+                fourcc[0].to_ascii_uppercase() == Thmb::FOURCC[0] &&
+                fourcc[1].to_ascii_uppercase() == Thmb::FOURCC[1] &&
+                fourcc[2..] == Thmb::FOURCC[2..];
+
+            if !is_thmb {
+                let Ok(offset) = i64::try_from(chunk_size) else {
+                    return Err(ReadError::with_message(
+                        ReadErrorKind::BrokenFile,
+                        format!("chunk size too big: {chunk_size}")));
+                };
+                reader.seek(SeekFrom::Current(offset))?;
+                continue;
+            }
+
+            let mut buf = vec![0u8; chunk_size as usize];
+            reader.read_exact(&mut buf)?;
+
+            if fourcc[1].is_ascii_lowercase() {
+                let mut decompr = Vec::new();
+                let mut decoder = ZlibDecoder::new(&buf[..]);
+                decoder.read_to_end(&mut decompr)?;
+                buf = decompr;
+            }
+
+            return Ok(Some(Thmb::read(&buf)?));
+        }
+
+        Ok(None)
+    }
+
+    /// Creates the THMB chunk from the BODY chunk, replacing any existing
+    /// thumbnail. The thumbnail keeps the aspect ratio and fits into
+    /// `max_size` x `max_size`. It is 8 bit, non-interleaved and has the
+    /// palette applied and the colors converted to RGB (or L or RGBA) with
+    /// straight alpha.
+    pub fn generate_thumbnail(&mut self, max_size: u32) -> Result<(), InvalidParams> {
+        let Some(body) = &self.body else {
+            return Err(InvalidParams::with_message("a thumbnail requires a BODY chunk"));
+        };
+
+        let data = self.body_rgb_data(body)?;
+        let color_type = data.color_type();
+        let width  = self.head.width();
+        let height = self.head.height();
+
+        if data.len() as u64 != width as u64 * height as u64 {
+            return Err(InvalidParams::with_message(
+                format!("image of {width} x {height} has {} colors", data.len())));
+        }
+
+        let (thumbnail_width, thumbnail_height) = fit_size(width, height, max_size);
+        let pixels = downscale_pixels(&to_pixels(&data), width, height, thumbnail_width, thumbnail_height, alpha_channel(color_type));
+
+        let like = Format(ChannelValueType::U8, color_type).make_color_list();
+        let head = Head::new(NumberType::Integer, false, color_type, 8, 0, thumbnail_width, thumbnail_height)?;
+
+        self.thmb = Some(Thmb::new(head, Body::with_data(from_pixels(&like, &pixels)))?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,810 @@
+use std::collections::BTreeMap;
+
+use crate::{chunks::Xmet, error::InvalidParams, lang::is_language_tag};
+
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+const DC_NS:  &str = Xmet::DUBLIN_CORE;
+
+/// Namespaces used for keys without an `xmlns:` entry.
+const WELL_KNOWN_NAMESPACES: [(&str, &str); 9] = [
+    ("dc",            DC_NS),
+    ("xmp",           "http://ns.adobe.com/xap/1.0/"),
+    ("xmpRights",     "http://ns.adobe.com/xap/1.0/rights/"),
+    ("xmpMM",         "http://ns.adobe.com/xap/1.0/mm/"),
+    ("photoshop",     "http://ns.adobe.com/photoshop/1.0/"),
+    ("exif",          "http://ns.adobe.com/exif/1.0/"),
+    ("tiff",          "http://ns.adobe.com/tiff/1.0/"),
+    ("Iptc4xmpCore",  "http://iptc.org/std/Iptc4xmpCore/1.0/xmlns/"),
+    ("darktable",     "http://darktable.sf.net/"),
+];
+
+const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArrayKind {
+    Bag,
+    Seq,
+    Alt,
+}
+
+impl ArrayKind {
+    #[inline]
+    fn name(self) -> &'static str {
+        match self {
+            Self::Bag => "rdf:Bag",
+            Self::Seq => "rdf:Seq",
+            Self::Alt => "rdf:Alt",
+        }
+    }
+
+    /// The array type of Dublin Core properties as defined by XMP.
+    fn of_dublin_core(property: &str) -> Option<Self> {
+        match property {
+            "creator" | "date" => Some(Self::Seq),
+            "contributor" | "language" | "publisher" | "relation" | "subject" | "type" => Some(Self::Bag),
+            "title" | "description" | "rights" => Some(Self::Alt),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn text(&self) -> String {
+        let mut text = String::new();
+        for node in &self.children {
+            if let Node::Text(value) = node {
+                text.push_str(value);
+            }
+        }
+        text
+    }
+}
+
+#[inline]
+fn is_xml_whitespace(ch: char) -> bool {
+    matches!(ch, ' ' | '\t' | '\r' | '\n')
+}
+
+/// Whether `name` is usable as a namespace prefix or as a local name.
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+
+    (first.is_alphabetic() || first == '_') && chars.all(|ch| ch.is_alphanumeric() || matches!(ch, '-' | '.' | '_'))
+}
+
+fn decode_entities(text: &str) -> Result<String, String> {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(index) = rest.find('&') {
+        decoded.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+
+        let Some(end) = rest.find(';') else {
+            return Err("unterminated entity reference".to_owned());
+        };
+        let entity = &rest[..end];
+        rest = &rest[end + 1..];
+
+        let ch = match entity {
+            "lt"   => '<',
+            "gt"   => '>',
+            "amp"  => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok()
+                } else {
+                    None
+                };
+
+                let Some(ch) = code.and_then(char::from_u32) else {
+                    return Err(format!("unknown entity: &{entity};"));
+                };
+                ch
+            }
+        };
+        decoded.push(ch);
+    }
+    decoded.push_str(rest);
+
+    Ok(decoded)
+}
+
+fn escape(text: &str, out: &mut String) {
+    for ch in text.chars() {
+        match ch {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+/// A minimal XML parser, enough for RDF/XML. DTDs are skipped, not
+/// interpreted.
+struct Parser<'a> {
+    input: &'a str,
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    #[inline]
+    fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> InvalidParams {
+        InvalidParams::with_message(format!("illegal XML at byte {}: {message}", self.offset))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start_matches(is_xml_whitespace).len();
+    }
+
+    /// Skips past the next `end` and returns the text before it.
+    fn skip_past(&mut self, end: &str) -> Result<&'a str, InvalidParams> {
+        let rest = self.rest();
+        let Some(index) = rest.find(end) else {
+            return Err(self.error(format!("missing {end:?}")));
+        };
+        self.offset += index + end.len();
+
+        Ok(&rest[..index])
+    }
+
+    /// Skips whitespace, comments, processing instructions and doctype
+    /// declarations.
+    fn skip_misc(&mut self) -> Result<(), InvalidParams> {
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<!DOCTYPE") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str, InvalidParams> {
+        let rest = self.rest();
+        let len = rest.find(|ch: char| is_xml_whitespace(ch) || matches!(ch, '/' | '>' | '<' | '=' | '"' | '\'')).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+        self.offset += len;
+
+        Ok(&rest[..len])
+    }
+
+    fn document(&mut self) -> Result<Element, InvalidParams> {
+        if self.rest().starts_with('\u{FEFF}') {
+            self.offset += '\u{FEFF}'.len_utf8();
+        }
+
+        self.skip_misc()?;
+        let root = self.element(0)?;
+        self.skip_misc()?;
+
+        if !self.rest().is_empty() {
+            return Err(self.error("content after the root element"));
+        }
+
+        Ok(root)
+    }
+
+    fn element(&mut self, depth: usize) -> Result<Element, InvalidParams> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(format!("elements nested deeper than {MAX_DEPTH}")));
+        }
+
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expected an element"));
+        }
+        self.offset += 1;
+
+        let name = self.name()?.to_owned();
+        let mut attributes = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.offset += 2;
+                return Ok(Element { name, attributes, children: Vec::new() });
+            }
+            if rest.starts_with('>') {
+                self.offset += 1;
+                break;
+            }
+
+            let attribute = self.name()?.to_owned();
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.error(format!("expected '=' after attribute {attribute}")));
+            }
+            self.offset += 1;
+            self.skip_whitespace();
+
+            let quote = match self.rest().chars().next() {
+                Some('"')  => "\"",
+                Some('\'') => "'",
+                _ => return Err(self.error(format!("expected a quoted value for attribute {attribute}"))),
+            };
+            self.offset += 1;
+
+            let value = self.skip_past(quote)?;
+            if value.contains('<') {
+                return Err(self.error(format!("'<' in the value of attribute {attribute}")));
+            }
+            let value = decode_entities(value).map_err(|message| self.error(message))?;
+            attributes.push((attribute, value));
+        }
+
+        let mut children = Vec::new();
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.offset += 2;
+                let end = self.name()?;
+                if end != name {
+                    return Err(self.error(format!("expected </{name}>, but found </{end}>")));
+                }
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') {
+                    return Err(self.error(format!("expected '>' after </{name}")));
+                }
+                self.offset += 1;
+
+                return Ok(Element { name, attributes, children });
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.offset += "<![CDATA[".len();
+                let text = self.skip_past("]]>")?;
+                children.push(Node::Text(text.to_owned()));
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with('<') {
+                children.push(Node::Element(self.element(depth + 1)?));
+            } else if rest.is_empty() {
+                return Err(self.error(format!("unclosed element <{name}>")));
+            } else {
+                let len = rest.find('<').unwrap_or(rest.len());
+                let text = decode_entities(&rest[..len]).map_err(|message| self.error(message))?;
+                self.offset += len;
+                children.push(Node::Text(text));
+            }
+        }
+    }
+}
+
+/// Namespace declarations in scope, innermost last. The empty prefix is the
+/// default namespace.
+type Namespaces<'a> = Vec<(&'a str, &'a str)>;
+
+/// Values of a property with their language, `None` for untagged values.
+type LocalizedValues<'a> = Vec<(Option<&'a str>, &'a String)>;
+
+fn push_namespaces<'a>(element: &'a Element, namespaces: &mut Namespaces<'a>) {
+    for (name, value) in &element.attributes {
+        if name == "xmlns" {
+            namespaces.push(("", value));
+        } else if let Some(prefix) = name.strip_prefix("xmlns:") {
+            namespaces.push((prefix, value));
+        }
+    }
+}
+
+/// Resolves a qualified name to prefix, namespace and local name. Attributes
+/// without a prefix have no namespace.
+fn resolve<'a>(name: &'a str, namespaces: &Namespaces<'a>, is_attribute: bool) -> Option<(&'a str, &'a str, &'a str)> {
+    let (prefix, local) = match name.split_once(':') {
+        Some((prefix, local)) => (prefix, local),
+        None if is_attribute => return None,
+        None => ("", name),
+    };
+
+    if prefix == "xml" {
+        return Some((prefix, XML_NS, local));
+    }
+
+    namespaces.iter().rev()
+        .find(|(declared, _)| *declared == prefix)
+        .map(|&(_, namespace)| (prefix, namespace, local))
+}
+
+#[inline]
+fn is_rdf(name: Option<(&str, &str, &str)>, local: &str) -> bool {
+    matches!(name, Some((_, RDF_NS, name_local)) if name_local == local)
+}
+
+/// The values of a property element with the language of `rdf:Alt` items,
+/// `None` for structured values.
+fn property_values<'a>(property: &'a Element, namespaces: &mut Namespaces<'a>) -> Option<Vec<(Option<&'a str>, String)>> {
+    for (name, value) in &property.attributes {
+        if is_rdf(resolve(name, namespaces, true), "resource") {
+            return Some(vec![(None, value.clone())]);
+        }
+    }
+
+    let mut elements = property.elements();
+    let Some(container) = elements.next() else {
+        return Some(vec![(None, property.text())]);
+    };
+
+    if elements.next().is_some() {
+        return None;
+    }
+
+    let len = namespaces.len();
+    push_namespaces(container, namespaces);
+
+    let name = resolve(&container.name, namespaces, false);
+    let is_alt = is_rdf(name, "Alt");
+    let is_array = is_alt || is_rdf(name, "Bag") || is_rdf(name, "Seq");
+    let is_structured = |item: &Element| item.elements().next().is_some() ||
+        item.attributes.iter().any(|(name, _)| name != "xml:lang");
+    let lang = |item: &'a Element| if is_alt {
+        item.attributes.iter()
+            .find(|(name, _)| name == "xml:lang")
+            .map(|(_, lang)| lang.as_str())
+    } else {
+        None
+    };
+
+    let values = if is_array && !container.elements().any(is_structured) {
+        Some(container.elements().map(|item| (lang(item), item.text())).collect())
+    } else {
+        None
+    };
+
+    namespaces.truncate(len);
+
+    values
+}
+
+/// The XMET key of a property, adding an `xmlns:` entry for its namespace if
+/// needed.
+fn property_key(data: &mut BTreeMap<String, Vec<String>>, prefix: &str, namespace: &str, local: &str) -> String {
+    if namespace == DC_NS {
+        return local.to_owned();
+    }
+
+    let declared = data.iter()
+        .find(|(key, values)| key.starts_with("xmlns:") && values.first().map(String::as_str) == Some(namespace))
+        .map(|(key, _)| key["xmlns:".len()..].to_owned());
+
+    let prefix = match declared {
+        Some(prefix) => prefix,
+        None => {
+            let prefix = if is_xml_name(prefix) && !data.contains_key(&format!("xmlns:{prefix}")) {
+                prefix.to_owned()
+            } else {
+                (1..).map(|index| format!("ns{index}"))
+                    .find(|prefix| !data.contains_key(&format!("xmlns:{prefix}")))
+                    .unwrap_or_default()
+            };
+            data.insert(format!("xmlns:{prefix}"), vec![namespace.to_owned()]);
+            prefix
+        }
+    };
+
+    format!("{prefix}:{local}")
+}
+
+fn read_description<'a>(description: &'a Element, namespaces: &mut Namespaces<'a>, data: &mut BTreeMap<String, Vec<String>>) {
+    for (name, value) in &description.attributes {
+        if name == "xmlns" || name.starts_with("xmlns:") {
+            continue;
+        }
+
+        let Some((prefix, namespace, local)) = resolve(name, namespaces, true) else {
+            continue;
+        };
+
+        if namespace != RDF_NS && namespace != XML_NS {
+            let key = property_key(data, prefix, namespace, local);
+            data.entry(key).or_default().push(value.clone());
+        }
+    }
+
+    for property in description.elements() {
+        let len = namespaces.len();
+        push_namespaces(property, namespaces);
+
+        let name = resolve(&property.name, namespaces, false);
+        let values = match name {
+            Some((_, namespace, _)) if namespace != RDF_NS => property_values(property, namespaces),
+            _ => None,
+        };
+
+        if let (Some((prefix, namespace, local)), Some(values)) = (name, values) {
+            let key = property_key(data, prefix, namespace, local);
+            for (lang, value) in values {
+                // x-default is the value for readers without language support
+                let key = match lang {
+                    Some(lang) if is_language_tag(lang) && !lang.eq_ignore_ascii_case("x-default") => format!("{key}@{lang}"),
+                    _ => key.clone(),
+                };
+                data.entry(key).or_default().push(value);
+            }
+        }
+
+        namespaces.truncate(len);
+    }
+}
+
+fn read_descriptions<'a>(element: &'a Element, namespaces: &mut Namespaces<'a>, data: &mut BTreeMap<String, Vec<String>>) {
+    let len = namespaces.len();
+    push_namespaces(element, namespaces);
+
+    if is_rdf(resolve(&element.name, namespaces, false), "Description") {
+        read_description(element, namespaces, data);
+    } else {
+        for child in element.elements() {
+            read_descriptions(child, namespaces, data);
+        }
+    }
+
+    namespaces.truncate(len);
+}
+
+impl Xmet {
+    /// Serializes the entries as an XMP packet. Keys without a prefix are
+    /// Dublin Core properties. Prefixed keys use the namespace of their
+    /// `xmlns:` entry, or a well known namespace like `xmp` or `exif`.
+    /// Multiple values become an `rdf:Bag`, Dublin Core properties use the
+    /// array types defined by XMP. Keys with language variants like
+    /// `title@de` become an `rdf:Alt` with the untagged value as
+    /// `x-default`.
+    pub fn to_xmp(&self) -> Result<String, InvalidParams> {
+        let data = self.data();
+        let keys: Vec<&String> = data.keys()
+            .filter(|key| !key.starts_with("xmlns:"))
+            .collect();
+
+        let mut namespaces: Vec<(&str, &str)> = Vec::new();
+        let mut properties: Vec<(&str, &str, Option<ArrayKind>, LocalizedValues)> = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            let values = &data[key];
+            if values.is_empty() {
+                continue;
+            }
+
+            let (name, lang) = match key.rsplit_once('@') {
+                Some((name, lang)) => (name, Some(lang)),
+                None => (key.as_str(), None),
+            };
+
+            if lang.is_some_and(|lang| !is_language_tag(lang)) {
+                return Err(InvalidParams::with_message(
+                    format!("illegal language tag in key: {key:?}")));
+            }
+
+            if lang.is_some() && values.len() > 1 {
+                return Err(InvalidParams::with_message(
+                    format!("language variant {key:?} has to have exactly one value, but has: {values:?}")));
+            }
+
+            let (prefix, local) = name.split_once(':').unwrap_or(("dc", name));
+            if !is_xml_name(prefix) || !is_xml_name(local) {
+                return Err(InvalidParams::with_message(
+                    format!("key can't be used as an XML name: {key:?}")));
+            }
+
+            if matches!(prefix, "xml" | "xmlns" | "rdf") {
+                return Err(InvalidParams::with_message(
+                    format!("reserved namespace prefix in key: {key:?}")));
+            }
+
+            let namespace = match self.namespace(prefix) {
+                Some(namespace) => namespace,
+                None => match WELL_KNOWN_NAMESPACES.iter().find(|(known, _)| *known == prefix) {
+                    Some(&(_, namespace)) => namespace,
+                    None => return Err(InvalidParams::with_message(
+                        format!("no xmlns:{prefix} entry for key: {key:?}"))),
+                },
+            };
+
+            if !namespaces.iter().any(|&(used, _)| used == prefix) {
+                namespaces.push((prefix, namespace));
+            }
+
+            let items = values.iter().map(|value| (lang, value));
+            match properties.iter_mut().find(|(other_prefix, other_local, _, _)| (*other_prefix, *other_local) == (prefix, local)) {
+                Some((_, _, _, property_items)) => property_items.extend(items),
+                None => {
+                    let kind = if namespace == DC_NS {
+                        ArrayKind::of_dublin_core(local)
+                    } else {
+                        None
+                    };
+                    properties.push((prefix, local, kind, items.collect()));
+                }
+            }
+        }
+
+        for (prefix, local, kind, items) in &mut properties {
+            let localized = items.iter().any(|(lang, _)| lang.is_some());
+            if localized && items.iter().filter(|(lang, _)| lang.is_none()).count() > 1 {
+                return Err(InvalidParams::with_message(
+                    format!("{prefix}:{local} has language variants, but more than one untagged value")));
+            }
+
+            // the untagged value is the x-default and comes first
+            items.sort_by_key(|(lang, _)| lang.is_some());
+
+            *kind = match *kind {
+                _ if localized => Some(ArrayKind::Alt),
+                Some(ArrayKind::Alt) if items.len() > 1 => Some(ArrayKind::Bag),
+                None if items.len() > 1 => Some(ArrayKind::Bag),
+                kind => kind,
+            };
+        }
+
+        let mut xmp = String::new();
+        xmp.push_str("<?xpacket begin=\"\u{FEFF}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n");
+        xmp.push_str("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n");
+        xmp.push_str(" <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n");
+        xmp.push_str("  <rdf:Description rdf:about=\"\"");
+        for (prefix, namespace) in &namespaces {
+            xmp.push_str("\n    xmlns:");
+            xmp.push_str(prefix);
+            xmp.push_str("=\"");
+            escape(namespace, &mut xmp);
+            xmp.push('"');
+        }
+        xmp.push_str(">\n");
+
+        for (prefix, local, kind, items) in properties {
+            let name = format!("{prefix}:{local}");
+            match kind {
+                None => {
+                    xmp.push_str(&format!("   <{name}>"));
+                    escape(items[0].1, &mut xmp);
+                    xmp.push_str(&format!("</{name}>\n"));
+                }
+                Some(kind) => {
+                    xmp.push_str(&format!("   <{name}>\n    <{}>\n", kind.name()));
+                    for (lang, value) in items {
+                        xmp.push_str("     <rdf:li");
+                        if kind == ArrayKind::Alt {
+                            xmp.push_str(" xml:lang=\"");
+                            escape(lang.unwrap_or("x-default"), &mut xmp);
+                            xmp.push('"');
+                        }
+                        xmp.push('>');
+                        escape(value, &mut xmp);
+                        xmp.push_str("</rdf:li>\n");
+                    }
+                    xmp.push_str(&format!("    </{}>\n   </{name}>\n", kind.name()));
+                }
+            }
+        }
+
+        xmp.push_str("  </rdf:Description>\n");
+        xmp.push_str(" </rdf:RDF>\n");
+        xmp.push_str("</x:xmpmeta>\n");
+        xmp.push_str("<?xpacket end=\"w\"?>");
+
+        Ok(xmp)
+    }
+
+    /// Reads the properties of all `rdf:Description` elements of an XMP
+    /// packet or sidecar file. Dublin Core properties become keys without a
+    /// prefix, other namespaces get an `xmlns:` entry. Array items become
+    /// multiple values, `rdf:Alt` items with a language other than
+    /// `x-default` become keys like `title@de`. Structured values are skipped.
+    pub fn from_xmp(xmp: &str) -> Result<Self, InvalidParams> {
+        let root = Parser { input: xmp, offset: 0 }.document()?;
+
+        let mut data = BTreeMap::new();
+        read_descriptions(&root, &mut Vec::new(), &mut data);
+
+        Ok(Self::new(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(xmet: &Xmet) -> Vec<(&str, Vec<&str>)> {
+        xmet.data().iter().map(|(key, values)| (key.as_str(), values.iter().map(String::as_str).collect())).collect()
+    }
+
+    #[test]
+    fn darktable_sidecar() {
+        let xmp = "\u{FEFF}<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<x:xmpmeta xmlns:x=\"adobe:ns:meta/\" x:xmptk=\"XMP Core 4.4.0-Exiv2\">
+ <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">
+  <rdf:Description rdf:about=\"\"
+    xmlns:exif=\"http://ns.adobe.com/exif/1.0/\"
+    xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"
+    xmlns:xmpMM=\"http://ns.adobe.com/xap/1.0/mm/\"
+    xmlns:dc=\"http://purl.org/dc/elements/1.1/\"
+    xmlns:darktable=\"http://darktable.sf.net/\"
+   exif:DateTimeOriginal=\"2023:08:12 14:03:11\"
+   xmp:Rating=\"3\"
+   xmpMM:DerivedFrom=\"IMG_0001.CR2\"
+   darktable:history_end=\"2\">
+   <dc:subject>
+    <rdf:Bag>
+     <rdf:li>beach</rdf:li>
+     <rdf:li>sunset</rdf:li>
+    </rdf:Bag>
+   </dc:subject>
+   <darktable:history>
+    <rdf:Seq>
+     <rdf:li
+      darktable:num=\"0\"
+      darktable:operation=\"exposure\"
+      darktable:enabled=\"1\"
+      darktable:params=\"0000000000000000\"/>
+     <rdf:li darktable:num=\"1\" darktable:operation=\"colorin\" darktable:enabled=\"1\"/>
+    </rdf:Seq>
+   </darktable:history>
+   <darktable:masks_history>
+    <rdf:Seq/>
+   </darktable:masks_history>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+";
+        // the structured history and the empty masks history are skipped
+        let xmet = Xmet::from_xmp(xmp).unwrap();
+        assert_eq!(entries(&xmet), [
+            ("darktable:history_end", vec!["2"]),
+            ("exif:DateTimeOriginal", vec!["2023:08:12 14:03:11"]),
+            ("subject", vec!["beach", "sunset"]),
+            ("xmlns:darktable", vec!["http://darktable.sf.net/"]),
+            ("xmlns:exif", vec!["http://ns.adobe.com/exif/1.0/"]),
+            ("xmlns:xmp", vec!["http://ns.adobe.com/xap/1.0/"]),
+            ("xmlns:xmpMM", vec!["http://ns.adobe.com/xap/1.0/mm/"]),
+            ("xmp:Rating", vec!["3"]),
+            ("xmpMM:DerivedFrom", vec!["IMG_0001.CR2"]),
+        ]);
+    }
+
+    #[test]
+    fn language_alternatives() {
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+            <rdf:Description xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:title><rdf:Alt>
+                    <rdf:li xml:lang="x-default">Sunset</rdf:li>
+                    <rdf:li xml:lang="de">Sonnenuntergang</rdf:li>
+                    <rdf:li xml:lang="fr-CA">Coucher de soleil</rdf:li>
+                </rdf:Alt></dc:title>
+                <dc:description><rdf:Alt><rdf:li xml:lang="en">Only English</rdf:li></rdf:Alt></dc:description>
+                <dc:rights><rdf:Alt><rdf:li xml:lang="not a tag">CC0-1.0</rdf:li></rdf:Alt></dc:rights>
+            </rdf:Description>
+        </rdf:RDF></x:xmpmeta>"#;
+
+        let xmet = Xmet::from_xmp(xmp).unwrap();
+        assert_eq!(entries(&xmet), [
+            ("description@en", vec!["Only English"]),
+            ("rights", vec!["CC0-1.0"]),
+            ("title", vec!["Sunset"]),
+            ("title@de", vec!["Sonnenuntergang"]),
+            ("title@fr-CA", vec!["Coucher de soleil"]),
+        ]);
+        assert_eq!(xmet.title_for("de-AT"), Some("Sonnenuntergang"));
+        assert_eq!(xmet.title_for("ja"), Some("Sunset"));
+    }
+
+    #[test]
+    fn entities_cdata_and_nested_namespaces() {
+        let xmp = r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+            <rdf:Description xmlns:my="http://example.com/a/" my:label="a &amp; b &#x263A;&#65;">
+                <dc:source xmlns:dc="http://purl.org/dc/elements/1.1/">&lt;x&gt; &quot;q&apos; <![CDATA[<raw> & stuff]]><!-- comment --></dc:source>
+                <my:link rdf:resource="http://example.com/?a=1&amp;b=2"/>
+            </rdf:Description>
+            <rdf:Description xmlns:my="http://example.com/b/">
+                <my:other>value</my:other>
+            </rdf:Description>
+        </rdf:RDF>"#;
+
+        let xmet = Xmet::from_xmp(xmp).unwrap();
+        assert_eq!(entries(&xmet), [
+            ("my:label", vec!["a & b \u{263A}A"]),
+            ("my:link", vec!["http://example.com/?a=1&b=2"]),
+            ("ns1:other", vec!["value"]),
+            ("source", vec!["<x> \"q' <raw> & stuff"]),
+            ("xmlns:my", vec!["http://example.com/a/"]),
+            ("xmlns:ns1", vec!["http://example.com/b/"]),
+        ]);
+    }
+
+    #[test]
+    fn malformed_xml() {
+        let deep = format!("{}{}", "<a>".repeat(MAX_DEPTH + 2), "</a>".repeat(MAX_DEPTH + 2));
+        for xmp in [
+            "",
+            "text",
+            "<a>",
+            "<a></b>",
+            "<a b=c/>",
+            "<a b=\"<\"/>",
+            "<a b=\"c/>",
+            "<a>&unknown;</a>",
+            "<a>&amp</a>",
+            "<a>&#xD800;</a>",
+            "<a/><b/>",
+            "<a><!-- </a>",
+            "<a><![CDATA[</a>",
+            "<a></a",
+            &deep,
+        ] {
+            assert!(Xmet::from_xmp(xmp).is_err(), "{xmp:?}");
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let entries = [
+            ("title", vec!["Title"]),
+            ("title@de", vec!["Titel"]),
+            ("creator", vec!["Alice", "Bob"]),
+            ("subject", vec!["one"]),
+            ("date", vec!["2024-05-06"]),
+            ("rights", vec!["MIT"]),
+            ("description", vec!["<b>&\"'</b>\n  two lines"]),
+            ("xmlns:my", vec!["http://example.com/my/"]),
+            ("my:single", vec!["value"]),
+            ("my:list", vec!["a", "b", "c"]),
+        ];
+        let xmet = Xmet::new(entries.into_iter()
+            .map(|(key, values)| (key.to_owned(), values.into_iter().map(str::to_owned).collect()))
+            .collect());
+
+        let xmp = xmet.to_xmp().unwrap();
+        assert_eq!(Xmet::from_xmp(&xmp).unwrap().data(), xmet.data());
+
+        // well known prefixes get an xmlns: entry when read back
+        let mut xmet = Xmet::default();
+        xmet.data_mut().insert("xmp:Rating".to_owned(), vec!["5".to_owned()]);
+        let read = Xmet::from_xmp(&xmet.to_xmp().unwrap()).unwrap();
+        assert_eq!(read.get("xmp:Rating"), Some(&["5".to_owned()][..]));
+        assert_eq!(read.namespace("xmp"), Some("http://ns.adobe.com/xap/1.0/"));
+
+        xmet.data_mut().insert("undeclared:key".to_owned(), vec!["value".to_owned()]);
+        assert!(xmet.to_xmp().is_err());
+    }
+}